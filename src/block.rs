@@ -1,16 +1,18 @@
 //use nom::branch::*;
 use nom::IResult;
 use nom::bytes::complete::*;
-use nom::character::complete::*;
-use nom::multi::*;
 use nom::sequence::*; 
 use nom::combinator::*;
 
+use crate::comment::multispace0;
 use crate::statement::{ Statement, parse_statement };
-use crate::expression::{ Expression, parse_expression };
+use crate::expression::{ Expression, parse_expression, parse_block_like_expression };
+use crate::identifier::Tag;
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
+use crate::lowering::BlockTarget;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 pub struct Block {
@@ -22,9 +24,18 @@ impl GenType for Block {
     fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
         equs.into_scope();
         for s in self.statements.iter() {
-            let _exp = s.gen_type(equs, trs)?;
+            let before = equs.set_span(s.get_span());
+            let res = s.gen_type(equs, trs);
+            equs.set_span(before);
+            res?;
         }
-        let res = self.return_exp.as_ref().map_or(Ok(Type::from_str("void")), |exp| exp.gen_type(equs, trs));
+        let empty_type = if self.diverges() { Type::Never } else { Type::from_str("void") };
+        let res = self.return_exp.as_ref().map_or(Ok(empty_type), |exp| {
+            let before = equs.set_span(exp.get_span());
+            let res = exp.gen_type(equs, trs);
+            equs.set_span(before);
+            res
+        });
         equs.out_scope();
         res
     }
@@ -32,11 +43,31 @@ impl GenType for Block {
 
 impl Transpile for Block {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let statements = self.statements.iter().map(|s| format!("{};\n", s.transpile(ta))).collect::<Vec<_>>().join("");
-        let return_trans = match self.return_exp {
-            Some(Expression::IfExpr(ref ifexpr)) => format!("{};\n", ifexpr.transpile_for_return(ta)),
-            Some(ref return_exp) => format!("return {};\n", return_exp.transpile(ta)),
-            None => format!(""),
+        self.transpile_to(ta, &BlockTarget::Return)
+    }
+}
+
+impl Block {
+    pub fn ends_with_return(&self) -> bool {
+        self.return_exp.is_none() && matches!(self.statements.last(), Some(Statement::Return(..)))
+    }
+    pub fn diverges(&self) -> bool {
+        self.return_exp.is_none() && matches!(self.statements.last(), Some(Statement::Return(..)) | Some(Statement::Break(..)) | Some(Statement::Continue(..)))
+    }
+    pub fn transpile_for_void(&self, ta: &TypeAnnotation) -> String {
+        self.transpile_to(ta, &BlockTarget::Void)
+    }
+    pub fn transpile_to(&self, ta: &TypeAnnotation, target: &BlockTarget) -> String {
+        let statements = self.statements.iter().map(|s| ta.lowering.statement(|| format!("{};\n", s.transpile(ta)))).collect::<Vec<_>>().join("");
+        let return_trans = match (&self.return_exp, target) {
+            (Some(Expression::IfExpr(ref ifexpr)), _) if !ifexpr.is_void(ta) => ifexpr.transpile_to(ta, target),
+            (Some(Expression::MatchExpr(ref matchexpr)), _) if !matchexpr.is_void(ta) => matchexpr.transpile_to(ta, target),
+            (Some(Expression::LoopExpr(ref loopexpr)), BlockTarget::Return) => format!("{};\n", loopexpr.transpile_for_return(ta)),
+            (Some(ref return_exp @ Expression::IfExpr(_)), _) | (Some(ref return_exp @ Expression::MatchExpr(_)), _)
+                | (Some(ref return_exp @ Expression::ForExpr(_)), _) | (Some(ref return_exp @ Expression::WhileExpr(_)), _) | (Some(ref return_exp @ Expression::ForInExpr(_)), _)
+                | (Some(ref return_exp), BlockTarget::Void) => ta.lowering.statement(|| format!("{};\n", return_exp.transpile(ta))),
+            (Some(ref return_exp), _) => ta.lowering.statement(|| target.wrap(return_exp.transpile(ta))),
+            (None, _) => String::new(),
         };
         format!("{}{}", statements, return_trans)
     }
@@ -54,18 +85,41 @@ impl MutCheck for Block {
     }
 }
 
+impl Resolve for Block {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.statements.resolve(scope)?;
+        self.return_exp.resolve(scope)
+    }
+}
+
 
 pub fn parse_block(s: &str) -> IResult<&str, Block> {
-    let (s, (vec, _, return_exp, _)) = tuple((many0(tuple((multispace0, parse_statement, multispace0, tag(";")))), multispace0, opt(parse_expression), multispace0))(s)?;
     let mut statements = Vec::new();
-    for (_, st, _, _) in vec {
-        statements.push(st);
+    let mut s = s;
+    loop {
+        let (rest, _) = multispace0(s)?;
+        if let Ok((rest, (expr, _, semicolon))) = tuple((parse_block_like_expression, multispace0, opt(tag(";"))))(rest) {
+            if semicolon.is_none() && (rest.is_empty() || rest.starts_with('}')) {
+                return Ok((rest, Block { statements, return_exp: Some(expr) }));
+            }
+            let span = expr.get_span();
+            statements.push(Statement::Expression(expr, Tag::with_span(span)));
+            s = rest;
+        }
+        else if let Ok((rest, (st, _, _))) = tuple((parse_statement, multispace0, tag(";")))(rest) {
+            statements.push(st);
+            s = rest;
+        }
+        else {
+            let (rest, (return_exp, _)) = tuple((opt(parse_expression), multispace0))(rest)?;
+            return Ok((rest, Block { statements, return_exp }));
+        }
     }
-    Ok((s, Block { statements, return_exp,}))
 }
 
 #[test]
 fn parse_block_test() {
     println!("{:?}", parse_block("let x = 0; let y = 91; let z = 1333; func(x * x, y, z);"));
     println!("{:?}", parse_block("let x = 0; let y = 91; let z = 1333; func(x * x, y, z)"));
+    println!("{:?}", parse_block("if x { y = 1; } while z { z = false; } { w } x"));
 }
@@ -0,0 +1,68 @@
+use nom::IResult;
+use nom::branch::*;
+use nom::bytes::complete::*;
+use nom::character::complete::{ char, not_line_ending };
+use nom::combinator::*;
+use nom::multi::*;
+use nom::sequence::*;
+use nom::error::{ Error, ErrorKind };
+
+fn line_comment(s: &str) -> IResult<&str, &str> {
+    recognize(tuple((tag("//"), not(tuple((char('/'), not(char('/'))))), not_line_ending)))(s)
+}
+
+fn block_comment(s: &str) -> IResult<&str, &str> {
+    let (mut rest, _) = tag("/*")(s)?;
+    let mut depth = 1;
+    while depth > 0 {
+        if let Some(r) = rest.strip_prefix("*/") {
+            depth -= 1;
+            rest = r;
+        }
+        else if let Some(r) = rest.strip_prefix("/*") {
+            depth += 1;
+            rest = r;
+        }
+        else if let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+        }
+        else {
+            return Err(nom::Err::Error(Error::new(s, ErrorKind::TakeUntil)));
+        }
+    }
+    Ok((rest, &s[..s.len() - rest.len()]))
+}
+
+fn space_or_comment(s: &str) -> IResult<&str, &str> {
+    alt((nom::character::complete::multispace1, line_comment, block_comment))(s)
+}
+
+pub fn multispace0(s: &str) -> IResult<&str, &str> {
+    recognize(many0_count(space_or_comment))(s)
+}
+
+pub fn multispace1(s: &str) -> IResult<&str, &str> {
+    recognize(many1_count(space_or_comment))(s)
+}
+
+fn doc_comment(s: &str) -> IResult<&str, String> {
+    let (s, (_, _, doc)) = tuple((tag("///"), not(char('/')), not_line_ending))(s)?;
+    Ok((s, doc.to_string()))
+}
+
+pub fn parse_doc_comments(s: &str) -> IResult<&str, Vec<String>> {
+    many0(terminated(doc_comment, multispace0))(s)
+}
+
+pub fn transpile_doc_comments(doc: &[String]) -> String {
+    doc.iter().map(|line| format!("///{}\n", line)).collect()
+}
+
+#[test]
+fn parse_comment_test() {
+    assert_eq!(multispace0("  // comment\n  /* block /* nested */ */ x"), Ok(("x", "  // comment\n  /* block /* nested */ */ ")));
+    assert_eq!(multispace0("//// not doc\nx"), Ok(("x", "//// not doc\n")));
+    assert_eq!(multispace0("/// doc\nx"), Ok(("/// doc\nx", "")));
+    assert!(multispace1("/* unterminated").is_err());
+    assert_eq!(parse_doc_comments("/// first\n// plain\n///second\nfn"), Ok(("fn", vec![" first".to_string(), "second".to_string()])));
+}
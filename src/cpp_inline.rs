@@ -11,6 +11,7 @@ use crate::identifier::{ Identifier, parse_identifier, Tag };
 use crate::unify::*;
 use crate::trans::*;
 use crate::type_spec::*;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug, Clone)]
 pub struct CppInline {
@@ -72,6 +73,15 @@ impl CppInline {
     }
 }
 
+impl Resolve for CppInline {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.inlines.iter_mut().try_for_each(|inline| match inline {
+            CppInlineElem::Type(tyid) => tyid.resolve(scope),
+            _ => Ok(()),
+        })
+    }
+}
+
 fn parse_inline_elem_type(s: &str) -> IResult<&str, CppInlineElem> {
     let (s, (_, _, id, _, _)) = tuple((tag("$ty("), multispace0, parse_type_id, multispace0, tag(")")))(s)?;
     Ok((s, CppInlineElem::Type(id)))
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+
+#[derive(Debug)]
+struct SourceFile {
+    path: PathBuf,
+    text: String,
+    furthest: usize,
+}
+
+thread_local! {
+    static SOURCES: RefCell<Vec<SourceFile>> = const { RefCell::new(Vec::new()) };
+    static CURRENT_SOURCE: RefCell<Option<(usize, usize)>> = const { RefCell::new(None) };
+    static TAG_SPANS: RefCell<HashMap<usize, Span>> = RefCell::new(HashMap::new());
+}
+
+pub fn regist_source(path: &Path, text: &str) -> usize {
+    SOURCES.with(|sources| {
+        let mut sources = sources.borrow_mut();
+        sources.push(SourceFile { path: path.to_path_buf(), text: text.to_string(), furthest: 0 });
+        sources.len() - 1
+    })
+}
+
+pub fn set_current_source(file: Option<usize>) -> Option<usize> {
+    let len = file.map(|file| SOURCES.with(|sources| sources.borrow()[file].text.len()));
+    CURRENT_SOURCE.with(|current| {
+        let before = current.replace(file.zip(len));
+        before.map(|(file, _)| file)
+    })
+}
+
+pub fn regist_tag_span(tag: usize, span: Span) {
+    if !span.is_empty() {
+        TAG_SPANS.with(|spans| spans.borrow_mut().insert(tag, span));
+    }
+}
+
+pub fn tag_span(tag: usize) -> Span {
+    TAG_SPANS.with(|spans| spans.borrow().get(&tag).cloned().unwrap_or_else(Span::empty))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: Option<usize>,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn empty() -> Self {
+        Span { file: None, start: 0, end: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file.is_none()
+    }
+
+    pub fn between(before: &str, after: &str) -> Self {
+        CURRENT_SOURCE.with(|current| match *current.borrow() {
+            Some((file, len)) if before.len() <= len => {
+                let span = Span { file: Some(file), start: len - before.len(), end: len - after.len().min(before.len()) };
+                SOURCES.with(|sources| {
+                    let source = &mut sources.borrow_mut()[file];
+                    source.furthest = source.furthest.max(span.end);
+                });
+                span
+            }
+            _ => Span::empty(),
+        })
+    }
+
+    pub fn merge(&self, right: &Span) -> Self {
+        match (self.file, right.file) {
+            (Some(l), Some(r)) if l == r => Span { file: Some(l), start: self.start.min(right.start), end: self.end.max(right.end) },
+            (Some(_), _) => *self,
+            _ => *right,
+        }
+    }
+
+    pub fn source_stem(&self) -> Option<String> {
+        let file = self.file?;
+        SOURCES.with(|sources| sources.borrow().get(file)?.path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+    }
+
+    fn location(&self) -> Option<(PathBuf, usize, usize, String, usize)> {
+        let file = self.file?;
+        SOURCES.with(|sources| {
+            let sources = sources.borrow();
+            let source = sources.get(file)?;
+            let start = self.start.min(source.text.len());
+            let line_start = source.text[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source.text[start..].find('\n').map_or(source.text.len(), |i| start + i);
+            let line = source.text[..start].matches('\n').count() + 1;
+            let column = source.text[line_start..start].chars().count() + 1;
+            let width = source.text[start..self.end.min(line_end).max(start)].trim_end().chars().count().max(1);
+            Some((source.path.clone(), line, column, source.text[line_start..line_end].to_string(), width))
+        })
+    }
+
+    pub fn error_message(&self, message: &str) -> String {
+        match self.location() {
+            Some((path, line, column, text, width)) => {
+                let num = line.to_string();
+                let pad = " ".repeat(num.len());
+                let indent = text.chars().take(column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+                format!("{}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+                        message, pad, path.display(), line, column, pad, num, text, pad, indent, "^".repeat(width))
+            }
+            None => message.to_string(),
+        }
+    }
+}
+
+pub fn parse_error_message(file: usize, remaining: &str) -> String {
+    let (text, furthest) = SOURCES.with(|sources| {
+        let source = &sources.borrow()[file];
+        (source.text.clone(), source.furthest)
+    });
+    let start = furthest.max(text.len() - remaining.len().min(text.len()));
+    let rest = &text[start..];
+    let start = start + rest.len() - rest.trim_start().len();
+    let end = start + text[start..].chars().next().map_or(0, |c| c.len_utf8());
+    Span { file: Some(file), start, end }.error_message("parse error: unexpected token")
+}
+
+#[test]
+fn span_error_message_test() {
+    let file = regist_source(Path::new("test.niu"), "fn main() -> void {\n    let x = y;\n}\n");
+    let before = set_current_source(Some(file));
+    let s = "fn main() -> void {\n    let x = y;\n}\n";
+    let span = Span::between(&s[32..], &s[33..]);
+    set_current_source(before);
+    assert_eq!(span.error_message("error"), "error\n --> test.niu:2:13\n  |\n2 |     let x = y;\n  |             ^");
+}
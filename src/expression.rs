@@ -1,5 +1,11 @@
 pub mod if_expr;
 pub mod for_expr;
+pub mod match_expr;
+pub mod while_expr;
+pub mod loop_expr;
+pub mod for_in_expr;
+pub mod range_expr;
+pub mod closure_expr;
 
 //use nom::branch::*;
 use nom::IResult;
@@ -8,19 +14,33 @@ use nom::multi::*;
 use nom::sequence::*; 
 use nom::bytes::complete::*;
 use nom::branch::*;
+use nom::combinator::*;
 
-use crate::unary_expr::{ UnaryExpr, parse_unary_expr };
+use crate::comment::{ multispace0, multispace1 };
+use crate::unary_expr::{ UnaryExpr, parse_unary_expr, parse_bracket_block };
 use crate::identifier::*;
 use crate::traits::*;
+use crate::type_id::is_primitive_number;
+use crate::type_spec::*;
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::lowering::temporary_name;
+use crate::module::{ ModuleScope, Resolve };
 
 pub use if_expr::*;
 pub use for_expr::*;
+pub use match_expr::*;
+pub use while_expr::*;
+pub use loop_expr::*;
+pub use for_in_expr::*;
+pub use range_expr::*;
+pub use closure_expr::*;
 
 fn expr_gen_type<'a, EI: Iterator<Item=Type>, O: 'a, OI: Iterator<Item=&'a O>, F: Fn(&O) -> (&'static str, &'static str)>
-(equs: &mut TypeEquations, mut exprs: EI, opes: OI, f: F, tag: Tag) -> TResult {
+(equs: &mut TypeEquations, mut exprs: EI, opes: OI, f: F, tag: Tag, span: Span) -> TResult {
+    let before = equs.set_span(span);
     let ty = exprs.next().unwrap();
     let mut left = tag.generate_type_variable("Operators", 0, equs);
     equs.add_equation(ty, left.clone());
@@ -38,22 +58,64 @@ fn expr_gen_type<'a, EI: Iterator<Item=Type>, O: 'a, OI: Iterator<Item=&'a O>, F
         left = tag.generate_type_variable("Operators", cnt + 1, equs);
         equs.add_equation(next_ty, left.clone());
     }
+    equs.set_span(before);
     Ok(left)
 }
 
+// C++ promotes u8 operands to int, so results are narrowed back after every operator
+fn transpile_operators<O: Transpile>(ta: &TypeAnnotation, terms: Vec<String>, opes: &[O], tag: &Tag) -> String {
+    let mut terms = terms.into_iter();
+    let mut res = terms.next().unwrap();
+    for (i, (term, ope)) in terms.zip(opes.iter()).enumerate() {
+        res = format!("{}{}{}", res, ope.transpile(ta), term);
+        if ta.try_annotation(tag.get_num(), "Operators", i + 1) == Some(Type::from_str("u8")) {
+            res = format!("static_cast<std::uint8_t>({})", res);
+        }
+    }
+    res
+}
+
 #[derive(Debug)]
 pub enum Expression {
     IfExpr(Box<IfExpr>),
     ForExpr(Box<ForExpr>),
+    MatchExpr(Box<MatchExpr>),
+    WhileExpr(Box<WhileExpr>),
+    LoopExpr(Box<LoopExpr>),
+    ForInExpr(Box<ForInExpr>),
+    RangeExpr(Box<RangeExpr>),
+    ClosureExpr(Box<ClosureExpr>),
     Expression(ExpOr),
 }
 
+impl Expression {
+    pub fn get_span(&self) -> Span {
+        match *self {
+            Expression::Expression(ref e) => e.span,
+            Expression::IfExpr(ref ifexpr) => ifexpr.as_ref().get_span(),
+            Expression::ForExpr(ref forexpr) => forexpr.as_ref().span,
+            Expression::MatchExpr(ref matchexpr) => matchexpr.as_ref().get_span(),
+            Expression::WhileExpr(ref whileexpr) => whileexpr.as_ref().span,
+            Expression::LoopExpr(ref loopexpr) => loopexpr.as_ref().get_span(),
+            Expression::ForInExpr(ref forin) => forin.as_ref().get_span(),
+            Expression::RangeExpr(ref range) => range.as_ref().get_span(),
+            Expression::ClosureExpr(ref closure) => closure.as_ref().get_span(),
+        }
+    }
+}
+
 impl GenType for Expression {
     fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
         match *self {
             Expression::Expression(ref e) => e.gen_type(equs, trs),
             Expression::IfExpr(ref ifexpr) => ifexpr.as_ref().gen_type(equs, trs),
             Expression::ForExpr(ref forexpr) => forexpr.as_ref().gen_type(equs, trs),
+            Expression::MatchExpr(ref matchexpr) => matchexpr.as_ref().gen_type(equs, trs),
+            Expression::WhileExpr(ref whileexpr) => whileexpr.as_ref().gen_type(equs, trs),
+            Expression::LoopExpr(ref loopexpr) => loopexpr.as_ref().gen_type(equs, trs),
+            Expression::ForInExpr(ref forin) => forin.as_ref().gen_type(equs, trs),
+            Expression::RangeExpr(ref range) => range.as_ref().gen_type(equs, trs),
+            Expression::ClosureExpr(ref closure) => closure.as_ref().gen_type(equs, trs),
         }
     }
 }
@@ -64,6 +126,12 @@ impl Transpile for Expression {
             Expression::Expression(ref e) => e.transpile(ta),
             Expression::IfExpr(ref ifexpr) => ifexpr.as_ref().transpile(ta),
             Expression::ForExpr(ref forexpr) => forexpr.as_ref().transpile(ta),
+            Expression::MatchExpr(ref matchexpr) => matchexpr.as_ref().transpile(ta),
+            Expression::WhileExpr(ref whileexpr) => whileexpr.as_ref().transpile(ta),
+            Expression::LoopExpr(ref loopexpr) => loopexpr.as_ref().transpile(ta),
+            Expression::ForInExpr(ref forin) => forin.as_ref().transpile(ta),
+            Expression::RangeExpr(ref range) => range.as_ref().transpile(ta),
+            Expression::ClosureExpr(ref closure) => closure.as_ref().transpile(ta),
         }
     }
 }
@@ -74,6 +142,28 @@ impl MutCheck for Expression {
             Expression::Expression(ref e) => e.mut_check(ta, vars),
             Expression::IfExpr(ref ifexpr) => ifexpr.as_ref().mut_check(ta, vars),
             Expression::ForExpr(ref forexpr) => forexpr.as_ref().mut_check(ta, vars),
+            Expression::MatchExpr(ref matchexpr) => matchexpr.as_ref().mut_check(ta, vars),
+            Expression::WhileExpr(ref whileexpr) => whileexpr.as_ref().mut_check(ta, vars),
+            Expression::LoopExpr(ref loopexpr) => loopexpr.as_ref().mut_check(ta, vars),
+            Expression::ForInExpr(ref forin) => forin.as_ref().mut_check(ta, vars),
+            Expression::RangeExpr(ref range) => range.as_ref().mut_check(ta, vars),
+            Expression::ClosureExpr(ref closure) => closure.as_ref().mut_check(ta, vars),
+        }
+    }
+}
+
+impl Resolve for Expression {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        match *self {
+            Expression::Expression(ref mut e) => e.resolve(scope),
+            Expression::IfExpr(ref mut ifexpr) => ifexpr.resolve(scope),
+            Expression::ForExpr(ref mut forexpr) => forexpr.resolve(scope),
+            Expression::MatchExpr(ref mut matchexpr) => matchexpr.resolve(scope),
+            Expression::WhileExpr(ref mut whileexpr) => whileexpr.resolve(scope),
+            Expression::LoopExpr(ref mut loopexpr) => loopexpr.resolve(scope),
+            Expression::ForInExpr(ref mut forin) => forin.resolve(scope),
+            Expression::RangeExpr(ref mut range) => range.resolve(scope),
+            Expression::ClosureExpr(ref mut closure) => closure.resolve(scope),
         }
     }
 }
@@ -83,6 +173,7 @@ where
     P::Child: ParseExpression,
     P::Operator: ParseOperator,
 {
+    let start = s;
     let (s, (head, _, tails)) = 
         tuple((P::Child::parse_expression, multispace0, many0(tuple((P::Operator::parse_operator, multispace0, P::Child::parse_expression, multispace0)))))(s)?;
     let mut terms = vec![head];
@@ -92,13 +183,13 @@ where
         terms.push(term);
         opes.push(ope);
     }
-    Ok((s, P::new_expr(terms, opes)))
+    Ok((s, P::new_expr(terms, opes, Span::between(start, s))))
 }
 
 trait ParseExpression: Sized {
     type Child: Sized;
     type Operator: Sized;
-    fn new_expr(childs: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self;
+    fn new_expr(childs: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self;
     fn parse_expression(s: &str) -> IResult<&str, Self>;
 }
 
@@ -106,10 +197,27 @@ trait ParseOperator: Sized {
     fn parse_operator(s: &str) -> IResult<&str, Self>;
 }
 
+fn transpile_short_circuit<T: Transpile>(ta: &TypeAnnotation, terms: &[T], is_and: bool, tag: &Tag) -> String {
+    let ope = if is_and { "&&" } else { "||" };
+    let first = terms[0].transpile(ta);
+    let rest = terms[1..].iter().map(|term| ta.lowering.capture(|| term.transpile(ta))).collect::<Vec<_>>();
+    if rest.iter().all(|(prelude, _)| prelude.is_empty()) {
+        return std::iter::once(first).chain(rest.into_iter().map(|(_, term)| term)).collect::<Vec<_>>().join(ope)
+    }
+    let tmp = temporary_name(tag.get_num());
+    let cond = if is_and { tmp } else { format!("!{}", tmp) };
+    ta.lowering.temporary(tag.get_num(), "bool".to_string(), |target| {
+        let assigns = rest.into_iter().map(|(prelude, term)| format!("if({}) {{\n{}{}}}\n", cond, prelude, target.wrap(term))).collect::<Vec<_>>().join("");
+        format!("{}{}", target.wrap(first), assigns)
+    })
+}
+
 #[derive(Debug)]
 pub struct ExpOr {
     pub terms: Vec<ExpAnd>,
     pub opes: Vec<OperatorOr>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpOr {
@@ -117,7 +225,9 @@ impl GenType for ExpOr {
         if self.terms.len() > 1 {
             for t in self.terms.iter() {
                 let ty = t.gen_type(equs, trs)?;
+                let before = equs.set_span(t.span);
                 equs.add_equation(ty, Type::from_str("bool"));
+                equs.set_span(before);
             }
             Ok(Type::from_str("bool"))
         }
@@ -132,12 +242,7 @@ pub struct OperatorOr();
 
 impl Transpile for ExpOr {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.terms.len() {
-            res.push_str(&self.terms[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        transpile_short_circuit(ta, &self.terms, false, &self.tag)
     }
 }
 
@@ -150,8 +255,8 @@ impl Transpile for OperatorOr {
 impl ParseExpression for ExpOr {
     type Child = ExpAnd;
     type Operator = OperatorOr;
-    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { terms, opes }
+    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { terms, opes, span, tag: Tag::new() }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
         default_parse_expression::<Self>(s)
@@ -179,10 +284,18 @@ impl MutCheck for ExpOr {
     }
 }
 
+impl Resolve for ExpOr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub struct ExpAnd {
     pub terms: Vec<ExpOrd>,
     pub opes: Vec<OperatorAnd>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpAnd {
@@ -190,7 +303,9 @@ impl GenType for ExpAnd {
         if self.terms.len() > 1 {
             for t in self.terms.iter() {
                 let ty = t.gen_type(equs, trs)?;
+                let before = equs.set_span(t.span);
                 equs.add_equation(ty, Type::from_str("bool"));
+                equs.set_span(before);
             }
             Ok(Type::from_str("bool"))
         }
@@ -205,12 +320,7 @@ pub struct OperatorAnd();
 
 impl Transpile for ExpAnd {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.terms.len() {
-            res.push_str(&self.terms[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        transpile_short_circuit(ta, &self.terms, true, &self.tag)
     }
 }
 
@@ -223,8 +333,8 @@ impl Transpile for OperatorAnd {
 impl ParseExpression for ExpAnd {
     type Child = ExpOrd;
     type Operator = OperatorAnd;
-    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { terms, opes }
+    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { terms, opes, span, tag: Tag::new() }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
         default_parse_expression::<Self>(s)
@@ -252,20 +362,33 @@ impl MutCheck for ExpAnd {
     }
 }
 
+impl Resolve for ExpAnd {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 
 #[derive(Debug)]
 pub struct ExpOrd {
     pub terms: Vec<ExpBitOr>,
     pub ope: Option<OperatorOrd>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpOrd {
     fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
         match self.ope {
-            Some(_) => {
+            Some(ref ope) => {
                 let t0 = self.terms[0].gen_type(equs, trs)?;
                 let t1 = self.terms[1].gen_type(equs, trs)?;
-                equs.add_equation(t0, t1);
+                let before = equs.set_span(self.span);
+                let alpha = self.tag.generate_not_void_type_variable("OrdType", 0, equs);
+                equs.add_equation(alpha.clone(), t0);
+                equs.add_equation(alpha.clone(), t1);
+                equs.add_has_trait(alpha, TraitGenerics { trait_id: TraitId::from_str(ope.comparison_trait()), generics: Vec::new() });
+                equs.set_span(before);
                 Ok(Type::from_str("bool"))
             }
             None => self.terms[0].gen_type(equs, trs),
@@ -287,12 +410,38 @@ pub enum OperatorOrd {
 impl Transpile for ExpOrd {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
         match self.ope {
-            Some(ref o) => format!("{} {} {}", self.terms[0].transpile(ta), o.transpile(ta), self.terms[1].transpile(ta)),
+            Some(ref o) => {
+                let ty = ta.annotation(self.tag.get_num(), "OrdType", 0);
+                let terms = ta.lowering.operands(&self.terms, |term| term.transpile(ta));
+                let (left, right) = (&terms[0], &terms[1]);
+                if is_primitive_number(&ty) || ty == Type::from_str("bool") {
+                    format!("{} {} {}", left, o.transpile(ta), right)
+                }
+                else {
+                    match *o {
+                        OperatorOrd::Equal => format!("{} == {}", left, right),
+                        OperatorOrd::NotEq => format!("!({} == {})", left, right),
+                        OperatorOrd::Less => format!("{} < {}", left, right),
+                        OperatorOrd::Greater => format!("{} < {}", right, left),
+                        OperatorOrd::Leq => format!("!({} < {})", right, left),
+                        OperatorOrd::Grq => format!("!({} < {})", left, right),
+                    }
+                }
+            }
             None => self.terms[0].transpile(ta),
         }
     }
 }
 
+impl OperatorOrd {
+    fn comparison_trait(&self) -> &'static str {
+        match *self {
+            OperatorOrd::Equal | OperatorOrd::NotEq => "Eq",
+            _ => "Ord",
+        }
+    }
+}
+
 impl Transpile for OperatorOrd {
     fn transpile(&self, _: &TypeAnnotation) -> String {
         match *self {
@@ -309,12 +458,12 @@ impl Transpile for OperatorOrd {
 impl ParseExpression for ExpOrd {
     type Child = ExpBitOr;
     type Operator = OperatorOrd;
-    fn new_expr(terms: Vec<Self::Child>, mut opes: Vec<Self::Operator>) -> Self {
+    fn new_expr(terms: Vec<Self::Child>, mut opes: Vec<Self::Operator>, span: Span) -> Self {
         if terms.len() == 1 && opes.len() == 0 {
-            Self { terms, ope: None }
+            Self { terms, ope: None, span, tag: Tag::new() }
         }
         else if terms.len() == 2 && opes.len() == 1 {
-            Self { terms, ope: Some(opes.remove(0)) }
+            Self { terms, ope: Some(opes.remove(0)), span, tag: Tag::new() }
         }
         else {
             unreachable!();
@@ -355,10 +504,18 @@ impl MutCheck for ExpOrd {
     }
 }
 
+impl Resolve for ExpOrd {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub struct ExpBitOr {
     pub terms: Vec<ExpBitXor>,
     pub opes: Vec<OperatorBitOr>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpBitOr {
@@ -366,7 +523,7 @@ impl GenType for ExpBitOr {
         let exprs = self.terms.iter().map(|e| e.gen_type(equs, trs)).collect::<Result<Vec<_>, _>>()?;
         expr_gen_type(equs, exprs.into_iter(), self.opes.iter(), |ope| match *ope {
                 OperatorBitOr() => ("BitOr", "operator|"),
-            }, Tag::new())
+            }, self.tag.clone(), self.span)
     }
 }
 
@@ -375,12 +532,8 @@ pub struct OperatorBitOr();
 
 impl Transpile for ExpBitOr {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.terms.len() {
-            res.push_str(&self.terms[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        let terms = ta.lowering.operands(&self.terms, |term| term.transpile(ta));
+        transpile_operators(ta, terms, &self.opes, &self.tag)
     }
 }
 
@@ -393,8 +546,8 @@ impl Transpile for OperatorBitOr {
 impl ParseExpression for ExpBitOr {
     type Child = ExpBitXor;
     type Operator = OperatorBitOr;
-    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { terms, opes }
+    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { terms, opes, span, tag: Tag::new() }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
         default_parse_expression::<Self>(s)
@@ -403,7 +556,7 @@ impl ParseExpression for ExpBitOr {
 
 impl ParseOperator for OperatorBitOr {
     fn parse_operator(s: &str) -> IResult<&str, Self> {
-        let (s, _) = char('|')(s)?;
+        let (s, _) = terminated(char('|'), not(char('|')))(s)?;
         Ok((s, OperatorBitOr()))
     }
 }
@@ -422,10 +575,18 @@ impl MutCheck for ExpBitOr {
     }
 }
 
+impl Resolve for ExpBitOr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub struct ExpBitXor {
     pub terms: Vec<ExpBitAnd>,
     pub opes: Vec<OperatorBitXor>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpBitXor {
@@ -433,7 +594,7 @@ impl GenType for ExpBitXor {
         let exprs = self.terms.iter().map(|e| e.gen_type(equs, trs)).collect::<Result<Vec<_>, _>>()?;
         expr_gen_type(equs, exprs.into_iter(), self.opes.iter(), |ope| match *ope {
                 OperatorBitXor() => ("BitXor", "operator^"),
-            }, Tag::new())
+            }, self.tag.clone(), self.span)
     }
 }
 
@@ -442,12 +603,8 @@ pub struct OperatorBitXor();
 
 impl Transpile for ExpBitXor {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.terms.len() {
-            res.push_str(&self.terms[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        let terms = ta.lowering.operands(&self.terms, |term| term.transpile(ta));
+        transpile_operators(ta, terms, &self.opes, &self.tag)
     }
 }
 
@@ -460,8 +617,8 @@ impl Transpile for OperatorBitXor {
 impl ParseExpression for ExpBitXor {
     type Child = ExpBitAnd;
     type Operator = OperatorBitXor;
-    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { terms, opes }
+    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { terms, opes, span, tag: Tag::new() }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
         default_parse_expression::<Self>(s)
@@ -489,10 +646,18 @@ impl MutCheck for ExpBitXor {
     }
 }
 
+impl Resolve for ExpBitXor {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub struct ExpBitAnd {
     pub terms: Vec<ExpShift>,
     pub opes: Vec<OperatorBitAnd>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpBitAnd {
@@ -500,7 +665,7 @@ impl GenType for ExpBitAnd {
         let exprs = self.terms.iter().map(|e| e.gen_type(equs, trs)).collect::<Result<Vec<_>, _>>()?;
         expr_gen_type(equs, exprs.into_iter(), self.opes.iter(), |ope| match *ope {
                 OperatorBitAnd() => ("BitAnd", "operator&"),
-            }, Tag::new())
+            }, self.tag.clone(), self.span)
     }
 
 }
@@ -510,12 +675,8 @@ pub struct OperatorBitAnd();
 
 impl Transpile for ExpBitAnd {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.terms.len() {
-            res.push_str(&self.terms[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        let terms = ta.lowering.operands(&self.terms, |term| term.transpile(ta));
+        transpile_operators(ta, terms, &self.opes, &self.tag)
     }
 }
 
@@ -528,8 +689,8 @@ impl Transpile for OperatorBitAnd {
 impl ParseExpression for ExpBitAnd {
     type Child = ExpShift;
     type Operator = OperatorBitAnd;
-    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { terms, opes }
+    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { terms, opes, span, tag: Tag::new() }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
         default_parse_expression::<Self>(s)
@@ -538,7 +699,7 @@ impl ParseExpression for ExpBitAnd {
 
 impl ParseOperator for OperatorBitAnd {
     fn parse_operator(s: &str) -> IResult<&str, Self> {
-        let (s, _) = char('&')(s)?;
+        let (s, _) = terminated(char('&'), not(char('&')))(s)?;
         Ok((s, OperatorBitAnd()))
     }
 }
@@ -557,10 +718,18 @@ impl MutCheck for ExpBitAnd {
     }
 }
 
+impl Resolve for ExpBitAnd {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub struct ExpShift {
     pub terms: Vec<ExpAddSub>,
     pub opes: Vec<OperatorShift>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpShift {
@@ -569,7 +738,7 @@ impl GenType for ExpShift {
         expr_gen_type(equs, exprs.into_iter(), self.opes.iter(), |ope| match *ope {
                 OperatorShift::Shl => ("Shl", "operator<<"),
                 OperatorShift::Shr => ("Shr", "operator>>"),
-            }, Tag::new())
+            }, self.tag.clone(), self.span)
     }
 }
 
@@ -581,12 +750,8 @@ pub enum OperatorShift {
 
 impl Transpile for ExpShift {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.terms.len() {
-            res.push_str(&self.terms[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        let terms = ta.lowering.operands(&self.terms, |term| term.transpile(ta));
+        transpile_operators(ta, terms, &self.opes, &self.tag)
     }
 }
 
@@ -602,8 +767,8 @@ impl Transpile for OperatorShift {
 impl ParseExpression for ExpShift {
     type Child = ExpAddSub;
     type Operator = OperatorShift;
-    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { terms, opes }
+    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { terms, opes, span, tag: Tag::new() }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
         default_parse_expression::<Self>(s)
@@ -636,10 +801,18 @@ impl MutCheck for ExpShift {
     }
 }
 
+impl Resolve for ExpShift {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub struct ExpAddSub {
     pub terms: Vec<ExpMulDivRem>,
     pub opes: Vec<OperatorAddSub>,
+    pub span: Span,
+    pub tag: Tag,
 }
 
 impl GenType for ExpAddSub {
@@ -648,7 +821,7 @@ impl GenType for ExpAddSub {
         expr_gen_type(equs, exprs.into_iter(), self.opes.iter(), |ope| match *ope {
                 OperatorAddSub::Add => ("Add", "operator+"),
                 OperatorAddSub::Sub => ("Sub", "operator-"),
-            }, Tag::new())
+            }, self.tag.clone(), self.span)
     }
 }
 
@@ -660,12 +833,8 @@ pub enum OperatorAddSub {
 
 impl Transpile for ExpAddSub {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.terms.len() {
-            res.push_str(&self.terms[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        let terms = ta.lowering.operands(&self.terms, |term| term.transpile(ta));
+        transpile_operators(ta, terms, &self.opes, &self.tag)
     }
 }
 
@@ -681,8 +850,8 @@ impl Transpile for OperatorAddSub {
 impl ParseExpression for ExpAddSub {
     type Child = ExpMulDivRem;
     type Operator = OperatorAddSub;
-    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { terms, opes }
+    fn new_expr(terms: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { terms, opes, span, tag: Tag::new() }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
         default_parse_expression::<Self>(s)
@@ -715,11 +884,18 @@ impl MutCheck for ExpAddSub {
     }
 }
 
+impl Resolve for ExpAddSub {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.terms.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub struct ExpMulDivRem {
     pub unary_exprs: Vec<ExpUnaryOpe>,
     pub opes: Vec<OperatorMulDivRem>,
     pub tag: Tag,
+    pub span: Span,
 }
 
 impl GenType for ExpMulDivRem {
@@ -729,7 +905,7 @@ impl GenType for ExpMulDivRem {
                 OperatorMulDivRem::Mul => ("Mul", "operator*"),
                 OperatorMulDivRem::Div => ("Div", "operator/"),
                 OperatorMulDivRem::Rem => ("Rem", "operator%"),
-            }, self.tag.clone())
+            }, self.tag.clone(), self.span)
     }
 }
 
@@ -742,12 +918,8 @@ pub enum OperatorMulDivRem {
 
 impl Transpile for ExpMulDivRem {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let mut res = String::new();
-        for i in 0..self.unary_exprs.len() {
-            res.push_str(&self.unary_exprs[i].transpile(ta));
-            if i < self.opes.len() { res.push_str(&self.opes[i].transpile(ta)); }
-        }
-        res
+        let unary_exprs = ta.lowering.operands(&self.unary_exprs, |unary_expr| unary_expr.transpile(ta));
+        transpile_operators(ta, unary_exprs, &self.opes, &self.tag)
     }
 }
 
@@ -764,12 +936,13 @@ impl Transpile for OperatorMulDivRem {
 impl ParseExpression for ExpMulDivRem {
     type Child = ExpUnaryOpe;
     type Operator = OperatorMulDivRem;
-    fn new_expr(unary_exprs: Vec<Self::Child>, opes: Vec<Self::Operator>) -> Self {
-        Self { unary_exprs, opes, tag: Tag::new(), }
+    fn new_expr(unary_exprs: Vec<Self::Child>, opes: Vec<Self::Operator>, span: Span) -> Self {
+        Self { unary_exprs, opes, tag: Tag::with_span(span), span }
     }
     fn parse_expression(s: &str) -> IResult<&str, Self> {
+        let start = s;
         let (s, (head, _, tails)) = 
-            tuple((parse_exp_unary_ope, multispace0, many0(tuple((Self::Operator::parse_operator, multispace0, parse_exp_unary_ope, multispace0)))))(s)?;
+            tuple((parse_exp_cast, multispace0, many0(tuple((Self::Operator::parse_operator, multispace0, parse_exp_cast, multispace0)))))(s)?;
         let mut unary_exprs = vec![head];
         let mut opes = Vec::new();
 
@@ -777,7 +950,7 @@ impl ParseExpression for ExpMulDivRem {
             unary_exprs.push(expr);
             opes.push(ope);
         }
-        Ok((s, ExpMulDivRem { unary_exprs, opes, tag: Tag::new() }))
+        Ok((s, Self::new_expr(unary_exprs, opes, Span::between(start, s))))
     }
 }
 
@@ -808,12 +981,39 @@ impl MutCheck for ExpMulDivRem {
     }
 }
 
+impl Resolve for ExpMulDivRem {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.unary_exprs.resolve(scope)
+    }
+}
+
 #[derive(Debug)]
 pub enum ExpUnaryOpe {
     UnaryExpr(UnaryExpr),
     Ref(Box<ExpUnaryOpe>),
     MutRef(Box<ExpUnaryOpe>),
     Deref(Box<ExpUnaryOpe>, Tag),
+    Cast(Box<ExpUnaryOpe>, TypeSpec, Tag),
+    Neg(Box<ExpUnaryOpe>, Tag),
+    Not(Box<ExpUnaryOpe>, Tag),
+}
+
+fn unary_operator_gen_type(ty: Type, tr: &str, tag: &Tag, equs: &mut TypeEquations) -> TResult {
+    let (method, _) = find_unary_operator(tr).unwrap();
+    let alpha = tag.generate_type_variable("Operators", 0, equs);
+    let before = equs.set_span(tag.get_span());
+    equs.add_equation(ty, alpha.clone());
+    let call = Type::CallEquation(CallEquation {
+        caller_type: Some(Box::new(alpha.clone())),
+        trait_gen: Some(TraitGenerics { trait_id: TraitId::from_str(tr), generics: Vec::new() }),
+        func_id: Identifier::from_str(method),
+        args: vec![alpha],
+        tag: Tag::new(),
+    });
+    let beta = tag.generate_type_variable("Operators", 1, equs);
+    equs.add_equation(call, beta.clone());
+    equs.set_span(before);
+    Ok(beta)
 }
 
 impl GenType for ExpUnaryOpe {
@@ -828,6 +1028,26 @@ impl GenType for ExpUnaryOpe {
                 equs.add_equation(alpha.clone(), right);
                 Ok(Type::Deref(Box::new(alpha)))
             }
+            Self::Cast(ref exp, ref spec, ref tag) => {
+                let alpha = tag.generate_not_void_type_variable("CastFromType", 0, equs);
+                let from = exp.as_ref().gen_type(equs, trs)?;
+                equs.add_equation(alpha, from);
+                let beta = tag.generate_not_void_type_variable("CastToType", 0, equs);
+                let to = spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+                equs.add_equation(beta.clone(), to);
+                Ok(beta)
+            }
+            Self::Neg(ref exp, ref tag) => {
+                let ty = match exp.as_ref() {
+                    Self::UnaryExpr(UnaryExpr::Literal(ref lit)) => lit.gen_type_negated(equs, trs)?,
+                    exp => exp.gen_type(equs, trs)?,
+                };
+                unary_operator_gen_type(ty, "Neg", tag, equs)
+            }
+            Self::Not(ref exp, ref tag) => {
+                let ty = exp.gen_type(equs, trs)?;
+                unary_operator_gen_type(ty, "Not", tag, equs)
+            }
         }
     }
 }
@@ -839,6 +1059,12 @@ impl Transpile for ExpUnaryOpe {
             Self::Ref(ref exp) => format!("&{}", exp.as_ref().transpile(ta)),
             Self::MutRef(ref exp) => format!("&{}", exp.as_ref().transpile(ta)),
             Self::Deref(ref exp, _) => format!("*{}", exp.as_ref().transpile(ta)),
+            Self::Neg(ref exp, _) => match exp.as_ref() {
+                Self::UnaryExpr(UnaryExpr::Literal(ref lit)) => lit.transpile_negated(ta),
+                exp => format!("(-{})", exp.transpile(ta)),
+            },
+            Self::Not(ref exp, _) => format!("(!{})", exp.as_ref().transpile(ta)),
+            Self::Cast(ref exp, _, ref tag) => format!("static_cast<{}>({})", ta.annotation(tag.get_num(), "CastToType", 0).transpile(ta), exp.as_ref().transpile(ta)),
         }
     }
 }
@@ -865,6 +1091,38 @@ impl MutCheck for ExpUnaryOpe {
                     _ => Ok(MutResult::NotMut),
                 }
             }
+            Self::Neg(ref exp, _) | Self::Not(ref exp, _) => {
+                exp.mut_check(ta, vars)?;
+                Ok(MutResult::NotMut)
+            }
+            Self::Cast(ref exp, _, ref tag) => {
+                exp.mut_check(ta, vars)?;
+                let from = ta.annotation(tag.get_num(), "CastFromType", 0);
+                let to = ta.annotation(tag.get_num(), "CastToType", 0);
+                if is_primitive_number(&from) && is_primitive_number(&to) {
+                    Ok(MutResult::NotMut)
+                }
+                else {
+                    let name = |ty: &Type| match *ty {
+                        Type::Generics(ref id, _) => id.id.name.clone(),
+                        ref ty => format!("{:?}", ty),
+                    };
+                    Err(tag.get_span().error_message(&format!("cannot cast {} as {}", name(&from), name(&to))))
+                }
+            }
+        }
+    }
+}
+
+impl Resolve for ExpUnaryOpe {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        match *self {
+            Self::UnaryExpr(ref mut exp) => exp.resolve(scope),
+            Self::Ref(ref mut exp) | Self::MutRef(ref mut exp) | Self::Deref(ref mut exp, _) | Self::Neg(ref mut exp, _) | Self::Not(ref mut exp, _) => exp.resolve(scope),
+            Self::Cast(ref mut exp, ref mut spec, _) => {
+                exp.resolve(scope)?;
+                spec.resolve(scope)
+            }
         }
     }
 }
@@ -883,28 +1141,73 @@ pub fn parse_exp_unary_ope_deref(s: &str) -> IResult<&str, ExpUnaryOpe> {
     Ok((s, ExpUnaryOpe::Deref(Box::new(exp), Tag::new())))
 }
 
+pub fn parse_exp_unary_ope_neg(s: &str) -> IResult<&str, ExpUnaryOpe> {
+    let start = s;
+    let (s, (_, _, exp)) = tuple((char('-'), multispace0, parse_exp_unary_ope))(s)?;
+    Ok((s, ExpUnaryOpe::Neg(Box::new(exp), Tag::with_span(Span::between(start, s)))))
+}
+
+pub fn parse_exp_unary_ope_not(s: &str) -> IResult<&str, ExpUnaryOpe> {
+    let start = s;
+    let (s, (_, _, exp)) = tuple((char('!'), multispace0, parse_exp_unary_ope))(s)?;
+    Ok((s, ExpUnaryOpe::Not(Box::new(exp), Tag::with_span(Span::between(start, s)))))
+}
+
 pub fn parse_exp_unary_ope_unary_exp(s: &str) -> IResult<&str, ExpUnaryOpe> {
     let (s, exp) = parse_unary_expr(s)?;
     Ok((s, ExpUnaryOpe::UnaryExpr(exp)))
 }
 
 pub fn parse_exp_unary_ope(s: &str) -> IResult<&str, ExpUnaryOpe> {
-    alt((parse_exp_unary_ope_mutref, parse_exp_unary_ope_ref, parse_exp_unary_ope_deref, parse_exp_unary_ope_unary_exp))(s)
+    alt((parse_exp_unary_ope_mutref, parse_exp_unary_ope_ref, parse_exp_unary_ope_deref, parse_exp_unary_ope_neg, parse_exp_unary_ope_not, parse_exp_unary_ope_unary_exp))(s)
+}
+
+pub fn parse_exp_cast(s: &str) -> IResult<&str, ExpUnaryOpe> {
+    let start = s;
+    let (mut s, mut exp) = parse_exp_unary_ope(s)?;
+    while let Ok((next, (_, _, _, spec))) = tuple((multispace0, parse_keyword("as"), multispace1, parse_type_spec))(s) {
+        s = next;
+        exp = ExpUnaryOpe::Cast(Box::new(exp), spec, Tag::with_span(Span::between(start, s)));
+    }
+    Ok((s, exp))
 }
 
 
 
 
 pub fn parse_expression(s: &str) -> IResult<&str, Expression> {
-    let (s, expr) = alt((parse_if_expr, parse_for_expr, parse_expor))(s)?;
+    let (s, expr) = alt((parse_if_expr, parse_for_in_expr, parse_for_expr, parse_match_expr, parse_while_expr, parse_loop_expr, parse_closure_expr, parse_range_or_expor))(s)?;
     Ok((s, expr))
 }
 
-pub fn parse_expor(s: &str) -> IResult<&str, Expression> {
-    let (s, p) = ExpOr::parse_expression(s)?;
-    Ok((s, Expression::Expression(p)))
+fn single_term<P: ParseExpression>(child: P::Child, span: Span) -> P {
+    P::new_expr(vec![child], Vec::new(), span)
+}
+
+pub fn unary_expression(uexpr: UnaryExpr, span: Span) -> Expression {
+    let exp: ExpMulDivRem = single_term(ExpUnaryOpe::UnaryExpr(uexpr), span);
+    let exp: ExpAddSub = single_term(exp, span);
+    let exp: ExpShift = single_term(exp, span);
+    let exp: ExpBitAnd = single_term(exp, span);
+    let exp: ExpBitXor = single_term(exp, span);
+    let exp: ExpBitOr = single_term(exp, span);
+    let exp: ExpOrd = single_term(exp, span);
+    let exp: ExpAnd = single_term(exp, span);
+    let exp: ExpOr = single_term(exp, span);
+    Expression::Expression(exp)
+}
+
+fn parse_block_expression(s: &str) -> IResult<&str, Expression> {
+    let start = s;
+    let (s, block) = parse_bracket_block(s)?;
+    Ok((s, unary_expression(block, Span::between(start, s))))
 }
 
+pub fn parse_block_like_expression(s: &str) -> IResult<&str, Expression> {
+    alt((parse_if_expr, parse_for_in_expr, parse_for_expr, parse_match_expr, parse_while_expr, parse_loop_expr, parse_block_expression))(s)
+}
+
+
 
 #[test]
 fn parse_expression_test() {
@@ -912,6 +1215,8 @@ fn parse_expression_test() {
     println!("{:?}", parse_expression("func(1 + 2, 3 - 4)"));
     println!("{:#?}", parse_expression("1 + 2 * 3 - 4 / 5"));
     println!("{:#?}", parse_expression("(1 + 2) * (3 - 4) / 5"));
+    println!("{:#?}", parse_expression("x as u128 * y as u128 % m as u128"));
+    println!("{:#?}", parse_expression("-x + !flag"));
 }
 
 #[test]
@@ -933,6 +1238,13 @@ fn parse_all_test() {
     println!("{:?}", parse_expression("1 + 2 == 3 * 4 || 5 << 6 & 7 >> 8 != 9 | 0 ^ 1 && 2 % 3 < 4 / 5 && 6 > 7 && 8 < 9 || 0 <= 1 && 2 >= 3"));
 }
 
+#[test]
+fn parse_logical_test() {
+    let (s, _) = parse_expression("a < b && b == c || !d").unwrap();
+    assert_eq!(s, "");
+    println!("{:?}", parse_expression("a & &b | c"));
+}
+
 #[test]
 fn parse_ref_test() {
     println!("{:?}", parse_expression("*var"));
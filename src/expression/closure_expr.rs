@@ -0,0 +1,140 @@
+use nom::IResult;
+use nom::branch::*;
+use nom::character::complete::*;
+use nom::combinator::*;
+use nom::multi::*;
+use nom::sequence::*;
+use nom::bytes::complete::*;
+
+use crate::comment::multispace0;
+use crate::identifier::*;
+use crate::expression::{ Expression, parse_expression };
+use crate::block::{ Block, parse_block };
+use crate::unary_expr::Variable;
+use crate::type_spec::*;
+use crate::unify::*;
+use crate::trans::*;
+use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
+
+#[derive(Debug)]
+pub struct ClosureExpr {
+    is_move: bool,
+    args: Vec<(Identifier, Option<TypeSpec>)>,
+    return_type: Option<TypeSpec>,
+    block: Block,
+    tag: Tag,
+}
+
+impl ClosureExpr {
+    pub fn get_span(&self) -> Span {
+        self.tag.get_span()
+    }
+}
+
+impl GenType for ClosureExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        equs.into_scope();
+        let mut args = Vec::new();
+        for (i, (id, spec)) in self.args.iter().enumerate() {
+            let alpha = self.tag.generate_not_void_type_variable("ClosureArgType", i, equs);
+            if let Some(spec) = spec {
+                let ty = spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+                equs.add_equation(alpha.clone(), ty);
+            }
+            equs.regist_variable(Variable::from_identifier(id.clone()), alpha.clone());
+            args.push(alpha);
+        }
+        let return_t = self.tag.generate_type_variable("ClosureReturnType", 0, equs);
+        if let Some(ref spec) = self.return_type {
+            let ty = spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+            equs.add_equation(return_t.clone(), ty);
+        }
+        let before_return = equs.set_return_type(Some((return_t.clone(), false)));
+        let before_loops = equs.replace_loops(Vec::new());
+        let result_type = self.block.gen_type(equs, trs);
+        equs.replace_loops(before_loops);
+        equs.set_return_type(before_return);
+        let result_type = result_type?;
+        if !self.block.ends_with_return() {
+            let before = equs.set_span(self.get_span());
+            equs.add_equation(result_type, return_t.clone());
+            equs.set_span(before);
+        }
+        equs.out_scope();
+        Ok(Type::Func(args, Box::new(return_t), FuncTypeInfo::None))
+    }
+}
+
+impl Transpile for ClosureExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let args = self.args.iter().enumerate().map(|(i, (id, _))| {
+            format!("{} {}", ta.annotation(self.tag.get_num(), "ClosureArgType", i).transpile(ta), id.into_string())
+        }).collect::<Vec<_>>().join(", ");
+        let return_type = ta.annotation(self.tag.get_num(), "ClosureReturnType", 0);
+        let block = if return_type == Type::from_str("void") {
+            self.block.transpile_for_void(ta)
+        }
+        else {
+            self.block.transpile(ta)
+        };
+        if self.is_move {
+            format!("[=]({}) mutable -> {} {{\n{}}}", args, return_type.transpile(ta), block)
+        }
+        else {
+            format!("[&]({}) -> {} {{\n{}}}", args, return_type.transpile(ta), block)
+        }
+    }
+}
+
+impl MutCheck for ClosureExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        vars.into_scope();
+        for (id, _) in self.args.iter() {
+            vars.regist_variable(id, false);
+        }
+        let res = self.block.mut_check(ta, vars);
+        vars.out_scope();
+        res?;
+        Ok(MutResult::NotMut)
+    }
+}
+
+impl Resolve for ClosureExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.args.iter_mut().try_for_each(|(_, spec)| spec.resolve(scope))?;
+        self.return_type.resolve(scope)?;
+        self.block.resolve(scope)
+    }
+}
+
+fn parse_closure_args(s: &str) -> IResult<&str, Vec<(Identifier, Option<TypeSpec>)>> {
+    let (s, args) = alt((
+            map(tag("||"), |_| Vec::new()),
+            map(tuple((char('|'), multispace0,
+                       separated_list0(tuple((multispace0, char(','), multispace0)), tuple((parse_identifier, opt(tuple((multispace0, char(':'), multispace0, parse_type_spec)))))),
+                       multispace0, opt(char(',')), multispace0, char('|'))),
+                |(_, _, args, _, _, _, _)| args.into_iter().map(|(id, spec)| (id, spec.map(|(_, _, _, spec)| spec))).collect()),
+            ))(s)?;
+    Ok((s, args))
+}
+
+pub fn parse_closure_expr(s: &str) -> IResult<&str, Expression> {
+    let start = s;
+    let (s, (is_move, args, _)) = tuple((opt(tuple((parse_keyword("move"), multispace0))), parse_closure_args, multispace0))(s)?;
+    let (s, (return_type, block)) = alt((
+            map(tuple((tag("->"), multispace0, parse_type_spec, multispace0, char('{'), parse_block, char('}'))), |(_, _, spec, _, _, block, _)| (Some(spec), block)),
+            map(tuple((char('{'), parse_block, char('}'))), |(_, block, _)| (None, block)),
+            map(parse_expression, |exp| (None, Block { statements: Vec::new(), return_exp: Some(exp) })),
+            ))(s)?;
+    let tag = Tag::with_span(Span::between(start, s));
+    Ok((s, Expression::ClosureExpr(Box::new(ClosureExpr { is_move: is_move.is_some(), args, return_type, block, tag }))))
+}
+
+#[test]
+fn parse_closure_expr_test() {
+    println!("{:?}", parse_closure_expr("|a, b| a + b"));
+    println!("{:?}", parse_closure_expr("|x: i64| -> bool { x * x >= k }"));
+    println!("{:?}", parse_closure_expr("move || { cnt += 1; }"));
+}
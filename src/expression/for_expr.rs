@@ -4,12 +4,16 @@ use nom::character::complete::*;
 use nom::sequence::*; 
 use nom::bytes::complete::*;
 
+use crate::comment::multispace0;
+use crate::identifier::Tag;
 use crate::statement::*;
 use crate::expression::*;
 use crate::block::*;
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 pub struct ForExpr {
@@ -17,15 +21,24 @@ pub struct ForExpr {
     cond: Expression,
     update: Statement,
     block: Block,
+    pub span: Span,
 }
 
 impl GenType for ForExpr {
     fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
         equs.into_scope();
-        self.init.gen_type(equs, trs)?;
-        let cond_type = self.cond.gen_type(equs, trs)?;
-        self.update.gen_type(equs, trs)?;
-        let bl_type = self.block.gen_type(equs, trs)?;
+        let before_return = equs.set_return_type(None);
+        let before_loops = equs.replace_loops(Vec::new());
+        let header = self.init.gen_type(equs, trs)
+            .and_then(|_| self.cond.gen_type(equs, trs))
+            .and_then(|cond_type| self.update.gen_type(equs, trs).map(|_| cond_type));
+        equs.set_return_type(before_return);
+        equs.replace_loops(before_loops);
+        let cond_type = header?;
+        equs.into_loop(None);
+        let bl_type = self.block.gen_type(equs, trs);
+        equs.out_loop();
+        let bl_type = bl_type?;
         equs.add_equation(cond_type, Type::from_str("bool"));
         equs.add_equation(bl_type, Type::from_str("void"));
         equs.out_scope();
@@ -35,11 +48,26 @@ impl GenType for ForExpr {
 
 impl Transpile for ForExpr {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let init_trans = self.init.transpile(ta);
-        let cond_trans = self.cond.transpile(ta);
-        let update_trans = self.update.transpile(ta);
-        let block_trans = self.block.transpile(ta);
-        format!("for({}; {}; {}){{\n{}}}", init_trans, cond_trans, update_trans, block_trans)
+        let (init_prelude, init_trans) = ta.lowering.capture(|| self.init.transpile(ta));
+        let (cond_prelude, cond_trans) = ta.lowering.capture(|| self.cond.transpile(ta));
+        let (update_prelude, update_trans) = ta.lowering.capture(|| self.update.transpile(ta));
+        let for_trans = if cond_prelude.is_empty() && update_prelude.is_empty() {
+            let block_trans = ta.lowering.in_loop_body(None, || self.block.transpile_for_void(ta));
+            format!("for({}; {}; {}){{\n{}}}", init_trans, cond_trans, update_trans, block_trans)
+        }
+        else {
+            // the condition and the update are evaluated on every iteration, so their preludes go inside the loop
+            let label = format!("for_continue_{}", Tag::new().get_num());
+            let block_trans = ta.lowering.in_loop_body(Some(label.clone()), || self.block.transpile_for_void(ta));
+            let label_trans = if block_trans.contains(&format!("goto {};", label)) { format!("{}:;\n", label) } else { String::new() };
+            format!("{};\nwhile(true) {{\n{}if(!({})) break;\n{{\n{}}}\n{}{}{};\n}}", init_trans, cond_prelude, cond_trans, block_trans, label_trans, update_prelude, update_trans)
+        };
+        if init_prelude.is_empty() && cond_prelude.is_empty() && update_prelude.is_empty() {
+            for_trans
+        }
+        else {
+            format!("{{\n{}{}}}", init_prelude, for_trans)
+        }
     }
 }
 
@@ -55,14 +83,24 @@ impl MutCheck for ForExpr {
     }
 }
 
+impl Resolve for ForExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.init.resolve(scope)?;
+        self.cond.resolve(scope)?;
+        self.update.resolve(scope)?;
+        self.block.resolve(scope)
+    }
+}
+
 pub fn parse_for_expr(s: &str) -> IResult<&str, Expression> {
+    let start = s;
     let (s, (_, _, _, _, init, _, _, _, cond, _, _, _, update, _, _, _, _, _, block, _, _)) =
         tuple((tag("for"), multispace0, char('('), multispace0,
             parse_statement, multispace0, char(';'), multispace0,
             parse_expression, multispace0, char(';'), multispace0,
             alt((parse_substitute_to_statement, parse_expression_to_statement)), multispace0, char(')'), multispace0, char('{'), multispace0,
             parse_block, multispace0, char('}')))(s)?;
-    Ok((s, Expression::ForExpr(Box::new(ForExpr { init, cond, update, block }))))
+    Ok((s, Expression::ForExpr(Box::new(ForExpr { init, cond, update, block, span: Span::between(start, s) }))))
 }
 
 #[test]
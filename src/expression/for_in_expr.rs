@@ -0,0 +1,134 @@
+use nom::IResult;
+use nom::character::complete::*;
+use nom::sequence::*;
+
+use crate::comment::{ multispace0, multispace1 };
+use crate::identifier::*;
+use crate::expression::{ Expression, parse_expression };
+use crate::block::{ Block, parse_block };
+use crate::unary_expr::{ UnaryExpr, Variable, Parentheses };
+use crate::subseq::method_call;
+use crate::type_id::{ TypeId, INTEGER_TYPES };
+use crate::type_spec::TypeSign;
+use crate::structs::search_enum_type;
+use crate::unify::*;
+use crate::trans::*;
+use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
+
+#[derive(Debug)]
+pub struct ForInExpr {
+    item: Identifier,
+    iter_id: Identifier,
+    into_iter: UnaryExpr,
+    next: UnaryExpr,
+    block: Block,
+    tag: Tag,
+}
+
+impl ForInExpr {
+    pub fn get_span(&self) -> Span {
+        self.tag.get_span()
+    }
+    fn is_integer_range(&self, ta: &TypeAnnotation) -> bool {
+        match ta.annotation(self.tag.get_num(), "IterType", 0) {
+            Type::Generics(ref id, ref gens) if *id == TypeId::from_str("Range") && gens.len() == 1 => {
+                matches!(gens[0], Type::Generics(ref id, ref gens) if gens.is_empty() && INTEGER_TYPES.contains(&id.id.into_string().as_str()))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl GenType for ForInExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        let sign = TypeSign { id: TypeId::from_str("Option"), gens: Vec::new() };
+        let option_type = match search_enum_type(&sign, equs, trs)? {
+            Some((ty, _)) => ty,
+            None => return Err(self.get_span().error_message("for-in loop needs enum Option, import \"std/iter.niu\"")),
+        };
+        let item_type = match option_type {
+            Type::Generics(_, ref gens) if gens.len() == 1 => gens[0].clone(),
+            _ => return Err(self.get_span().error_message("enum Option must have one generics argument")),
+        };
+        equs.into_scope();
+        let into_iter_type = self.into_iter.gen_type(equs, trs)?;
+        let iter_type = self.tag.generate_not_void_type_variable("IterType", 0, equs);
+        equs.add_equation(iter_type.clone(), into_iter_type);
+        equs.regist_variable(Variable::from_identifier(self.iter_id.clone()), iter_type);
+        let next_type = self.next.gen_type(equs, trs)?;
+        let alpha = self.tag.generate_not_void_type_variable("NextType", 0, equs);
+        equs.add_equation(alpha.clone(), next_type);
+        equs.add_equation(alpha, option_type);
+        let item = self.item.generate_not_void_type_variable("ForItemType", 0, equs);
+        equs.add_equation(item.clone(), item_type);
+        equs.regist_variable(Variable::from_identifier(self.item.clone()), item);
+        equs.into_loop(None);
+        let bl_type = self.block.gen_type(equs, trs);
+        equs.out_loop();
+        equs.out_scope();
+        equs.add_equation(bl_type?, Type::from_str("void"));
+        Ok(Type::from_str("void"))
+    }
+}
+
+impl Transpile for ForInExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let iter_type = ta.annotation(self.tag.get_num(), "IterType", 0).transpile(ta);
+        let item_type = ta.annotation(self.item.get_tag_number(), "ForItemType", 0).transpile(ta);
+        let iter = self.iter_id.into_string();
+        let item = self.item.into_string();
+        let block = ta.lowering.in_loop_body(None, || self.block.transpile_for_void(ta));
+        if self.is_integer_range(ta) {
+            format!("for({} {} = {}; {}.start < {}.end; ++{}.start) {{\n{} const {} = {}.start;\n{}}}",
+                iter_type, iter, self.into_iter.transpile(ta), iter, iter, iter, item_type, item, iter, block)
+        }
+        else {
+            let next_type = ta.annotation(self.tag.get_num(), "NextType", 0).transpile(ta);
+            let some = ta.get_enum_variant_index(&TypeId::from_str("Option"), &Identifier::from_str("Some"));
+            let next = format!("for_next_{}", self.tag.get_num());
+            let next_trans = ta.lowering.statement(|| format!("{} {} = {};\n", next_type, next, self.next.transpile(ta)));
+            format!("for({} {} = {};;) {{\n{}if({}.data.index() != {}) {{ break; }}\n{} const {} = std::get<0>(std::get<{}>({}.data));\n{}}}",
+                iter_type, iter, self.into_iter.transpile(ta), next_trans, next, some, item_type, item, some, next, block)
+        }
+    }
+}
+
+impl MutCheck for ForInExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        vars.into_scope();
+        self.into_iter.mut_check(ta, vars)?;
+        vars.regist_variable(&self.iter_id, true);
+        self.next.mut_check(ta, vars)?;
+        vars.regist_variable(&self.item, false);
+        self.block.mut_check(ta, vars)?;
+        vars.out_scope();
+        Ok(MutResult::NotMut)
+    }
+}
+
+impl Resolve for ForInExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.into_iter.resolve(scope)?;
+        self.next.resolve(scope)?;
+        self.block.resolve(scope)
+    }
+}
+
+pub fn parse_for_in_expr(s: &str) -> IResult<&str, Expression> {
+    let start = s;
+    let (s, (_, _, item, _, _, _, expr, _, _, block, _)) = tuple((parse_keyword("for"), multispace1, parse_identifier, multispace1, parse_keyword("in"), multispace1,
+            parse_expression, multispace0, char('{'), parse_block, char('}')))(s)?;
+    let tag = Tag::with_span(Span::between(start, s));
+    let iter_id = Identifier::from_str(&format!("for_iter_{}", tag.get_num()));
+    let into_iter = method_call(UnaryExpr::Parentheses(Parentheses { expr }), Identifier::from_str("into_iter"), Vec::new());
+    let next = method_call(UnaryExpr::Variable(Variable::from_identifier(iter_id.clone())), Identifier::from_str("next"), Vec::new());
+    Ok((s, Expression::ForInExpr(Box::new(ForInExpr { item, iter_id, into_iter, next, block, tag }))))
+}
+
+#[test]
+fn parse_for_in_expr_test() {
+    println!("{:?}", parse_for_in_expr("for i in 0i64..n { s = s + i; }"));
+    println!("{:?}", parse_for_in_expr("for x in &v { w.push(x); }"));
+}
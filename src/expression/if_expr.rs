@@ -1,16 +1,21 @@
 //use nom::branch::*;
 use nom::IResult;
 use nom::character::complete::*;
+use nom::combinator::*;
 use nom::multi::*;
 use nom::sequence::*; 
 use nom::bytes::complete::*;
 
+use crate::comment::{ multispace0, multispace1 };
 use crate::identifier::*;
 use crate::expression::{ Expression, parse_expression };
 use crate::block::{ Block, parse_block };
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
+use crate::lowering::BlockTarget;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 struct IfPair {
@@ -22,16 +27,32 @@ struct IfPair {
 pub struct IfExpr {
     ifp: IfPair,
     elifp: Vec<IfPair>,
-    el_block: Block,
+    el_block: Option<Block>,
     tag: Tag,
 }
 
 impl IfExpr {
-    pub fn transpile_for_return(&self, ta: &TypeAnnotation) -> String {
-        let if_trans = format!("if({}) {{\n{}}}\n", self.ifp.cond.transpile(ta), self.ifp.block.transpile(ta));
-        let elif_trans = self.elifp.iter().map(|ifp| format!("\nelse if({}) {{\n{}}}\n", ifp.cond.transpile(ta), ifp.block.transpile(ta))).collect::<Vec<_>>().join("");
-        let else_trans = format!("else {{\n{}}}", self.el_block.transpile(ta));
-        format!("{}{}{}", if_trans, elif_trans, else_trans)
+    pub fn get_span(&self) -> Span {
+        self.tag.get_span()
+    }
+    pub fn is_void(&self, ta: &TypeAnnotation) -> bool {
+        ta.annotation(self.tag.get_num(), "ReturnType", 0).is_void()
+    }
+    pub fn transpile_to(&self, ta: &TypeAnnotation, target: &BlockTarget) -> String {
+        let (if_prelude, if_cond) = ta.lowering.capture(|| self.ifp.cond.transpile(ta));
+        let if_block = self.ifp.block.transpile_to(ta, target);
+        let mut tail = self.el_block.as_ref().map_or(String::new(), |el_block| format!("else {{\n{}}}\n", el_block.transpile_to(ta, target)));
+        for IfPair { cond, block } in self.elifp.iter().rev() {
+            let (prelude, cond) = ta.lowering.capture(|| cond.transpile(ta));
+            let block = block.transpile_to(ta, target);
+            tail = if prelude.is_empty() {
+                format!("else if({}) {{\n{}}}\n{}", cond, block, tail)
+            }
+            else {
+                format!("else {{\n{}if({}) {{\n{}}}\n{}}}\n", prelude, cond, block, tail)
+            };
+        }
+        format!("{}if({}) {{\n{}}}\n{}", if_prelude, if_cond, if_block, tail)
     }
 }
 
@@ -48,22 +69,29 @@ impl GenType for IfExpr {
             equs.add_equation(cond_type, Type::from_str("bool"));
             equs.add_equation(return_type.clone(), bl2_type);
         }
-        let el_bl_type = self.el_block.gen_type(equs, trs)?;
-        equs.add_equation(return_type.clone(), el_bl_type);
+        match self.el_block {
+            Some(ref el_block) => {
+                let el_bl_type = el_block.gen_type(equs, trs)?;
+                equs.add_equation(return_type.clone(), el_bl_type);
+            }
+            None => {
+                let before = equs.set_span(self.get_span());
+                equs.add_equation(return_type.clone(), Type::from_str("void"));
+                equs.set_span(before);
+            }
+        }
         Ok(return_type)
     }
 }
 
 impl Transpile for IfExpr {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let if_trans = format!("if({}) {{\n {}}}\n", self.ifp.cond.transpile(ta), self.ifp.block.transpile(ta));
-        let elif_trans = self.elifp.iter().map(|ifp| format!("\nelse if({}) {{\n {}}}\n", ifp.cond.transpile(ta), ifp.block.transpile(ta))).collect::<Vec<_>>().join("");
-        let else_trans = format!("else {{\n {}}}", self.el_block.transpile(ta));
-        if Type::from_str("void") == ta.annotation(self.tag.get_num(), "ReturnType", 0) {
-            format!("{}{}{}", if_trans, elif_trans, else_trans)
+        if self.is_void(ta) {
+            self.transpile_to(ta, &BlockTarget::Void)
         }
         else {
-            format!("[&](){{ {}{}{} \n}}()", if_trans, elif_trans, else_trans)
+            let ty = ta.annotation(self.tag.get_num(), "ReturnType", 0).transpile(ta);
+            ta.lowering.temporary(self.tag.get_num(), ty, |target| self.transpile_to(ta, target))
         }
     }
 }
@@ -76,21 +104,36 @@ impl MutCheck for IfExpr {
             cond.mut_check(ta, vars)?;
             block.mut_check(ta, vars)?;
         }
-        self.el_block.mut_check(ta, vars)?;
+        if let Some(ref el_block) = self.el_block {
+            el_block.mut_check(ta, vars)?;
+        }
         Ok(MutResult::NotMut)
     }
 }
 
+impl Resolve for IfExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        for pair in std::iter::once(&mut self.ifp).chain(self.elifp.iter_mut()) {
+            pair.cond.resolve(scope)?;
+            pair.block.resolve(scope)?;
+        }
+        self.el_block.resolve(scope)
+    }
+}
+
 pub fn parse_if_expr(s: &str) -> IResult<&str, Expression> {
-    let (s, (_, _, if_cond, _, _, if_block, _, _, many, _, _, _, el_block, _, _)) = tuple((tag("if"), space1, parse_expression, multispace0, char('{'), parse_block, char('}'), multispace0,
-                        many0(tuple((tag("else"), space1, tag("if"), space1, parse_expression, multispace0, char('{'), parse_block, char('}'), multispace0))),
-                        tag("else"), space1, char('{'), parse_block, char('}'), multispace0))(s)?;
+    let start = s;
+    let (s, (_, _, if_cond, _, _, if_block, _, _, many, el_block)) = tuple((tag("if"), multispace1, parse_expression, multispace0, char('{'), parse_block, char('}'), multispace0,
+                        many0(tuple((tag("else"), multispace1, tag("if"), multispace1, parse_expression, multispace0, char('{'), parse_block, char('}'), multispace0))),
+                        opt(tuple((tag("else"), multispace1, char('{'), parse_block, char('}'), multispace0)))))(s)?;
     let ifp = IfPair { cond: if_cond, block: if_block };
     let elifp = many.into_iter().map(|(_, _, _, _, cond, _, _, block, _, _)| IfPair { cond, block }).collect::<Vec<_>>();
-    Ok((s, Expression::IfExpr(Box::new(IfExpr { ifp, elifp, el_block, tag: Tag::new(), }))))
+    let el_block = el_block.map(|(_, _, _, block, _, _)| block);
+    Ok((s, Expression::IfExpr(Box::new(IfExpr { ifp, elifp, el_block, tag: Tag::with_span(Span::between(start, s)), }))))
 }
 
 #[test]
 fn parse_if_expr_test() {
     println!("{:?}", parse_if_expr("if a == b { c } else { d }"));
+    println!("{:?}", parse_if_expr("if a == b { c; }"));
 }
@@ -0,0 +1,85 @@
+use nom::IResult;
+use nom::character::complete::*;
+use nom::sequence::*;
+
+use crate::comment::multispace0;
+use crate::identifier::*;
+use crate::expression::Expression;
+use crate::block::{ Block, parse_block };
+use crate::unify::*;
+use crate::trans::*;
+use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
+
+#[derive(Debug)]
+pub struct LoopExpr {
+    block: Block,
+    tag: Tag,
+}
+
+impl LoopExpr {
+    pub fn get_span(&self) -> Span {
+        self.tag.get_span()
+    }
+    pub fn transpile_for_return(&self, ta: &TypeAnnotation) -> String {
+        ta.lowering.in_loop(None, || format!("while(true) {{\n{}}}", ta.lowering.in_loop_body(None, || self.block.transpile_for_void(ta))))
+    }
+}
+
+impl GenType for LoopExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        let loop_type = self.tag.generate_type_variable("LoopType", 0, equs);
+        equs.into_loop(Some(loop_type.clone()));
+        let bl_type = self.block.gen_type(equs, trs);
+        let has_break = equs.out_loop();
+        equs.add_equation(bl_type?, Type::from_str("void"));
+        if !has_break {
+            // a loop without break takes the type its context wants and falls back to never
+            equs.add_equation(loop_type.clone(), Type::Never);
+        }
+        Ok(loop_type)
+    }
+}
+
+impl Transpile for LoopExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let loop_type = ta.annotation(self.tag.get_num(), "LoopType", 0);
+        if loop_type == Type::Never {
+            ta.lowering.hoist(format!("{}\n", self.transpile_for_return(ta)));
+            String::new()
+        }
+        else if loop_type.is_void() {
+            self.transpile_for_return(ta)
+        }
+        else {
+            ta.lowering.temporary(self.tag.get_num(), loop_type.transpile(ta), |target| {
+                ta.lowering.in_loop(Some(target.clone()), || format!("while(true) {{\n{}}}\n", ta.lowering.in_loop_body(None, || self.block.transpile_for_void(ta))))
+            })
+        }
+    }
+}
+
+impl MutCheck for LoopExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        self.block.mut_check(ta, vars)?;
+        Ok(MutResult::NotMut)
+    }
+}
+
+impl Resolve for LoopExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.block.resolve(scope)
+    }
+}
+
+pub fn parse_loop_expr(s: &str) -> IResult<&str, Expression> {
+    let start = s;
+    let (s, (_, _, _, block, _)) = tuple((parse_keyword("loop"), multispace0, char('{'), parse_block, char('}')))(s)?;
+    Ok((s, Expression::LoopExpr(Box::new(LoopExpr { block, tag: Tag::with_span(Span::between(start, s)) }))))
+}
+
+#[test]
+fn parse_loop_expr_test() {
+    println!("{:?}", parse_loop_expr("loop { if i == n { break i; } else { i = i + 1; }; }"));
+}
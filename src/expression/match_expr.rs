@@ -0,0 +1,482 @@
+use std::collections::HashSet;
+
+use nom::IResult;
+use nom::character::complete::*;
+use nom::multi::*;
+use nom::sequence::*;
+use nom::bytes::complete::*;
+use nom::branch::*;
+use nom::combinator::*;
+
+use crate::comment::{ multispace0, multispace1 };
+use crate::identifier::*;
+use crate::expression::{ Expression, parse_expression };
+use crate::block::{ Block, parse_block };
+use crate::unary_expr::Variable;
+use crate::type_spec::*;
+use crate::type_id::TypeId;
+use crate::literal::*;
+use crate::structs::*;
+use crate::unify::*;
+use crate::trans::*;
+use crate::mut_checker::*;
+use crate::lowering::BlockTarget;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve, parse_item_member };
+
+#[derive(Debug)]
+pub enum Pattern {
+    Wildcard(Tag),
+    Binding(Identifier),
+    Literal(Literal, Tag),
+    Tuple(Vec<Pattern>, Tag),
+    Variant(TypeSign, Identifier, Vec<Pattern>),
+}
+
+#[derive(Debug)]
+pub struct MatchArm {
+    pattern: Pattern,
+    block: Block,
+}
+
+#[derive(Debug)]
+pub struct MatchExpr {
+    expr: Expression,
+    arms: Vec<MatchArm>,
+    tag: Tag,
+}
+
+fn is_wildcard(id: &Identifier) -> bool {
+    id.name == "_"
+}
+
+impl MatchExpr {
+    pub fn get_span(&self) -> Span {
+        self.tag.get_span()
+    }
+    fn has_variant_arm(&self) -> bool {
+        self.arms.iter().any(|arm| matches!(arm.pattern, Pattern::Variant(..)))
+    }
+    pub fn is_void(&self, ta: &TypeAnnotation) -> bool {
+        ta.annotation(self.tag.get_num(), "ReturnType", 0).is_void()
+    }
+    pub fn transpile_to(&self, ta: &TypeAnnotation, target: &BlockTarget) -> String {
+        let tmp = format!("match_tmp_{}", self.tag.get_num());
+        let deref = match ta.try_annotation(self.tag.get_num(), "AutoRefType", 0) {
+            Some(Type::AutoRef(_, AutoRefTag::Ref)) | Some(Type::AutoRef(_, AutoRefTag::MutRef)) => "*",
+            _ => "",
+        };
+        let head = ta.lowering.statement(|| format!("{} const& {} = {}{};\n", ta.annotation(self.tag.get_num(), "MatchType", 0).transpile(ta), tmp, deref, self.expr.transpile(ta)));
+        // the match is exhaustive, so the last arm needs no test
+        let last = self.arms.len() - 1;
+        let arms = self.arms.iter().enumerate().map(|(i, arm)| {
+            let mut conds = Vec::new();
+            if i < last {
+                arm.pattern.transpile_conditions(ta, &tmp, &mut conds);
+            }
+            let cond = if conds.is_empty() { String::new() } else { format!("if({}) ", conds.join(" && ")) };
+            format!("{}{{\n{}{}}}\n", cond, arm.pattern.transpile_binds(ta, &tmp), arm.block.transpile_to(ta, target))
+        }).collect::<Vec<_>>().join("else ");
+        format!("{}{}", head, arms)
+    }
+}
+
+impl GenType for MatchExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        let expr_type = self.expr.gen_type(equs, trs)?;
+        let match_type = self.tag.generate_not_void_type_variable("MatchType", 0, equs);
+        if self.has_variant_arm() {
+            equs.add_equation(expr_type, Type::AutoRef(Box::new(match_type.clone()), AutoRefTag::Tag(self.tag.clone())));
+        }
+        else {
+            equs.add_equation(expr_type, match_type.clone());
+        }
+        let return_type = self.tag.generate_type_variable("ReturnType", 0, equs);
+        let mut rows: Vec<Vec<Space>> = Vec::new();
+        for arm in self.arms.iter() {
+            equs.into_scope();
+            let space = arm.pattern.gen_type(match_type.clone(), equs, trs, &mut HashSet::new());
+            let space = match space {
+                Ok(space) => space,
+                Err(err) => {
+                    equs.out_scope();
+                    return Err(err)
+                }
+            };
+            if !useful(&rows, std::slice::from_ref(&space)) {
+                equs.out_scope();
+                return Err(arm.pattern.get_span().error_message("unreachable pattern"))
+            }
+            rows.push(vec![space]);
+            let res = arm.block.gen_type(equs, trs);
+            equs.out_scope();
+            equs.add_equation(return_type.clone(), res?);
+        }
+        if let Some(witness) = missing(&rows, 1) {
+            return Err(self.get_span().error_message(&format!("non-exhaustive patterns: {} not covered", witness[0])))
+        }
+        Ok(return_type)
+    }
+}
+
+impl Transpile for MatchExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        if self.is_void(ta) {
+            format!("{{\n{}}}", self.transpile_to(ta, &BlockTarget::Void))
+        }
+        else {
+            let ty = ta.annotation(self.tag.get_num(), "ReturnType", 0).transpile(ta);
+            ta.lowering.temporary(self.tag.get_num(), ty, |target| format!("{{\n{}}}\n", self.transpile_to(ta, target)))
+        }
+    }
+}
+
+impl MutCheck for MatchExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        self.expr.mut_check(ta, vars)?;
+        for arm in self.arms.iter() {
+            vars.into_scope();
+            arm.pattern.regist_variables(vars);
+            let res = arm.block.mut_check(ta, vars);
+            vars.out_scope();
+            res?;
+        }
+        Ok(MutResult::NotMut)
+    }
+}
+
+impl Resolve for MatchExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.expr.resolve(scope)?;
+        for arm in self.arms.iter_mut() {
+            arm.pattern.resolve(scope)?;
+            arm.block.resolve(scope)?;
+        }
+        Ok(())
+    }
+}
+
+impl Pattern {
+    pub fn get_span(&self) -> Span {
+        match *self {
+            Pattern::Wildcard(ref tag) | Pattern::Literal(_, ref tag) | Pattern::Tuple(_, ref tag) => tag.get_span(),
+            Pattern::Binding(ref id) => id.get_span(),
+            Pattern::Variant(ref sign, ref variant, _) => sign.id.id.get_span().merge(&variant.get_span()),
+        }
+    }
+    fn gen_type(&self, ty: Type, equs: &mut TypeEquations, trs: &TraitsInfo, names: &mut HashSet<String>) -> Result<Space, String> {
+        match *self {
+            Pattern::Wildcard(_) => Ok(Space::Any),
+            Pattern::Binding(ref id) => {
+                if !names.insert(id.name.clone()) {
+                    return Err(id.get_span().error_message(&format!("identifier {} is bound more than once in the same pattern", id.name)))
+                }
+                let alpha = id.generate_not_void_type_variable("MatchBindType", 0, equs);
+                equs.regist_variable(Variable::from_identifier(id.clone()), alpha.clone());
+                equs.add_equation(alpha, ty);
+                Ok(Space::Any)
+            }
+            Pattern::Literal(ref lit, _) => {
+                let lit_type = lit.gen_type(equs, trs)?;
+                equs.add_equation(ty, lit_type);
+                let ctor = Ctor::Literal(literal_key(lit));
+                let ctors = if let Literal::Boolean(_) = *lit {
+                    vec![Ctor::Literal("true".to_string()), Ctor::Literal("false".to_string())]
+                }
+                else {
+                    Vec::new()
+                };
+                Ok(Space::Ctor(ctor, Vec::new(), ctors))
+            }
+            Pattern::Tuple(ref elems, ref tag) => {
+                let elem_types = (0..elems.len()).map(|i| tag.generate_not_void_type_variable("PatternType", i, equs)).collect::<Vec<_>>();
+                equs.add_equation(ty, Type::Tuple(elem_types.clone()));
+                let spaces = elems.iter().zip(elem_types).map(|(elem, elem_type)| elem.gen_type(elem_type, equs, trs, names)).collect::<Result<Vec<_>, _>>()?;
+                Ok(Space::Ctor(Ctor::Tuple(elems.len()), spaces, vec![Ctor::Tuple(elems.len())]))
+            }
+            Pattern::Variant(ref sign, ref variant, ref elems) => {
+                let (enum_ty, def) = match search_enum_type(sign, equs, trs)? {
+                    Some(res) => res,
+                    None => return Err(sign.id.id.get_span().error_message(&format!("{} is not enum", sign.id.id.name))),
+                };
+                let gens = match enum_ty {
+                    Type::Generics(_, ref gens) => gens.clone(),
+                    _ => unreachable!(),
+                };
+                let payload = def.get_variant_types(equs, trs, &gens, variant)?;
+                if payload.len() != elems.len() {
+                    return Err(variant.get_span().error_message(&format!("variant {}::{} has {} fields, but pattern has {}", def.struct_id.id.name, variant.name, payload.len(), elems.len())))
+                }
+                let alpha = variant.generate_not_void_type_variable("PatternType", 0, equs);
+                equs.add_equation(alpha, enum_ty.clone());
+                equs.add_equation(ty, enum_ty);
+                let spaces = elems.iter().zip(payload).map(|(elem, elem_type)| elem.gen_type(elem_type, equs, trs, names)).collect::<Result<Vec<_>, _>>()?;
+                let info = def.get_enum_info().unwrap();
+                let ctors = info.variants_order.iter()
+                    .map(|v| Ctor::Variant(format!("{}::{}", def.struct_id.id.name, v.name), info.variants[v].len()))
+                    .collect::<Vec<_>>();
+                let index = info.get_variant_index(variant).unwrap();
+                Ok(Space::Ctor(ctors[index].clone(), spaces, ctors))
+            }
+        }
+    }
+    // conditions are pushed outermost first, so `&&` checks the variant before reading its payload
+    fn transpile_conditions(&self, ta: &TypeAnnotation, value: &str, conds: &mut Vec<String>) {
+        match *self {
+            Pattern::Wildcard(_) | Pattern::Binding(_) => {}
+            Pattern::Literal(ref lit, _) => conds.push(format!("{} == {}", value, lit.transpile(ta))),
+            Pattern::Tuple(ref elems, _) => {
+                for (i, elem) in elems.iter().enumerate() {
+                    elem.transpile_conditions(ta, &format!("std::get<{}>({})", i, value), conds);
+                }
+            }
+            Pattern::Variant(_, ref variant, ref elems) => {
+                let idx = self.variant_index(ta, variant);
+                conds.push(format!("{}.data.index() == {}", value, idx));
+                for (i, elem) in elems.iter().enumerate() {
+                    elem.transpile_conditions(ta, &format!("std::get<{}>(std::get<{}>({}.data))", i, idx, value), conds);
+                }
+            }
+        }
+    }
+    fn transpile_binds(&self, ta: &TypeAnnotation, value: &str) -> String {
+        match *self {
+            Pattern::Wildcard(_) | Pattern::Literal(..) => String::new(),
+            Pattern::Binding(ref id) => format!("{} const& {} = {};\n", ta.annotation(id.get_tag_number(), "MatchBindType", 0).transpile(ta), id.into_string(), value),
+            Pattern::Tuple(ref elems, _) => {
+                elems.iter().enumerate().map(|(i, elem)| elem.transpile_binds(ta, &format!("std::get<{}>({})", i, value))).collect::<Vec<_>>().join("")
+            }
+            Pattern::Variant(_, ref variant, ref elems) => {
+                let idx = self.variant_index(ta, variant);
+                elems.iter().enumerate().map(|(i, elem)| elem.transpile_binds(ta, &format!("std::get<{}>(std::get<{}>({}.data))", i, idx, value))).collect::<Vec<_>>().join("")
+            }
+        }
+    }
+    fn variant_index(&self, ta: &TypeAnnotation, variant: &Identifier) -> usize {
+        match ta.annotation(variant.get_tag_number(), "PatternType", 0) {
+            Type::Generics(ref id, _) => ta.get_enum_variant_index(id, variant),
+            _ => unreachable!("pattern type is not enum"),
+        }
+    }
+    fn regist_variables(&self, vars: &mut VariablesInfo) {
+        match *self {
+            Pattern::Binding(ref id) => vars.regist_variable(id, false),
+            Pattern::Tuple(ref elems, _) | Pattern::Variant(_, _, ref elems) => {
+                for elem in elems.iter() {
+                    elem.regist_variables(vars);
+                }
+            }
+            Pattern::Wildcard(_) | Pattern::Literal(..) => {}
+        }
+    }
+}
+
+impl Resolve for Pattern {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        match *self {
+            Pattern::Variant(ref mut sign, _, ref mut elems) => {
+                sign.resolve(scope)?;
+                elems.iter_mut().try_for_each(|elem| elem.resolve(scope))
+            }
+            Pattern::Tuple(ref mut elems, _) => elems.iter_mut().try_for_each(|elem| elem.resolve(scope)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn literal_key(lit: &Literal) -> String {
+    match *lit {
+        Literal::U64(LiteralU64 { ref number, .. }) | Literal::I64(LiteralI64 { ref number, .. }) | Literal::Int(LiteralInt { ref number, .. }) => {
+            number.parse::<u128>().map(|n| n.to_string()).unwrap_or_else(|_| number.clone())
+        }
+        Literal::Boolean(Boolean::True) => "true".to_string(),
+        Literal::Boolean(Boolean::False) => "false".to_string(),
+        Literal::Char(LiteralChar { ref text }) => format!("'{}'", text),
+        Literal::F64(_) | Literal::Str(_) => unreachable!("float and string literals are not patterns"),
+    }
+}
+
+// patterns as seen by the exhaustiveness check, a constructor carries the
+// constructors of its type when there are finitely many of them
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    Variant(String, usize),
+    Tuple(usize),
+    Literal(String),
+}
+
+#[derive(Debug, Clone)]
+enum Space {
+    Any,
+    Ctor(Ctor, Vec<Space>, Vec<Ctor>),
+}
+
+impl Ctor {
+    fn arity(&self) -> usize {
+        match *self {
+            Ctor::Variant(_, n) | Ctor::Tuple(n) => n,
+            Ctor::Literal(_) => 0,
+        }
+    }
+    fn show(&self, args: &[String]) -> String {
+        match *self {
+            Ctor::Variant(ref name, 0) => name.clone(),
+            Ctor::Variant(ref name, _) => format!("{}({})", name, args.join(", ")),
+            Ctor::Tuple(1) => format!("({},)", args[0]),
+            Ctor::Tuple(_) => format!("({})", args.join(", ")),
+            Ctor::Literal(ref lit) => lit.clone(),
+        }
+    }
+}
+
+fn specialize(rows: &[Vec<Space>], ctor: &Ctor) -> Vec<Vec<Space>> {
+    rows.iter().filter_map(|row| {
+        let head = match row[0] {
+            Space::Any => vec![Space::Any; ctor.arity()],
+            Space::Ctor(ref c, ref spaces, _) if c == ctor => spaces.clone(),
+            Space::Ctor(..) => return None,
+        };
+        Some(head.into_iter().chain(row[1..].iter().cloned()).collect())
+    }).collect()
+}
+
+fn default_rows(rows: &[Vec<Space>]) -> Vec<Vec<Space>> {
+    rows.iter().filter(|row| matches!(row[0], Space::Any)).map(|row| row[1..].to_vec()).collect()
+}
+
+// the constructors used in the first column, and all constructors of its type if they are all used
+fn head_ctors(rows: &[Vec<Space>]) -> (Vec<&Ctor>, Option<&Vec<Ctor>>) {
+    let heads = rows.iter().filter_map(|row| match row[0] {
+        Space::Ctor(ref c, _, _) => Some(c),
+        Space::Any => None,
+    }).collect::<Vec<_>>();
+    let all = rows.iter().find_map(|row| match row[0] {
+        Space::Ctor(_, _, ref all) => Some(all),
+        Space::Any => None,
+    });
+    let complete = all.filter(|all| !all.is_empty() && all.iter().all(|c| heads.contains(&c)));
+    (heads, complete)
+}
+
+// whether some value matches `q` but none of `rows`
+fn useful(rows: &[Vec<Space>], q: &[Space]) -> bool {
+    if q.is_empty() {
+        return rows.is_empty()
+    }
+    match q[0] {
+        Space::Ctor(ref c, ref spaces, _) => {
+            let q = spaces.iter().chain(q[1..].iter()).cloned().collect::<Vec<_>>();
+            useful(&specialize(rows, c), &q)
+        }
+        Space::Any => match head_ctors(rows).1 {
+            Some(all) => all.iter().any(|c| {
+                let q = vec![Space::Any; c.arity()].into_iter().chain(q[1..].iter().cloned()).collect::<Vec<_>>();
+                useful(&specialize(rows, c), &q)
+            }),
+            None => useful(&default_rows(rows), &q[1..]),
+        }
+    }
+}
+
+// a value of `width` columns no row matches, written as one pattern per column
+fn missing(rows: &[Vec<Space>], width: usize) -> Option<Vec<String>> {
+    if width == 0 {
+        return if rows.is_empty() { Some(Vec::new()) } else { None }
+    }
+    let (heads, complete) = head_ctors(rows);
+    match complete {
+        Some(all) => all.iter().find_map(|c| {
+            missing(&specialize(rows, c), c.arity() + width - 1).map(|witness| {
+                let (args, rest) = witness.split_at(c.arity());
+                std::iter::once(c.show(args)).chain(rest.iter().cloned()).collect()
+            })
+        }),
+        None => missing(&default_rows(rows), width - 1).map(|witness| {
+            let all = rows.iter().find_map(|row| match row[0] {
+                Space::Ctor(_, _, ref all) => Some(all),
+                Space::Any => None,
+            });
+            let head = match all.and_then(|all| all.iter().find(|c| !heads.contains(c))) {
+                Some(c) => c.show(&vec!["_".to_string(); c.arity()]),
+                None => "_".to_string(),
+            };
+            std::iter::once(head).chain(witness).collect()
+        }),
+    }
+}
+
+fn parse_variant_path(s: &str) -> IResult<&str, (TypeSign, Identifier)> {
+    alt((
+        map(tuple((parse_type_sign, multispace0, tag("::"), multispace0, parse_identifier)), |(sign, _, _, _, variant)| (sign, variant)),
+        map(parse_item_member, |(id, variant)| (TypeSign { id: TypeId { id, const_type: None }, gens: Vec::new() }, variant)),
+    ))(s)
+}
+
+fn parse_variant_pattern(s: &str) -> IResult<&str, Pattern> {
+    let (s, ((sign, variant), _, elems)) = tuple((parse_variant_path, multispace0,
+            opt(tuple((char('('), multispace0, separated_list0(tuple((multispace0, char(','), multispace0)), parse_pattern), multispace0, opt(tuple((char(','), multispace0))), char(')'))))))(s)?;
+    let elems = elems.map(|(_, _, elems, _, _, _)| elems).unwrap_or(Vec::new());
+    Ok((s, Pattern::Variant(sign, variant, elems)))
+}
+
+fn parse_tuple_pattern(s: &str) -> IResult<&str, Pattern> {
+    let start = s;
+    let (s, (_, _, head, _, tails, comma, _, _)) = tuple((char('('), multispace0, parse_pattern, multispace0,
+            many0(tuple((char(','), multispace0, parse_pattern, multispace0))), opt(char(',')), multispace0, char(')')))(s)?;
+    if tails.is_empty() && comma.is_none() {
+        return Ok((s, head))
+    }
+    let elems = std::iter::once(head).chain(tails.into_iter().map(|(_, _, elem, _)| elem)).collect();
+    Ok((s, Pattern::Tuple(elems, Tag::with_span(Span::between(start, s)))))
+}
+
+fn parse_literal_pattern(s: &str) -> IResult<&str, Pattern> {
+    let start = s;
+    let (s, lit) = parse_pattern_literal(s)?;
+    Ok((s, Pattern::Literal(lit, Tag::with_span(Span::between(start, s)))))
+}
+
+fn parse_binding_pattern(s: &str) -> IResult<&str, Pattern> {
+    let (s, id) = parse_identifier(s)?;
+    if is_wildcard(&id) {
+        Ok((s, Pattern::Wildcard(id.tag)))
+    }
+    else {
+        Ok((s, Pattern::Binding(id)))
+    }
+}
+
+fn parse_pattern(s: &str) -> IResult<&str, Pattern> {
+    alt((parse_tuple_pattern, parse_variant_pattern, parse_literal_pattern, parse_binding_pattern))(s)
+}
+
+fn parse_arm_block(s: &str) -> IResult<&str, Block> {
+    let (s, (_, block, _)) = tuple((char('{'), parse_block, char('}')))(s)?;
+    Ok((s, block))
+}
+
+fn parse_arm_expression(s: &str) -> IResult<&str, Block> {
+    let (s, expr) = parse_expression(s)?;
+    Ok((s, Block { statements: Vec::new(), return_exp: Some(expr) }))
+}
+
+fn parse_match_arm(s: &str) -> IResult<&str, MatchArm> {
+    let (s, (pattern, _, _, _, block, _, _)) = tuple((parse_pattern, multispace0, tag("=>"), multispace0, alt((parse_arm_block, parse_arm_expression)), multispace0, opt(char(','))))(s)?;
+    Ok((s, MatchArm { pattern, block }))
+}
+
+pub fn parse_match_expr(s: &str) -> IResult<&str, Expression> {
+    let start = s;
+    let (s, (_, _, expr, _, _, _, arms, _)) = tuple((tag("match"), multispace1, parse_expression, multispace0, char('{'), multispace0,
+                        many0(tuple((parse_match_arm, multispace0))), char('}')))(s)?;
+    let arms = arms.into_iter().map(|(arm, _)| arm).collect();
+    Ok((s, Expression::MatchExpr(Box::new(MatchExpr { expr, arms, tag: Tag::with_span(Span::between(start, s)) }))))
+}
+
+#[test]
+fn parse_match_expr_test() {
+    println!("{:?}", parse_match_expr("match x { Option::Some(y) => y, Option::None => { 0i64 } }"));
+    println!("{:?}", parse_match_expr("match x { Edge::To(_, c) => c, _ => 0i64, }"));
+    assert!(parse_match_expr("match x { Option::Some((a, b)) => a, Option::Some(Option::None) => b, 7 => c, (true, 'a') => d, (y,) => y, (z) => z }").is_ok());
+    assert!(parse_match_expr("match x { 1.5 => y }").is_err());
+}
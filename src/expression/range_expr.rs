@@ -0,0 +1,91 @@
+use nom::IResult;
+use nom::combinator::*;
+use nom::sequence::*;
+use nom::bytes::complete::*;
+
+use crate::comment::multispace0;
+use crate::identifier::*;
+use crate::expression::{ Expression, ExpOr, ParseExpression };
+use crate::type_id::TypeId;
+use crate::type_spec::*;
+use crate::unify::*;
+use crate::trans::*;
+use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
+
+#[derive(Debug)]
+pub struct RangeExpr {
+    start: Expression,
+    end: Expression,
+    tag: Tag,
+}
+
+impl RangeExpr {
+    pub fn get_span(&self) -> Span {
+        self.tag.get_span()
+    }
+}
+
+impl GenType for RangeExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        let sign = TypeSign { id: TypeId::from_str("Range"), gens: Vec::new() };
+        if trs.search_typeid(&sign.id).is_err() {
+            return Err(self.get_span().error_message("struct Range is not defined, import \"std/iter.niu\""))
+        }
+        let range_type = sign.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+        let item_type = match range_type {
+            Type::Generics(_, ref gens) if gens.len() == 1 => gens[0].clone(),
+            _ => return Err(self.get_span().error_message("struct Range must have one generics argument")),
+        };
+        let start = self.start.gen_type(equs, trs)?;
+        let end = self.end.gen_type(equs, trs)?;
+        let before = equs.set_span(self.get_span());
+        equs.add_equation(item_type.clone(), start);
+        equs.add_equation(item_type, end);
+        equs.set_span(before);
+        let alpha = self.tag.generate_not_void_type_variable("RangeType", 0, equs);
+        equs.add_equation(alpha.clone(), range_type);
+        Ok(alpha)
+    }
+}
+
+impl Transpile for RangeExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let range_type = ta.annotation(self.tag.get_num(), "RangeType", 0).transpile(ta);
+        format!("{}({}, {})", range_type, self.start.transpile(ta), self.end.transpile(ta))
+    }
+}
+
+impl MutCheck for RangeExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        self.start.mut_check(ta, vars)?;
+        self.end.mut_check(ta, vars)?;
+        Ok(MutResult::NotMut)
+    }
+}
+
+impl Resolve for RangeExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.start.resolve(scope)?;
+        self.end.resolve(scope)
+    }
+}
+
+pub fn parse_range_or_expor(s: &str) -> IResult<&str, Expression> {
+    let start_s = s;
+    let (s, (start, end)) = tuple((ExpOr::parse_expression, opt(preceded(tuple((multispace0, tag(".."), multispace0)), ExpOr::parse_expression))))(s)?;
+    match end {
+        Some(end) => {
+            let tag = Tag::with_span(Span::between(start_s, s));
+            Ok((s, Expression::RangeExpr(Box::new(RangeExpr { start: Expression::Expression(start), end: Expression::Expression(end), tag }))))
+        }
+        None => Ok((s, Expression::Expression(start))),
+    }
+}
+
+#[test]
+fn parse_range_expr_test() {
+    println!("{:?}", parse_range_or_expor("0i64..n"));
+    println!("{:?}", parse_range_or_expor("a + 1u64 .. b * 2u64"));
+}
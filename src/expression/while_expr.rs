@@ -0,0 +1,71 @@
+use nom::IResult;
+use nom::character::complete::*;
+use nom::sequence::*;
+
+use crate::comment::{ multispace0, multispace1 };
+use crate::identifier::*;
+use crate::expression::{ Expression, parse_expression };
+use crate::block::{ Block, parse_block };
+use crate::unify::*;
+use crate::trans::*;
+use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
+
+#[derive(Debug)]
+pub struct WhileExpr {
+    cond: Expression,
+    block: Block,
+    pub span: Span,
+}
+
+impl GenType for WhileExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        let cond_type = self.cond.gen_type(equs, trs)?;
+        equs.add_equation(cond_type, Type::from_str("bool"));
+        equs.into_loop(None);
+        let bl_type = self.block.gen_type(equs, trs);
+        equs.out_loop();
+        equs.add_equation(bl_type?, Type::from_str("void"));
+        Ok(Type::from_str("void"))
+    }
+}
+
+impl Transpile for WhileExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let (prelude, cond) = ta.lowering.capture(|| self.cond.transpile(ta));
+        let block = ta.lowering.in_loop_body(None, || self.block.transpile_for_void(ta));
+        if prelude.is_empty() {
+            format!("while({}) {{\n{}}}", cond, block)
+        }
+        else {
+            format!("while(true) {{\n{}if(!({})) break;\n{}}}", prelude, cond, block)
+        }
+    }
+}
+
+impl MutCheck for WhileExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        self.cond.mut_check(ta, vars)?;
+        self.block.mut_check(ta, vars)?;
+        Ok(MutResult::NotMut)
+    }
+}
+
+impl Resolve for WhileExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.cond.resolve(scope)?;
+        self.block.resolve(scope)
+    }
+}
+
+pub fn parse_while_expr(s: &str) -> IResult<&str, Expression> {
+    let start = s;
+    let (s, (_, _, cond, _, _, block, _)) = tuple((parse_keyword("while"), multispace1, parse_expression, multispace0, char('{'), parse_block, char('}')))(s)?;
+    Ok((s, Expression::WhileExpr(Box::new(WhileExpr { cond, block, span: Span::between(start, s) }))))
+}
+
+#[test]
+fn parse_while_expr_test() {
+    println!("{:?}", parse_while_expr("while i < n { i = i + 1; }"));
+}
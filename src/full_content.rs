@@ -7,14 +7,23 @@ use nom::character::complete::*;
 use nom::sequence::*;
 use nom::multi::*;
 use nom::branch::*;
+use nom::combinator::*;
 
 
+use crate::comment::{ multispace0, multispace1, parse_doc_comments };
+use crate::identifier::parse_identifier;
 use crate::func_definition::{ FuncDefinition, parse_func_definition };
 use crate::traits::*;
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
 use crate::structs::*;
+use crate::type_alias::*;
+use crate::module::*;
+use crate::manifest::ImportRoot;
+use crate::tree_shaking::*;
+use crate::snippet::*;
+use crate::diagnostics::{ regist_source, set_current_source, parse_error_message };
 
 #[derive(Debug)]
 pub struct FullContent {
@@ -22,6 +31,7 @@ pub struct FullContent {
     pub traits: Vec<TraitDefinition>,
     pub impls: Vec<ImplDefinition>,
     pub funcs: Vec<FuncDefinition>,
+    pub aliases: Vec<TypeAliasDefinition>,
 }
 
 impl FullContent {
@@ -31,15 +41,51 @@ impl FullContent {
         }
         Ok(())
     }
-    fn regist_impls(&mut self, equs: &mut TypeEquations, trs: &mut TraitsInfo) -> Result<(), String> {
+    fn regist_type_aliases(&self, trs: &mut TraitsInfo) -> Result<(), String> {
+        let aliases = sort_type_aliases(&self.aliases)?;
+        for alias in aliases.iter() {
+            trs.regist_type_alias(alias)?;
+        }
+        for alias in aliases.iter() {
+            alias.check_definition(trs)?;
+        }
+        Ok(())
+    }
+    fn expand_derives(&mut self, ta: &mut TypeAnnotation) -> Result<(), String> {
+        for (i, st) in self.structs.iter().enumerate() {
+            let mut impls = st.derive_impls()?;
+            // the std::hash specialisation in the struct body calls the derived Hash impl
+            let hash = impls.iter().filter(|im| im.get_trait_id().id.into_string() == "Hash")
+                .map(|im| im.trait_spec.get_tag().get_num()).collect();
+            ta.regist_selected_impls(ItemIndex::Struct(i), hash);
+            self.impls.append(&mut impls);
+        }
+        Ok(())
+    }
+    fn regist_impls(&mut self, equs: &mut TypeEquations, trs: &mut TraitsInfo, ta: &mut TypeAnnotation) -> Result<(), String> {
         for im in self.impls.iter() {
             trs.preregist_impl_candidate(im);
         }
-        for im in self.impls.iter() {
+        for (i, im) in self.impls.iter().enumerate() {
             trs.regist_impl_candidate(equs, im)?;
+            ta.regist_selected_impls(ItemIndex::Impl(i), trs.take_selected_impls());
         }
         Ok(())
     }
+    fn find_trait(&self, trait_id: &TraitId) -> Option<&TraitDefinition> {
+        self.traits.iter().find(|tr| tr.trait_id == *trait_id)
+    }
+    fn trait_depth(&self, trait_id: &TraitId, visited: &mut HashSet<TraitId>) -> usize {
+        if !visited.insert(trait_id.clone()) {
+            return 0
+        }
+        match self.find_trait(trait_id) {
+            Some(tr) => tr.where_sec.self_supertraits()
+                .map(|sup| self.trait_depth(&sup.trait_id, visited) + 1)
+                .max().unwrap_or(0),
+            None => 0,
+        }
+    }
     fn regist_self_impls(&mut self, trs: &mut TraitsInfo) -> Result<(), String> {
         for st in self.structs.iter() {
             trs.regist_self_impl(st.get_impl_self_def())?;
@@ -57,22 +103,43 @@ impl FullContent {
             ta.regist_structs_info(st.get_member_def());
         }
 
+        self.regist_type_aliases(&mut trs)?;
         self.regist_traits(&mut trs)?;
-        self.regist_impls(&mut equs, &mut trs)?;
+        let mut builtin_impls = trs.builtin_impls()?;
+        self.impls.append(&mut builtin_impls);
+        self.expand_derives(&mut ta)?;
+        trs.take_selected_impls();
+        self.regist_impls(&mut equs, &mut trs, &mut ta)?;
         self.regist_self_impls(&mut trs)?;
 
-        for st in self.structs.iter() {
+        // every impl selected while checking an item becomes a dependency of that item
+        for (i, st) in self.structs.iter().enumerate() {
+            st.check_derives(&mut equs, &trs)?;
+            ta.regist_selected_impls(ItemIndex::Struct(i), trs.take_selected_impls());
+        }
+
+        for (i, st) in self.structs.iter().enumerate() {
             st.unify_require_methods(&mut equs, &mut trs)?;
+            ta.regist_selected_impls(ItemIndex::Struct(i), trs.take_selected_impls());
         }
 
-        for im in self.impls.iter() {
+        for (i, im) in self.impls.iter().enumerate() {
             im.unify_require_methods(&mut equs, &mut trs)?;
+            ta.regist_selected_impls(ItemIndex::Impl(i), trs.take_selected_impls());
+        }
+
+        for (i, tr) in self.traits.iter().enumerate() {
+            tr.unify_default_methods(&mut equs, &trs)?;
+            ta.regist_selected_impls(ItemIndex::Trait(i), trs.take_selected_impls());
         }
 
         for f in self.funcs.iter() {
             equs.regist_func_info(f);
             ta.regist_func_info(f);
+        }
+        for (i, f) in self.funcs.iter().enumerate() {
             f.unify_definition(&mut equs, &mut trs)?;
+            ta.regist_selected_impls(ItemIndex::Func(i), trs.take_selected_impls());
         }
 
         for TypeSubst { tv, t } in equs.take_substs() {
@@ -92,13 +159,52 @@ impl FullContent {
             im.mut_check(ta, &mut vars)?;
         }
 
+        for tr in self.traits.iter() {
+            tr.mut_check(ta, &mut vars)?;
+        }
+
         for f in self.funcs.iter() {
             f.mut_check(ta, &mut vars)?;
         }
         Ok(())
     }
-    pub fn transpile(&self, ta: &mut TypeAnnotation) -> String {
-        let mut res = "#include <bits/stdc++.h>\n\n".to_string();
+    fn dependency_graph(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        for (i, t) in self.structs.iter().enumerate() {
+            graph.regist_item(ItemRef::Type(t.get_id().id.into_string()), ItemIndex::Struct(i));
+        }
+        for (i, t) in self.traits.iter().enumerate() {
+            graph.regist_item(ItemRef::Trait(t.trait_id.id.into_string()), ItemIndex::Trait(i));
+        }
+        for (i, alias) in self.aliases.iter().enumerate() {
+            graph.regist_item(ItemRef::Type(alias.get_id().id.into_string()), ItemIndex::Alias(i));
+        }
+        for (i, f) in self.funcs.iter().enumerate() {
+            graph.regist_item(ItemRef::Func(f.func_id.into_string()), ItemIndex::Func(i));
+        }
+        for (i, im) in self.impls.iter().enumerate() {
+            graph.regist_item(ItemRef::Impl(im.trait_spec.get_tag().get_num()), ItemIndex::Impl(i));
+        }
+        graph
+    }
+    pub fn entry_items(&self, names: &[String]) -> Result<Vec<ItemRef>, String> {
+        let graph = self.dependency_graph();
+        names.iter().map(|name| {
+            vec![ItemRef::Func(name.clone()), ItemRef::Type(name.clone()), ItemRef::Trait(name.clone())].into_iter()
+                .find(|item| graph.contains(item))
+                .ok_or(format!("entry item {} is not found", name))
+        }).collect()
+    }
+    pub fn main_items(&self) -> Vec<ItemRef> {
+        self.funcs.iter().map(|f| f.func_id.into_string()).filter(|name| name == "main").map(ItemRef::Func).collect()
+    }
+    pub fn transpile(&self, ta: &mut TypeAnnotation, roots: &[ItemRef], mode: &OutputMode) -> String {
+        let mut graph = self.dependency_graph();
+        let mut pieces = Vec::new();
+        let emit = |pieces: &mut Vec<(ItemIndex, String, HashSet<ItemRef>)>, index: ItemIndex, s: String, ta: &TypeAnnotation| {
+            pieces.push((index, s, ta.references.take()));
+        };
+        ta.references.take();
         let mut operators = HashMap::new();
         let opes_str = ["Index", "IndexMut", "BitOr", "BitXor", "BitAnd", "Shl", "Shr", "Add", "Sub", "Mul", "Div", "Rem"];
         for ope in opes_str {
@@ -113,53 +219,124 @@ impl FullContent {
             }
         }
         // structs definition
-        for t in self.structs.iter() {
+        for (i, t) in self.structs.iter().enumerate() {
             ta.self_type = Some(t.transpile_self_type());
             let s = t.transpile_definition(ta);
-            res.push_str(&s);
+            emit(&mut pieces, ItemIndex::Struct(i), s, ta);
             ta.self_type = None;
         }
         // traits definition
-        for t in self.traits.iter() {
+        for (i, t) in self.traits.iter().enumerate() {
             let s = t.transpile(ta);
-            res.push_str(&s);
+            emit(&mut pieces, ItemIndex::Trait(i), s, ta);
         }
-        // impls definition
-        for i in self.impls.iter() {
+        // type aliases
+        for alias in sort_type_aliases(&self.aliases).unwrap() {
+            let i = self.aliases.iter().position(|a| a.get_id() == alias.get_id()).unwrap();
+            emit(&mut pieces, ItemIndex::Alias(i), alias.transpile(ta), ta);
+        }
+        // impls definition, ordered so that the impls of supertraits come first
+        let mut impls = self.impls.iter().enumerate().collect::<Vec<_>>();
+        impls.sort_by_key(|(_, i)| self.trait_depth(&i.get_trait_id(), &mut HashSet::new()));
+        let mut definitions = Vec::new();
+        for (idx, i) in impls {
             ta.self_type = Some(i.impl_ty.transpile(ta));
-            let s = i.transpile(ta);
-            res.push_str(&s);
+            let s = i.transpile_definition(ta, self.find_trait(&i.get_trait_id()));
+            let mut definition = Vec::new();
+            emit(&mut definition, ItemIndex::Impl(idx), s, ta);
+            definitions.push(definition);
             ta.self_type = None;
         }
+        append_in_dependency_order(&mut pieces, definitions, &graph, ta);
         // functions definition
-        for f in self.funcs.iter() {
-            let s = f.transpile_definition_only(ta, "", false);
-            res.push_str(&s);
-            res.push_str(";\n");
+        for (i, f) in self.funcs.iter().enumerate() {
+            emit(&mut pieces, ItemIndex::Func(i), f.transpile_declaration(ta), ta);
         }
-        // structs implementation
-        for t in self.structs.iter() {
+        // structs implementation, ordered so that members used by value are complete
+        let mut members = Vec::new();
+        let mut bodies = Vec::new();
+        for (i, t) in self.structs.iter().enumerate() {
             ta.self_type = Some(t.transpile_self_type());
+            t.transpile_member_types(ta);
+            members.push((ItemIndex::Struct(i), ta.references.take()));
             let st_id = t.get_id();
             let opes = operators.iter()
                 .filter_map(|(k, set)| if set.contains(&st_id) { Some(k.clone()) } else { None })
                 .collect::<Vec<_>>();
             let s = t.transpile(ta, opes);
-            res.push_str(&s);
+            let mut body = Vec::new();
+            emit(&mut body, ItemIndex::Struct(i), s, ta);
+            emit(&mut body, ItemIndex::Struct(i), t.transpile_derive_hash(ta, mode), ta);
+            bodies.push(body);
             ta.self_type = None;
         }
-        // functions of impls implementation
-        for i in self.impls.iter() {
+        for k in graph.dependency_order(&members.iter().map(|(index, refs)| (*index, refs)).collect::<Vec<_>>()) {
+            pieces.append(&mut bodies[k]);
+        }
+        // functions of impls and functions implementation
+        let mut bodies = Vec::new();
+        for (idx, i) in self.impls.iter().enumerate() {
             ta.self_type = Some(i.impl_ty.transpile(ta));
-            let s = i.transpile_functions(ta);
-            res.push_str(&s);
+            let s = i.transpile_functions(ta, self.find_trait(&i.get_trait_id()));
+            let mut body = Vec::new();
+            emit(&mut body, ItemIndex::Impl(idx), s, ta);
+            bodies.push(body);
             ta.self_type = None;
         }
-        for f in self.funcs.iter() {
+        for (i, f) in self.funcs.iter().enumerate() {
             let s = f.transpile(ta, false);
-            res.push_str(&s);
+            let doc = if s.is_empty() { String::new() } else { f.transpile_doc() };
+            let mut body = Vec::new();
+            emit(&mut body, ItemIndex::Func(i), format!("{}{}", doc, s), ta);
+            bodies.push(body);
+        }
+        append_in_dependency_order(&mut pieces, bodies, &graph, ta);
+        for (index, _, refs) in pieces.iter() {
+            graph.add_dependencies(*index, refs.clone());
+            graph.add_dependencies(*index, ta.selected_impls(*index));
+        }
+        let reachable = if roots.is_empty() { None } else { Some(graph.reachable(roots)) };
+        let is_entry_point = |index: &ItemIndex| match *index {
+            ItemIndex::Func(i) => *mode != OutputMode::Program && self.funcs[i].func_id.into_string() == "main",
+            _ => false,
+        };
+        let mut counts = HashMap::new();
+        let mut res = String::new();
+        for (index, s, _) in pieces {
+            let count = counts.entry(index).or_insert(0);
+            *count += 1;
+            if reachable.as_ref().is_none_or(|reachable| reachable.contains(&index)) && !is_entry_point(&index) && !s.is_empty() {
+                match *mode {
+                    OutputMode::Program => res.push_str(&s),
+                    OutputMode::Library(ref namespace) => res.push_str(&guard_declaration(namespace, &format!("{} {}", self.item_key(index), count), &s)),
+                }
+            }
         }
-        res
+        transpile_output(mode, &res)
+    }
+    fn item_key(&self, index: ItemIndex) -> String {
+        let (span, key) = match index {
+            ItemIndex::Struct(i) => (self.structs[i].get_id().id.get_span(), format!("struct {}", self.structs[i].get_id().id.name)),
+            ItemIndex::Trait(i) => (self.traits[i].trait_id.id.get_span(), format!("trait {}", self.traits[i].trait_id.id.name)),
+            ItemIndex::Alias(i) => (self.aliases[i].get_id().id.get_span(), format!("type {}", self.aliases[i].get_id().id.name)),
+            ItemIndex::Impl(i) => (self.impls[i].trait_spec.trait_id.id.get_span(), format!("impl {} for {}", self.impls[i].trait_spec, self.impls[i].impl_ty)),
+            ItemIndex::Func(i) => (self.funcs[i].func_id.get_span(), format!("fn {}", self.funcs[i].func_id.name)),
+        };
+        format!("{} {}", span.source_stem().unwrap_or_else(|| "builtin".to_string()), key)
+    }
+}
+
+
+// every group holds the pieces of one item, its dependencies are what the pieces reference
+// together with the impls type checking selected for it
+fn append_in_dependency_order(pieces: &mut Vec<(ItemIndex, String, HashSet<ItemRef>)>, mut groups: Vec<Vec<(ItemIndex, String, HashSet<ItemRef>)>>, graph: &DependencyGraph, ta: &TypeAnnotation) {
+    let items = groups.iter().map(|group| {
+        let index = group[0].0;
+        let refs = group.iter().flat_map(|(_, _, refs)| refs.iter().cloned()).chain(ta.selected_impls(index)).collect::<HashSet<_>>();
+        (index, refs)
+    }).collect::<Vec<_>>();
+    for k in graph.dependency_order(&items.iter().map(|(index, refs)| (*index, refs)).collect::<Vec<_>>()) {
+        pieces.append(&mut groups[k]);
     }
 }
 
@@ -170,7 +347,9 @@ enum ContentElement {
     Func(FuncDefinition),
     Trait(TraitDefinition),
     ImplTrait(ImplDefinition),
+    TypeAlias(TypeAliasDefinition),
     Import(String),
+    Use(Vec<UseDeclaration>),
 }
 
 fn parse_element_struct(s: &str) -> IResult<&str, ContentElement> {
@@ -178,6 +357,11 @@ fn parse_element_struct(s: &str) -> IResult<&str, ContentElement> {
     Ok((s, ContentElement::Struct(f)))
 }
 
+fn parse_element_enum(s: &str) -> IResult<&str, ContentElement> {
+    let (s, f) = parse_enum_definition(s)?;
+    Ok((s, ContentElement::Struct(f)))
+}
+
 fn parse_element_func(s: &str) -> IResult<&str, ContentElement> {
     let (s, f) = parse_func_definition(s)?;
     Ok((s, ContentElement::Func(f)))
@@ -189,97 +373,178 @@ fn parse_element_trait(s: &str) -> IResult<&str, ContentElement> {
 }
 
 fn parse_element_impl_trait(s: &str) -> IResult<&str, ContentElement> {
-    let (s, it) = parse_impl_definition(s)?;
+    let (s, (_, it)) = tuple((parse_doc_comments, parse_impl_definition))(s)?;
     Ok((s, ContentElement::ImplTrait(it)))
 }
 
+fn parse_element_type_alias(s: &str) -> IResult<&str, ContentElement> {
+    let (s, alias) = parse_type_alias_definition(s)?;
+    Ok((s, ContentElement::TypeAlias(alias)))
+}
+
 fn parse_element_import(s: &str) -> IResult<&str, ContentElement> {
     let (s, (_, _, _, _, path, _, _)) = tuple((multispace0, tag("import"), multispace0, char('"'), is_not("\""), char('"'), multispace0))(s)?;
     Ok((s, ContentElement::Import(path.to_string())))
 }
 
+fn parse_use_targets(s: &str) -> IResult<&str, Vec<UseTarget>> {
+    alt((
+        map(char('*'), |_| vec![UseTarget::Glob]),
+        map(tuple((char('{'), multispace0, separated_list1(tuple((multispace0, char(','), multispace0)), parse_identifier), multispace0, opt(char(',')), multispace0, char('}'))),
+            |(_, _, ids, _, _, _, _)| ids.into_iter().map(|id| UseTarget::Item(id.into_string())).collect()),
+    ))(s)
+}
+
+fn parse_element_use(s: &str) -> IResult<&str, ContentElement> {
+    let (s, (_, _, head, tails, targets, _, _)) = tuple((tag("use"), multispace1, parse_identifier,
+            many0(preceded(tag("::"), parse_identifier)), opt(preceded(tag("::"), parse_use_targets)), multispace0, char(';')))(s)?;
+    let mut module = std::iter::once(head).chain(tails).collect::<Vec<_>>();
+    let targets = match targets {
+        Some(targets) => targets,
+        None if module.len() > 1 => vec![UseTarget::Item(module.pop().unwrap().into_string())],
+        None => return Err(nom::Err::Error(nom::error::Error::new(s, nom::error::ErrorKind::Tag))),
+    };
+    Ok((s, ContentElement::Use(targets.into_iter().map(|target| UseDeclaration { module: module.clone(), target }).collect())))
+}
 
 fn parse_content_element(s: &str) -> IResult<&str, ContentElement> {
-    alt((parse_element_import, parse_element_struct, parse_element_func, parse_element_trait, parse_element_impl_trait))(s)
+    alt((parse_element_import, parse_element_use, parse_element_struct, parse_element_enum, parse_element_func, parse_element_trait, parse_element_impl_trait, parse_element_type_alias))(s)
+}
+
+fn parse_element_visibility(s: &str) -> IResult<&str, bool> {
+    peek(preceded(tuple((parse_doc_comments, parse_derive_attributes)), parse_visibility))(s)
 }
 
-pub fn parse_full_content(s: &str) -> IResult<&str, (Vec<String>, FullContent)> {
-    let (s, (_, elems, _)) = tuple((multispace0, many0(tuple((parse_content_element, multispace0))), multispace0))(s)?;
+impl Resolve for FullContent {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.structs.resolve(scope)?;
+        self.funcs.resolve(scope)?;
+        self.traits.resolve(scope)?;
+        self.impls.resolve(scope)?;
+        self.aliases.resolve(scope)
+    }
+}
+
+#[derive(Debug)]
+pub struct ModuleContent {
+    pub imports: Vec<String>,
+    pub uses: Vec<UseDeclaration>,
+    pub items: Vec<(ItemKind, String, bool)>,
+    pub content: FullContent,
+}
+
+pub fn parse_full_content(s: &str) -> IResult<&str, ModuleContent> {
+    let (s, (_, elems, _)) = tuple((multispace0, many0(tuple((parse_element_visibility, parse_content_element, multispace0))), multispace0))(s)?;
     
     let mut structs = Vec::new();
     let mut funcs = Vec::new();
     let mut traits = Vec::new();
     let mut impls = Vec::new();
+    let mut aliases = Vec::new();
     let mut imports = Vec::new();
-    for (e, _) in elems {
+    let mut uses = Vec::new();
+    let mut items = Vec::new();
+    for (is_pub, e, _) in elems {
         match e {
-            ContentElement::Struct(s) => structs.push(s),
-            ContentElement::Func(f) => funcs.push(f),
-            ContentElement::Trait(t) => traits.push(t),
+            ContentElement::Struct(s) => {
+                items.push((ItemKind::Type, s.get_id().id.into_string(), is_pub));
+                structs.push(s)
+            }
+            ContentElement::Func(f) => {
+                items.push((ItemKind::Func, f.func_id.into_string(), is_pub));
+                funcs.push(f)
+            }
+            ContentElement::Trait(t) => {
+                items.push((ItemKind::Trait, t.trait_id.id.into_string(), is_pub));
+                traits.push(t)
+            }
             ContentElement::ImplTrait(it) => impls.push(it),
+            ContentElement::TypeAlias(alias) => {
+                items.push((ItemKind::Type, alias.alias_id.id.into_string(), is_pub));
+                aliases.push(alias)
+            }
             ContentElement::Import(path) => imports.push(path),
+            ContentElement::Use(mut decls) => uses.append(&mut decls),
         }
     }
-    Ok((s, (imports, FullContent { structs, funcs, traits, impls, })))
+    Ok((s, ModuleContent { imports, uses, items, content: FullContent { structs, funcs, traits, impls, aliases } }))
 }
 
-pub fn parse_full_content_from_file(filename: &str, import_path: &[PathBuf]) -> Result<FullContent, String> {
-    let mut structs = Vec::new();
-    let mut funcs = Vec::new();
-    let mut traits = Vec::new();
-    let mut impls = Vec::new();
+fn find_import(import: &str, from: &Path, roots: &[ImportRoot]) -> Result<PathBuf, String> {
+    roots.iter().filter_map(|root| root.resolve(import))
+        .chain(std::iter::once(from.parent().unwrap().join(import)))
+        .filter_map(|path| path.canonicalize().ok())
+        .find(|path| path.is_file())
+        .ok_or(format!("cant find {}", import))
+}
 
-    let mut que = Vec::new();
-    let mut read = HashSet::new();
-    {
-        let path = Path::new(filename).canonicalize().map_err(|e| format!("{:?}", e))?.to_path_buf();
-        if path.is_file() {
-            que.push(path.clone());
-            read.insert(path);
+fn parse_module(module: &str, file: usize, program: &str) -> Result<ModuleContent, String> {
+    let before = set_current_source(Some(file));
+    let result = parse_full_content(program);
+    set_current_source(before);
+    match result {
+        Ok(("", module)) => Ok(module),
+        Ok((s, _)) => Err(parse_error_message(file, s)),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(parse_error_message(file, e.input)),
+        Err(nom::Err::Incomplete(_)) => Err(format!("module {} parse error, incomplete input", module)),
+    }
+}
+
+pub fn parse_full_content_from_file(filename: &Path, import_roots: &[ImportRoot]) -> Result<FullContent, String> {
+    let entry = filename.canonicalize().map_err(|e| format!("{:?} {:?}", filename, e))?;
+    if !entry.is_file() {
+        Err(format!("path {:?} is not file", entry))?;
+    }
+    let search = import_roots.iter().filter_map(|root| root.canonicalize()).collect::<Vec<_>>();
+    let roots = search.iter().cloned()
+        .chain(std::iter::once(ImportRoot::new(entry.parent().unwrap().to_path_buf())))
+        .collect::<Vec<_>>();
+
+    let mut parsed = Vec::new();
+    let mut modules = HashMap::new();
+    let mut que = vec![entry.clone()];
+    modules.insert(entry.clone(), String::new());
+    while let Some(path) = que.pop() {
+        let program = std::fs::read_to_string(path.as_path()).map_err(|_| format!("cant open {:?}", path))?;
+        let file = regist_source(&path, &program);
+        let module = modules[&path].clone();
+        let content = parse_module(&module, file, &program)?;
+        let mut imports = Vec::new();
+        for import in content.imports.iter() {
+            let import = find_import(import, &path, &search)?;
+            let module = modules.entry(import.clone()).or_insert_with(|| {
+                que.push(import.clone());
+                module_path_from_file(&import, &roots)
+            });
+            imports.push(module.clone());
         }
-        else {
-            Err(format!("path {:?} is not file", path))?;
+        regist_module(&module);
+        for (kind, name, is_pub) in content.items.iter() {
+            regist_module_item(&module, *kind, name, *is_pub)?;
         }
+        parsed.push((module, imports, content));
     }
-    
-    while let Some(path) = que.pop() {
-        let program = std::fs::read_to_string(path.as_path()).map_err(|_| format!("cant open {}", filename))?;
-        let (s, (imports, mut full)) = crate::full_content::parse_full_content(&program).map_err(|e| format!("{:?}", e))?;
-        if s != "" {
-            Err(format!("path {:?} parse error, remaining -> {}", path, s))?;
-        }
-        dbg!(import_path);
-        for import in imports.into_iter() {
-            let mut ok = false;
-            for mut import_dir in import_path.into_iter().cloned().chain(std::iter::once(path.parent().unwrap().to_path_buf())) {
-                import_dir.push(&import);
-                if let Ok(path) = import_dir.as_path().canonicalize().map(|p| p.to_path_buf()) {
-                    log::debug!("path {:?}", path);
-                    if read.insert(path.clone()) {
-                        if path.is_file() {
-                            que.push(path);
-                            ok = true;
-                            break;
-                        }
-                    }
-                    else {
-                        log::debug!("already exist");
-                        ok = true;
-                        break;
-                    }
-                }
-            }
-            if !ok {
-                Err(format!("cant find {}", import))?;
-            }
+
+    let mut structs = Vec::new();
+    let mut funcs = Vec::new();
+    let mut traits = Vec::new();
+    let mut impls = Vec::new();
+    let mut aliases = Vec::new();
+    for (module, imports, content) in parsed {
+        let ModuleContent { uses, content: mut full, .. } = content;
+        let scope = ModuleScope::build(&module, &uses, &imports)?;
+        full.resolve(&scope)?;
+        for f in full.funcs.iter_mut() {
+            f.func_id.name = qualified_name(&module, &f.func_id.name);
         }
         structs.append(&mut full.structs);
         funcs.append(&mut full.funcs);
         traits.append(&mut full.traits);
         impls.append(&mut full.impls);
+        aliases.append(&mut full.aliases);
     }
 
-    Ok(FullContent { structs, funcs, traits, impls })
+    Ok(FullContent { structs, funcs, traits, impls, aliases })
 }
 /*
 #[test]
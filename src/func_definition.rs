@@ -8,15 +8,21 @@ use nom::multi::*;
 use nom::sequence::*;
 use nom::IResult;
 
+use crate::comment::{ multispace0, multispace1, parse_doc_comments, transpile_doc_comments };
 use crate::identifier::{ Identifier, parse_identifier };
-use crate::type_id::{ TypeId, parse_type_id };
+use crate::type_id::{ TypeId, parse_generics_param };
 use crate::block::{ Block, parse_block };
 use crate::unify::*;
-use crate::unary_expr::Variable;
+use crate::unary_expr::{ UnaryExpr, Variable };
+use crate::expression::unary_expression;
+use crate::statement::Statement;
+use crate::let_declaration::{ LetDeclaration, LetPattern, parse_let_pattern };
 use crate::trans::*;
 use crate::mut_checker::*;
+use crate::module::{ ModuleScope, Resolve, parse_visibility, transpile_in_namespace };
 use crate::type_spec::*;
 use crate::cpp_inline::*;
+use crate::diagnostics::Span;
 
 
 #[derive(Debug)]
@@ -33,6 +39,8 @@ pub struct FuncDefinition {
     pub args: Vec<(Identifier, TypeSpec)>,
     pub return_type: TypeSpec,
     pub block: FuncBlock,
+    pub span: Span,
+    pub doc: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +51,8 @@ pub struct FuncDefinitionInfo {
     pub args: Vec<(Identifier, TypeSpec)>,
     pub return_type: TypeSpec,
     pub inline: Option<CppInline>,
+    pub span: Span,
+    pub doc: Vec<String>,
 }
 
 impl FuncDefinitionInfo {
@@ -121,6 +131,8 @@ impl FuncDefinition {
              args: self.args.clone(),
              return_type: self.return_type.clone(),
              inline,
+             span: self.span,
+             doc: self.doc.clone(),
          }
          )
     }
@@ -128,13 +140,13 @@ impl FuncDefinition {
         if let FuncBlock::Block(ref block) = self.block {
             if self.func_id == Identifier::from_str("main") {
                 if self.generics.len() > 0 {
-                    Err(format!("main function must not have generics arguments"))
+                    Err(self.span.error_message("main function must not have generics arguments"))
                 }
                 else if !self.where_sec.is_empty() {
-                    Err(format!("main function must not have where sections"))
+                    Err(self.span.error_message("main function must not have where sections"))
                 }
                 else if self.return_type != TypeSpec::from_str("void") {
-                    Err(format!("main function must return void"))
+                    Err(self.span.error_message("main function must return void"))
                 }
                 else {
                     Ok(())
@@ -155,6 +167,10 @@ impl FuncDefinition {
                    }*/
             }
 
+            for ty_id in self.generics.iter() {
+                ty_id.regist_const_variable(equs, &trs)?;
+            }
+
             self.where_sec.regist_candidate(equs, &mut trs)?;
 
             for (i, t) in self.args.iter() {
@@ -163,9 +179,16 @@ impl FuncDefinition {
                 equs.regist_variable(Variable::from_identifier(i.clone()), alpha.clone());
                 equs.add_equation(alpha, t_type);
             }
-            let result_type = block.gen_type(equs, &trs)?;
             let return_t = self.return_type.generics_to_type(&GenericsTypeMap::empty(), equs, &trs)?;
-            equs.add_equation(result_type, return_t);
+            let before = equs.set_return_type(Some((return_t.clone(), self.func_id == Identifier::from_str("main"))));
+            let result_type = block.gen_type(equs, &trs);
+            equs.set_return_type(before);
+            let result_type = result_type?;
+            if !block.ends_with_return() {
+                let before = equs.set_span(block.return_exp.as_ref().map_or(self.span, |exp| exp.get_span()));
+                equs.add_equation(result_type, return_t);
+                equs.set_span(before);
+            }
 
             log::info!("function {:?} unify", self.func_id);
             //equs.debug();
@@ -191,10 +214,16 @@ impl FuncDefinition {
         Ok(())
     }
     pub fn transpile_definition_only(&self, ta: &TypeAnnotation, class_str: &str, is_static: bool) -> String {
+        self.transpile_signature(ta, class_str, &self.func_id.into_string(), is_static)
+    }
+    pub fn transpile_declaration(&self, ta: &TypeAnnotation) -> String {
+        transpile_in_namespace(&self.func_id.into_string(), |name| format!("{};\n", self.transpile_signature(ta, "", name, false)))
+    }
+    fn transpile_signature(&self, ta: &TypeAnnotation, class_str: &str, func_str: &str, is_static: bool) -> String {
         let where_empty = self.where_sec.is_empty();
         let template_str =
             if self.generics.len() > 0 {
-                let gen = self.generics.iter().map(|g| format!("class {}", g.transpile(ta))).collect::<Vec<_>>().join(", ");
+                let gen = self.generics.iter().map(|g| g.transpile_template_param(ta)).collect::<Vec<_>>().join(", ");
                 if where_empty  {
                     format!("template<{}> ", gen)
                 }
@@ -216,7 +245,6 @@ impl FuncDefinition {
             self.return_type.transpile(ta)
         };
         let static_str = if is_static { "static " } else { "" };
-        let func_str = self.func_id.into_string();
         let arg_str = self.args.iter().map(|(id, ty)| {
             format!("{} {}", ty.transpile(ta), id.into_string())
         }).collect::<Vec<_>>().join(", ");
@@ -225,10 +253,14 @@ impl FuncDefinition {
     }
 
     fn transpile_definition(&self, ta: &TypeAnnotation, class_str: &str, is_static: bool) -> String {
+        self.transpile_definition_with_return(ta, class_str, is_static, false)
+    }
+
+    fn transpile_definition_with_return(&self, ta: &TypeAnnotation, class_str: &str, is_static: bool, trailing_return: bool) -> String {
         let where_str = self.where_sec.transpile(ta);
         let template_str =
             if self.generics.len() > 0 {
-                let gen = self.generics.iter().map(|g| format!("class {}", g.transpile(ta))).collect::<Vec<_>>().join(", ");
+                let gen = self.generics.iter().map(|g| g.transpile_template_param(ta)).collect::<Vec<_>>().join(", ");
                 if self.where_sec.is_empty() {
                     format!("template<{}> ", gen)
                 }
@@ -255,7 +287,15 @@ impl FuncDefinition {
             format!("{} {}", ty.transpile(ta), id.into_string())
         }).collect::<Vec<_>>().join(", ");
 
-        format!("{}{}{} {}{}({})", template_str, static_str, return_str, class_str, func_str, arg_str)
+        if trailing_return {
+            format!("{}{}auto {}{}({}) -> {}", template_str, static_str, class_str, func_str, arg_str, return_str)
+        }
+        else {
+            format!("{}{}{} {}{}({})", template_str, static_str, return_str, class_str, func_str, arg_str)
+        }
+    }
+    pub fn transpile_doc(&self) -> String {
+        transpile_doc_comments(&self.doc)
     }
     pub fn transpile_for_impl(&self, ta: &TypeAnnotation, class_str: &str, is_static: bool) -> String {
         match self.block {
@@ -271,6 +311,13 @@ impl FuncDefinition {
             }
         }
     }
+    pub fn transpile_for_default_impl(&self, ta: &TypeAnnotation, class_str: &str) -> String {
+        let func_def = self.transpile_definition_with_return(ta, class_str, false, true);
+        match self.block {
+            FuncBlock::Block(ref block) => format!("{} {{\n{}}}\n", func_def, block.transpile(ta)),
+            FuncBlock::CppInline(ref block) => format!("{} {{\nreturn {};\n}}\n", func_def, block.transpile_implement(ta)),
+        }
+    }
     pub fn transpile(&self, ta: &TypeAnnotation, is_static: bool) -> String {
         if let FuncBlock::Block(ref block) = self.block {
             let func_def = self.transpile_definition(ta, "", is_static);
@@ -283,17 +330,44 @@ impl FuncDefinition {
     }
 }
 
+impl Resolve for FuncDefinitionInfo {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.generics.resolve(scope)?;
+        self.where_sec.resolve(scope)?;
+        self.args.iter_mut().try_for_each(|(_, spec)| spec.resolve(scope))?;
+        self.return_type.resolve(scope)?;
+        self.inline.resolve(scope)
+    }
+}
+
+impl Resolve for FuncDefinition {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.generics.resolve(scope)?;
+        self.where_sec.resolve(scope)?;
+        self.args.iter_mut().try_for_each(|(_, spec)| spec.resolve(scope))?;
+        self.return_type.resolve(scope)?;
+        match self.block {
+            FuncBlock::Block(ref mut block) => block.resolve(scope),
+            FuncBlock::CppInline(ref mut inline) => inline.resolve(scope),
+        }
+    }
+}
+
 
 /*fn parse_generics_arg(s: &str) -> IResult<&str, (TypeId, Option<TraitId>)> {
     let (s, (id, _, opt)) = tuple((parse_type_id, multispace0, opt(tuple((char(':'), multispace0, parse_trait_id)))))(s)?;
     Ok((s, (id, opt.map(|(_, _, tr)| tr))))
 }*/
 
-pub fn parse_func_definition_info(s: &str) -> IResult<&str, FuncDefinitionInfo> {
+type ArgDestructs = Vec<(Identifier, LetPattern)>;
+
+fn parse_func_signature(s: &str) -> IResult<&str, (FuncDefinitionInfo, ArgDestructs)> {
+    let (s, (doc, _)) = tuple((parse_doc_comments, parse_visibility))(s)?;
+    let start = s;
     let (s, (_, _, func_id, _, generics_opt, _, _, _, op, _, _, _, _, return_type, _, where_sec)) = 
-        tuple((tag("fn"), space1, parse_identifier, multispace0, opt(tuple((char('<'), multispace0, opt(tuple((parse_type_id, multispace0, many0(tuple((char(','), multispace0, parse_type_id, multispace0))), opt(char(',')), multispace0))), char('>'), multispace0))), multispace0,
+        tuple((tag("fn"), multispace1, parse_identifier, multispace0, opt(tuple((char('<'), multispace0, opt(tuple((parse_generics_param, multispace0, many0(tuple((char(','), multispace0, parse_generics_param, multispace0))), opt(char(',')), multispace0))), char('>'), multispace0))), multispace0,
                char('('), multispace0,
-            opt(tuple((parse_identifier, multispace0, char(':'), multispace0, parse_type_spec, multispace0, many0(tuple((char(','), multispace0, parse_identifier, multispace0, char(':'), multispace0, parse_type_spec, multispace0))), opt(char(',')), multispace0))),
+            opt(tuple((parse_let_pattern, multispace0, char(':'), multispace0, parse_type_spec, multispace0, many0(tuple((char(','), multispace0, parse_let_pattern, multispace0, char(':'), multispace0, parse_type_spec, multispace0))), opt(char(',')), multispace0))),
             char(')'), multispace0, tag("->"), multispace0, parse_type_spec, multispace0, parse_where_section))(s)?;
     let generics = match generics_opt {
         Some((_, _, generics_opt, _, _)) => {
@@ -320,7 +394,21 @@ pub fn parse_func_definition_info(s: &str) -> IResult<&str, FuncDefinitionInfo>
         }
         None => Vec::new(),
     };
-    Ok((s, FuncDefinitionInfo { func_id, generics, where_sec, args, return_type, inline: None }))
+    let mut destructs = Vec::new();
+    let args = args.into_iter().enumerate().map(|(i, (pattern, ty))| match pattern {
+        LetPattern::Identifier(id, _) => (id, ty),
+        pattern => {
+            let id = Identifier::from_str(&format!("_arg{}", i)).with_span(pattern.get_span());
+            destructs.push((id.clone(), pattern));
+            (id, ty)
+        }
+    }).collect();
+    Ok((s, (FuncDefinitionInfo { func_id, generics, where_sec, args, return_type, inline: None, span: Span::between(start, s), doc }, destructs)))
+}
+
+pub fn parse_func_definition_info(s: &str) -> IResult<&str, FuncDefinitionInfo> {
+    let (s, (info, _)) = parse_func_signature(s)?;
+    Ok((s, info))
 }
 
 fn parse_func_block_block(s: &str) -> IResult<&str, FuncBlock> {
@@ -339,8 +427,16 @@ fn parse_func_block(s: &str) -> IResult<&str, FuncBlock> {
 }
 
 pub fn parse_func_definition(s: &str) -> IResult<&str, FuncDefinition> {
-    let (s, (info, _, block)) = tuple((parse_func_definition_info, multispace0, parse_func_block))(s)?;
-    Ok((s, FuncDefinition { func_id: info.func_id, generics: info.generics, where_sec: info.where_sec, args: info.args, return_type: info.return_type, block }))
+    let (s, ((info, destructs), _, mut block)) = tuple((parse_func_signature, multispace0, parse_func_block))(s)?;
+    if let FuncBlock::Block(ref mut block) = block {
+        let lets = destructs.into_iter().map(|(id, pattern)| {
+            let span = pattern.get_span();
+            let value = unary_expression(UnaryExpr::Variable(Variable::from_identifier(id)), span);
+            Statement::LetDeclaration(LetDeclaration { pattern, type_info: None, value, span })
+        });
+        block.statements.splice(0..0, lets);
+    }
+    Ok((s, FuncDefinition { func_id: info.func_id, generics: info.generics, where_sec: info.where_sec, args: info.args, return_type: info.return_type, block, span: info.span, doc: info.doc }))
 }
 
 
@@ -349,6 +445,7 @@ fn parse_func_definition_test() {
     log::debug!("{:?}", parse_func_definition("fn func(x: i64) -> i64 { let y = x * x; y + x }"));
     log::debug!("{:?}", parse_func_definition("fn func2<t>(x: t) -> t { x }"));
     log::debug!("{:?}", parse_func_definition("fn func3<x, y, z>(x: x) -> z { x }"));
+    log::debug!("{:?}", parse_func_definition("fn func4((a, mut b): (i64, u64)) -> i64 { a }"));
 }
 #[test]
 fn parse_func_definition2_test() {
@@ -356,6 +453,12 @@ fn parse_func_definition2_test() {
     log::debug!("{:?}", parse_func_definition_info("fn nest_out<T>(t: T) -> T#MyTrait::Output#MyTrait::Output where T: MyTrait, T#MyTrait::Output: MyTrait"));
 }
 
+#[test]
+fn parse_func_doc_comment_test() {
+    let (_, func) = parse_func_definition("/// square of x\n/// never negative\nfn square(x: i64) -> i64 { x * x /* no overflow check */ }").unwrap();
+    assert_eq!(func.doc, vec![" square of x".to_string(), " never negative".to_string()]);
+}
+
 #[test]
 fn parse_func_cppinline_test() {
     log::debug!("{:?}", parse_func_definition("fn push_back(self: Self, t: T) -> bool $${ $arg(self).push_back($arg(t)) }$$"));
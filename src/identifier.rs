@@ -9,6 +9,7 @@ use nom::sequence::*;
 use nom::IResult;
 
 use crate::unify::*;
+use crate::diagnostics::{ Span, regist_tag_span, tag_span };
 
 static TAG_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
@@ -29,9 +30,17 @@ impl Tag {
     pub fn new() -> Tag {
         Tag(get_tag_counter())
     }
+    pub fn with_span(span: Span) -> Tag {
+        let tag = Tag::new();
+        regist_tag_span(tag.get_num(), span);
+        tag
+    }
     pub fn get_num(&self) -> usize {
         self.0
     }
+    pub fn get_span(&self) -> Span {
+        tag_span(self.get_num())
+    }
     pub fn generate_type_variable(&self, label: &'static str, num: usize, equs: &mut TypeEquations) -> Type {
         let var = TypeVariable::Counter(self.get_num(), label, num);
         equs.add_want_solve(&var, false);
@@ -63,6 +72,13 @@ impl<'a> Identifier {
     pub fn from_vec_str(vec: Vec<&str>) -> Self {
         Identifier { name: vec.join(""), tag: Tag::new() }
     }
+    pub fn with_span(self, span: Span) -> Self {
+        regist_tag_span(self.tag.get_num(), span);
+        self
+    }
+    pub fn get_span(&self) -> Span {
+        self.tag.get_span()
+    }
     pub fn into_string(&self) -> String {
         self.name.clone()
     }
@@ -77,20 +93,33 @@ impl<'a> Identifier {
     }
 }
 
+pub fn parse_keyword(keyword: &'static str) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |s| terminated(tag(keyword), not(alt((alphanumeric1, tag("_")))))(s)
+}
+
 pub fn parse_identifier(s: &str) -> IResult<&str, Identifier> {
-    not(all_consuming(alt((
-                    tag("if"),
-                    tag("else"),
-                    tag("while"),
-                    tag("for"),
-                    tag("in")
-                    ))))(s)?;
+    let start = s;
+    not(alt((
+                    parse_keyword("if"),
+                    parse_keyword("else"),
+                    parse_keyword("while"),
+                    parse_keyword("for"),
+                    parse_keyword("in"),
+                    parse_keyword("loop"),
+                    parse_keyword("match"),
+                    parse_keyword("break"),
+                    parse_keyword("continue"),
+                    parse_keyword("return"),
+                    parse_keyword("let"),
+                    parse_keyword("as"),
+                    parse_keyword("move"),
+                    )))(s)?;
     let (s, (head, tails)) = tuple((alt((alpha1, tag("_"))), many0(alt((alphanumeric1, tag("_"))))))(s)?;
     let mut name = vec![head];
     for s in tails {
         name.push(s);
     }
-    Ok((s, Identifier::from_vec_str(name)))
+    Ok((s, Identifier::from_vec_str(name).with_span(Span::between(start, s))))
 }
 
 #[test]
@@ -1,35 +1,121 @@
-//use nom::branch::*;
+use nom::branch::*;
 use nom::IResult;
 use nom::character::complete::*;
 use nom::sequence::*;
 use nom::bytes::complete::*;
 use nom::combinator::*;
+use nom::multi::*;
 
-use crate::identifier::{ Identifier, parse_identifier };
+use crate::comment::{ multispace0, multispace1 };
+use crate::identifier::{ Identifier, Tag, parse_identifier };
 use crate::expression::{ Expression, parse_expression };
 use crate::unary_expr::Variable;
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
 use crate::type_spec::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
+
+#[derive(Debug, Clone)]
+pub enum LetPattern {
+    Identifier(Identifier, bool),
+    Tuple(Vec<LetPattern>, Tag),
+}
+
+impl LetPattern {
+    pub fn get_span(&self) -> Span {
+        match *self {
+            LetPattern::Identifier(ref id, _) => id.get_span(),
+            LetPattern::Tuple(_, ref tag) => tag.get_span(),
+        }
+    }
+    fn gen_type(&self, equs: &mut TypeEquations) -> Type {
+        match *self {
+            LetPattern::Identifier(ref id, _) => {
+                let elem_type = id.generate_not_void_type_variable("LetType", 0, equs);
+                if id.name != "_" {
+                    equs.regist_variable(Variable::from_identifier(id.clone()), elem_type.clone());
+                }
+                elem_type
+            }
+            LetPattern::Tuple(ref elems, ref tag) => {
+                let elem_types = elems.iter().map(|elem| elem.gen_type(equs)).collect();
+                let alpha = tag.generate_not_void_type_variable("LetType", 0, equs);
+                equs.add_equation(alpha.clone(), Type::Tuple(elem_types));
+                alpha
+            }
+        }
+    }
+    fn binding_name(&self) -> String {
+        match *self {
+            LetPattern::Identifier(ref id, _) if id.name == "_" => format!("_{}", id.get_tag_number()),
+            LetPattern::Identifier(ref id, _) => id.into_string(),
+            LetPattern::Tuple(_, ref tag) => format!("_{}", tag.get_num()),
+        }
+    }
+    fn is_mut(&self) -> bool {
+        match *self {
+            LetPattern::Identifier(_, is_mut) => is_mut,
+            LetPattern::Tuple(ref elems, _) => elems.iter().any(|elem| elem.is_mut()),
+        }
+    }
+    fn transpile_destruct(&self, value: String) -> String {
+        match *self {
+            LetPattern::Identifier(..) => unreachable!("identifier pattern has no destruct"),
+            LetPattern::Tuple(ref elems, _) => {
+                let decl = format!("{}auto [{}] = {}",
+                        if self.is_mut() { "" } else { "const " },
+                        elems.iter().map(|elem| elem.binding_name()).collect::<Vec<_>>().join(", "),
+                        value
+                );
+                let nested = elems.iter().filter(|elem| matches!(elem, LetPattern::Tuple(..)))
+                    .map(|elem| elem.transpile_destruct(elem.binding_name()));
+                std::iter::once(decl).chain(nested).collect::<Vec<_>>().join(";\n")
+            }
+        }
+    }
+    fn regist_variables(&self, vars: &mut VariablesInfo) {
+        match *self {
+            LetPattern::Identifier(ref id, _) if id.name == "_" => {}
+            LetPattern::Identifier(ref id, is_mut) => vars.regist_variable(id, is_mut),
+            LetPattern::Tuple(ref elems, _) => {
+                for elem in elems.iter() {
+                    elem.regist_variables(vars);
+                }
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct LetDeclaration {
-    pub id: Identifier,
-    pub is_mut: bool,
+    pub pattern: LetPattern,
     pub type_info: Option<TypeSpec>,
     pub value: Expression,
+    pub span: Span,
 }
 
 impl GenType for LetDeclaration {
     fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
-        let alpha = self.id.generate_not_void_type_variable("LetType", 0, equs);
-        equs.regist_variable(Variable::from_identifier(self.id.clone()), alpha.clone());
-        let value_type = self.value.gen_type(equs, trs)?;
-        equs.add_equation(alpha.clone(), value_type);
+        let alpha = match self.pattern {
+            LetPattern::Identifier(ref id, _) => {
+                let alpha = id.generate_not_void_type_variable("LetType", 0, equs);
+                equs.regist_variable(Variable::from_identifier(id.clone()), alpha.clone());
+                let value_type = self.value.gen_type(equs, trs)?;
+                equs.add_equation(alpha.clone(), value_type);
+                alpha
+            }
+            LetPattern::Tuple(..) => {
+                let value_type = self.value.gen_type(equs, trs)?;
+                let alpha = self.pattern.gen_type(equs);
+                equs.add_equation(alpha.clone(), value_type);
+                alpha
+            }
+        };
         if let Some(ref t) = self.type_info {
             let t_type = t.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
-            equs.add_equation(alpha.clone(), t_type);
+            equs.add_equation(alpha, t_type);
         }
         Ok(Type::End)
     }
@@ -37,30 +123,72 @@ impl GenType for LetDeclaration {
 
 impl Transpile for LetDeclaration {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        format!("{}{} {} = {}",
-                ta.annotation(self.id.get_tag_number(), "LetType", 0).transpile(ta),
-                if self.is_mut { "" } else { " const" },
-                self.id.into_string(),
+        match self.pattern {
+            LetPattern::Identifier(ref id, _) if ta.annotation(id.get_tag_number(), "LetType", 0) == Type::Never => {
+                // the value never finishes, so there is nothing to bind
                 self.value.transpile(ta)
-        )
+            }
+            LetPattern::Identifier(ref id, is_mut) => {
+                format!("{}{} {} = {}",
+                        ta.annotation(id.get_tag_number(), "LetType", 0).transpile(ta),
+                        if is_mut { "" } else { " const" },
+                        id.into_string(),
+                        self.value.transpile(ta)
+                )
+            }
+            LetPattern::Tuple(..) => self.pattern.transpile_destruct(self.value.transpile(ta)),
+        }
     }
 }
 
 impl MutCheck for LetDeclaration {
     fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
-        vars.regist_variable(&self.id, self.is_mut);
+        self.value.mut_check(ta, vars)?;
+        match self.pattern {
+            LetPattern::Identifier(ref id, is_mut) => vars.regist_variable(id, is_mut),
+            LetPattern::Tuple(..) => self.pattern.regist_variables(vars),
+        }
         Ok(MutResult::NoType)
     }
 }
 
+impl Resolve for LetDeclaration {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.type_info.resolve(scope)?;
+        self.value.resolve(scope)
+    }
+}
+
+fn parse_let_pattern_tuple(s: &str) -> IResult<&str, LetPattern> {
+    let start = s;
+    let (s, (_, _, head, _, tails, comma, _, _)) = tuple((char('('), multispace0, parse_let_pattern, multispace0,
+            many0(tuple((char(','), multispace0, parse_let_pattern, multispace0))), opt(char(',')), multispace0, char(')')))(s)?;
+    if tails.is_empty() && comma.is_none() {
+        return Err(nom::Err::Error(nom::error::Error::new(start, nom::error::ErrorKind::Tag)))
+    }
+    let elems = std::iter::once(head).chain(tails.into_iter().map(|(_, _, elem, _)| elem)).collect();
+    Ok((s, LetPattern::Tuple(elems, Tag::with_span(Span::between(start, s)))))
+}
+
+fn parse_let_pattern_identifier(s: &str) -> IResult<&str, LetPattern> {
+    let (s, (is_mut, id)) = tuple((opt(tuple((tag("mut"), multispace1))), parse_identifier))(s)?;
+    Ok((s, LetPattern::Identifier(id, is_mut.is_some())))
+}
+
+pub fn parse_let_pattern(s: &str) -> IResult<&str, LetPattern> {
+    alt((parse_let_pattern_tuple, parse_let_pattern_identifier))(s)
+}
 
 pub fn parse_let_declaration(s: &str) -> IResult<&str, LetDeclaration> {
-    let (s, (_let, _, is_mut, id, _, tyinfo, _, _e, _, value)) = tuple((tag("let"), space1, opt(tuple((tag("mut"), space1))), parse_identifier, multispace0, opt(tuple((char(':'), multispace0, parse_type_spec))), multispace0, tag("="), multispace0, parse_expression))(s)?;
-    Ok((s, (LetDeclaration { id, is_mut: is_mut.is_some(), type_info: tyinfo.map(|(_, _, type_info)| type_info ), value })))
+    let start = s;
+    let (s, (_let, _, pattern, _, tyinfo, _, _e, _, value)) = tuple((tag("let"), multispace1, parse_let_pattern, multispace0, opt(tuple((char(':'), multispace0, parse_type_spec))), multispace0, tag("="), multispace0, parse_expression))(s)?;
+    Ok((s, (LetDeclaration { pattern, type_info: tyinfo.map(|(_, _, type_info)| type_info ), value, span: Span::between(start, s) })))
 }
 
 #[test]
 fn parse_decl_test() {
     log::debug!("{:?}", parse_let_declaration("let x = 1 + 2"));
     log::debug!("{:?}", parse_let_declaration("let x: i64 = 1 + 2"));
+    log::debug!("{:?}", parse_let_declaration("let (mut x, _, y): (i64, bool, u64) = t"));
+    log::debug!("{:?}", parse_let_declaration("let ((a, mut b), c) = t"));
 }
@@ -8,6 +8,9 @@ use nom::IResult;
 
 
 use crate::unary_expr::UnaryExpr;
+use crate::identifier::Tag;
+use crate::type_id::TypeId;
+use crate::diagnostics::Span;
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
@@ -16,24 +19,74 @@ use crate::mut_checker::*;
 pub enum Literal {
     U64(LiteralU64),
     I64(LiteralI64),
+    Int(LiteralInt),
     Boolean(Boolean),
+    F64(LiteralF64),
+    Char(LiteralChar),
+    Str(LiteralString),
 }
 
 impl GenType for Literal {
-    fn gen_type(&self, _: &mut TypeEquations, _: &TraitsInfo) -> TResult {
+    fn gen_type(&self, _: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
         match *self {
-            Literal::U64(_) => Ok(Type::from_str("u64")),
-            Literal::I64(_) => Ok(Type::from_str("i64")),
+            Literal::U64(ref u) => check_int_range(&u.number, "u64", &u.span, false),
+            Literal::I64(ref i) => check_int_range(&i.number, "i64", &i.span, false),
+            Literal::Int(ref i) => check_int_range(&i.number, &i.ty, &i.span, false),
             Literal::Boolean(_) => Ok(Type::from_str("bool")),
+            Literal::F64(_) => Ok(Type::from_str("f64")),
+            Literal::Char(_) => Ok(Type::from_str("char")),
+            Literal::Str(ref st) => {
+                if trs.search_typeid(&TypeId::from_str("String")).is_err() {
+                    Err(st.tag.get_span().error_message("struct String is not defined, import \"std/string.niu\""))
+                }
+                else {
+                    Ok(Type::from_str("String"))
+                }
+            }
         }
     }
 }
+impl Literal {
+    // a literal directly under unary minus may be the minimum of a signed type
+    pub fn gen_type_negated(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        match *self {
+            Literal::I64(ref i) => check_int_range(&i.number, "i64", &i.span, true),
+            Literal::Int(ref i) => check_int_range(&i.number, &i.ty, &i.span, true),
+            _ => self.gen_type(equs, trs),
+        }
+    }
+    pub fn transpile_negated(&self, ta: &TypeAnnotation) -> String {
+        let (number, ty) = match *self {
+            Literal::I64(ref i) => (&i.number, "i64"),
+            Literal::Int(ref i) if int_min(&i.ty) < 0 => (&i.number, i.ty.as_str()),
+            _ => return format!("(-{})", self.transpile(ta)),
+        };
+        let value = number.parse::<u128>().unwrap();
+        if ty == "i64" && value > i64::MAX as u128 {
+            format!("(-{}ll - 1)", i64::MAX)
+        }
+        else if value > i64::MAX as u128 {
+            format!("static_cast<{0}>(-(static_cast<unsigned __int128>({1}ull) << 64 | static_cast<unsigned __int128>({2}ull)))", Type::from_str(ty).transpile(ta), value >> 64, value as u64)
+        }
+        else if ty == "i64" {
+            format!("(-{}ll)", number)
+        }
+        else {
+            format!("static_cast<{}>(-{}ll)", Type::from_str(ty).transpile(ta), number)
+        }
+    }
+}
+
 impl Transpile for Literal {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
         match *self {
             Literal::U64(ref u) => u.transpile(ta),
             Literal::I64(ref i) => i.transpile(ta),
+            Literal::Int(ref i) => i.transpile(ta),
             Literal::Boolean(ref b) => b.transpile(ta),
+            Literal::F64(ref f) => f.transpile(ta),
+            Literal::Char(ref c) => c.transpile(ta),
+            Literal::Str(ref st) => st.transpile(ta),
         }
     }
 }
@@ -44,19 +97,49 @@ impl MutCheck for Literal {
     }
 }
 
+// floats and strings have no exact equality to match against
+pub fn parse_pattern_literal(s: &str) -> IResult<&str, Literal> {
+    alt((literal_i64, literal_int, literal_u64, terminated(literal_boolean, not(satisfy(|c| c.is_alphanumeric() || c == '_'))), literal_char))(s)
+}
+
 pub fn parse_literal(s: &str) -> IResult<&str, UnaryExpr> {
-    let (s, x) = alt((literal_i64, literal_u64, literal_boolean))(s)?;
+    let (s, x) = alt((literal_f64, literal_i64, literal_int, literal_u64, literal_boolean, literal_char, literal_string))(s)?;
     Ok((s, UnaryExpr::Literal(x)))
 }
 
 #[derive(Debug)]
 pub struct LiteralU64 {
     pub number: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct LiteralI64 {
     pub number: String,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct LiteralInt {
+    pub number: String,
+    pub ty: String,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct LiteralF64 {
+    pub number: String,
+}
+
+#[derive(Debug)]
+pub struct LiteralChar {
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub struct LiteralString {
+    pub text: String,
+    pub tag: Tag,
 }
 
 #[derive(Debug)]
@@ -65,6 +148,47 @@ pub enum Boolean {
     False,
 }
 
+fn int_min(ty: &str) -> i128 {
+    match ty {
+        "i32" => i32::MIN as i128,
+        "i64" => i64::MIN as i128,
+        "i128" => i128::MIN,
+        _ => 0,
+    }
+}
+
+pub fn int_fits(value: i128, ty: &str) -> bool {
+    int_min(ty) <= value && (value < 0 || value as u128 <= int_max(ty))
+}
+
+fn int_max(ty: &str) -> u128 {
+    match ty {
+        "i32" => i32::MAX as u128,
+        "u32" => u32::MAX as u128,
+        "i64" => i64::MAX as u128,
+        "u64" | "usize" => u64::MAX as u128,
+        "i128" => i128::MAX as u128,
+        "u8" => u8::MAX as u128,
+        _ => u128::MAX,
+    }
+}
+
+fn check_int_range(number: &str, ty: &str, span: &Span, negated: bool) -> TResult {
+    let value = number.parse::<u128>();
+    if negated && int_min(ty) < 0 {
+        match value {
+            Ok(value) if value <= int_min(ty).unsigned_abs() => Ok(Type::from_str(ty)),
+            _ => Err(span.error_message(&format!("literal -{} does not fit in {}, the minimum value is {}", number, ty, int_min(ty)))),
+        }
+    }
+    else {
+        match value {
+            Ok(value) if value <= int_max(ty) => Ok(Type::from_str(ty)),
+            _ => Err(span.error_message(&format!("literal {} does not fit in {}, the maximum value is {}", number, ty, int_max(ty)))),
+        }
+    }
+}
+
 impl Transpile for LiteralU64 {
     fn transpile(&self, _: &TypeAnnotation) -> String {
         format!("{}ull", self.number)
@@ -77,6 +201,38 @@ impl Transpile for LiteralI64 {
     }
 }
 
+impl Transpile for LiteralInt {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let ty = Type::from_str(&self.ty).transpile(ta);
+        let value = self.number.parse::<u128>().unwrap();
+        if value > u64::MAX as u128 {
+            format!("(static_cast<{0}>({1}ull) << 64 | static_cast<{0}>({2}ull))", ty, value >> 64, value as u64)
+        }
+        else {
+            let suffix = if self.ty.starts_with('i') { "ll" } else { "ull" };
+            format!("static_cast<{}>({}{})", ty, self.number, suffix)
+        }
+    }
+}
+
+impl Transpile for LiteralF64 {
+    fn transpile(&self, _: &TypeAnnotation) -> String {
+        self.number.clone()
+    }
+}
+
+impl Transpile for LiteralChar {
+    fn transpile(&self, _: &TypeAnnotation) -> String {
+        format!("'{}'", self.text)
+    }
+}
+
+impl Transpile for LiteralString {
+    fn transpile(&self, _: &TypeAnnotation) -> String {
+        format!("std::string(\"{}\")", self.text)
+    }
+}
+
 impl Transpile for Boolean {
     fn transpile(&self, _: &TypeAnnotation) -> String {
         match *self {
@@ -87,25 +243,75 @@ impl Transpile for Boolean {
 }
 
 pub fn literal_u64(s: &str) -> IResult<&str, Literal> {
+    let start = s;
     let (s, (number, _)) = 
          tuple((unsigned_number, opt(tag("u64"))))(s)?;
     Ok((s, 
-        Literal::U64(LiteralU64 { number: number.join("") })
+        Literal::U64(LiteralU64 { number: number.join(""), span: Span::between(start, s) })
         ))
 }
 
 pub fn literal_i64(s: &str) -> IResult<&str, Literal> {
+    let start = s;
     let (s, (number, _)) = 
          tuple((unsigned_number, tag("i64")))(s)?;
     Ok((s, 
-        Literal::I64(LiteralI64 { number: number.join("") })
+        Literal::I64(LiteralI64 { number: number.join(""), span: Span::between(start, s) })
+        ))
+}
+
+pub fn literal_int(s: &str) -> IResult<&str, Literal> {
+    let start = s;
+    let (s, (number, ty)) =
+         tuple((unsigned_number, alt((tag("i32"), tag("u32"), tag("i128"), tag("u128"), tag("usize"), tag("u8")))))(s)?;
+    Ok((s,
+        Literal::Int(LiteralInt { number: number.join(""), ty: ty.to_string(), span: Span::between(start, s) })
         ))
 }
 
+fn float_exponent(s: &str) -> IResult<&str, String> {
+    let (s, (_, sign, digits)) = tuple((one_of("eE"), opt(one_of("+-")), unsigned_number))(s)?;
+    Ok((s, format!("e{}{}", sign.map(|c| c.to_string()).unwrap_or_default(), digits.join(""))))
+}
+
+fn float_number(s: &str) -> IResult<&str, String> {
+    let (s, (int, frac, exp)) = tuple((unsigned_number, opt(preceded(char('.'), unsigned_number)), opt(float_exponent)))(s)?;
+    let frac = frac.map(|frac| frac.join(""));
+    if frac.is_none() && exp.is_none() {
+        return Err(nom::Err::Error(nom::error::Error::new(s, nom::error::ErrorKind::Float)))
+    }
+    Ok((s, format!("{}.{}{}", int.join(""), frac.unwrap_or_else(|| "0".to_string()), exp.unwrap_or_default())))
+}
+
+pub fn literal_f64(s: &str) -> IResult<&str, Literal> {
+    let (s, number) = alt((
+            terminated(float_number, opt(tag("f64"))),
+            map(terminated(unsigned_number, tag("f64")), |int| format!("{}.0", int.join(""))),
+            ))(s)?;
+    Ok((s, Literal::F64(LiteralF64 { number })))
+}
+
+fn escaped_char(s: &str) -> IResult<&str, &str> {
+    recognize(tuple((char('\\'), one_of("ntr0\\'\""))))(s)
+}
+
+pub fn literal_char(s: &str) -> IResult<&str, Literal> {
+    let (s, (_, text, _)) = tuple((char('\''), alt((escaped_char, recognize(satisfy(|c| c.is_ascii() && c != '\'' && c != '\\')))), char('\'')))(s)?;
+    Ok((s, Literal::Char(LiteralChar { text: text.to_string() })))
+}
+
+pub fn literal_string(s: &str) -> IResult<&str, Literal> {
+    let start = s;
+    let (s, (_, text, _)) = tuple((char('"'), recognize(many0(alt((escaped_char, is_not("\"\\"))))), char('"')))(s)?;
+    Ok((s, Literal::Str(LiteralString { text: text.to_string(), tag: Tag::with_span(Span::between(start, s)) })))
+}
+
 pub fn unsigned_number(s: &str) -> IResult<&str, Vec<&str>> {
     let (s, x) = digit1(s)?;
     fold_many0(alt((tag("_"), digit1)), vec![x], |mut acc: Vec<_>, item| {
-        acc.push(item);
+        if item != "_" {
+            acc.push(item);
+        }
         acc
     })(s)
 }
@@ -132,8 +338,58 @@ fn parse_literal_i64_test() {
     log::debug!("{:?}", parse_literal("6_5_9i64"));
 }
 
+#[test]
+fn parse_literal_int_test() {
+    log::debug!("{:?}", parse_literal("659i32"));
+    log::debug!("{:?}", parse_literal("1_000_000_007u128"));
+    log::debug!("{:?}", parse_literal("255u8"));
+}
+
+#[test]
+fn parse_literal_f64_test() {
+    log::debug!("{:?}", parse_literal("3.14"));
+    log::debug!("{:?}", parse_literal("2f64"));
+    log::debug!("{:?}", parse_literal("1_000.5f64"));
+    assert!(matches!(parse_literal("1e9"), Ok(("", UnaryExpr::Literal(Literal::F64(LiteralF64 { ref number })))) if number == "1.0e9"));
+    assert!(matches!(parse_literal("1e-9"), Ok(("", UnaryExpr::Literal(Literal::F64(LiteralF64 { ref number })))) if number == "1.0e-9"));
+    assert!(matches!(parse_literal("2.5E+3f64"), Ok(("", UnaryExpr::Literal(Literal::F64(LiteralF64 { ref number })))) if number == "2.5e+3"));
+    assert!(matches!(parse_literal("10u64"), Ok(("", UnaryExpr::Literal(Literal::U64(_))))));
+}
+
+#[test]
+fn parse_literal_char_string_test() {
+    log::debug!("{:?}", parse_literal("'a'"));
+    log::debug!("{:?}", parse_literal("'\\n'"));
+    log::debug!("{:?}", parse_literal("\"hello, \\\"niu\\\"\""));
+}
+
 #[test]
 fn parse_literal_boolean_test() {
     log::debug!("{:?}", parse_literal("true"));
     log::debug!("{:?}", parse_literal("false"));
 }
+
+#[test]
+fn check_int_range_test() {
+    assert!(check_int_range("255", "u8", &Span::empty(), false).is_ok());
+    assert!(check_int_range("300", "u8", &Span::empty(), false).is_err());
+    assert!(check_int_range("340282366920938463463374607431768211455", "u128", &Span::empty(), false).is_ok());
+    assert!(check_int_range("340282366920938463463374607431768211456", "u128", &Span::empty(), false).is_err());
+    assert!(check_int_range("2147483648", "i32", &Span::empty(), false).is_err());
+    assert!(check_int_range("2147483648", "i32", &Span::empty(), true).is_ok());
+    assert!(check_int_range("2147483649", "i32", &Span::empty(), true).is_err());
+    assert!(check_int_range("9223372036854775808", "i64", &Span::empty(), false).is_err());
+    assert!(check_int_range("9223372036854775808", "i64", &Span::empty(), true).is_ok());
+    assert!(check_int_range("9223372036854775809", "i64", &Span::empty(), true).is_err());
+    assert!(check_int_range("170141183460469231731687303715884105728", "i128", &Span::empty(), true).is_ok());
+    assert!(check_int_range("1", "u64", &Span::empty(), true).is_ok());
+}
+
+#[test]
+fn int_fits_test() {
+    assert!(int_fits(-3, "i32"));
+    assert!(int_fits(i32::MIN as i128, "i32"));
+    assert!(!int_fits(i32::MIN as i128 - 1, "i32"));
+    assert!(!int_fits(-1, "u64"));
+    assert!(int_fits(u64::MAX as i128, "u64"));
+}
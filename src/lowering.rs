@@ -0,0 +1,132 @@
+//! Lowering of value-producing `if`, `match`, `loop`, blocks and `[x; N]` into C++ statements.
+//!
+//! This runs while transpiling: every statement opens a prelude with `statement`, and
+//! expressions inside it hoist their temporaries into that prelude with `temporary`,
+//! `bind` and `operands`. Positions that are evaluated more than once, like the
+//! condition of a loop, `capture` their prelude and place it in front of each
+//! evaluation, so every expression in a function body is inside some prelude and
+//! none needs a lambda. Only the initializer of an associated `const` has no statement
+//! around it; `constant` wraps it in a `constexpr` lambda when it needs a prelude and
+//! initializes the temporaries, as a constant expression requires.
+//!
+//! A `for(;;)` whose condition or update needs a prelude becomes a `while(true)` loop.
+//! `continue` in its body jumps to the update with the label from `continue_label`.
+
+use std::cell::{ Cell, RefCell };
+
+use crate::identifier::Tag;
+
+#[derive(Debug, Clone)]
+pub enum BlockTarget {
+    Return,
+    Void,
+    Assign(String),
+}
+
+pub fn temporary_name(tag: usize) -> String {
+    format!("lowered_{}", tag)
+}
+
+fn is_literal(s: &str) -> bool {
+    s == "true" || s == "false" || s.starts_with(|c: char| c.is_ascii_digit()) && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+}
+
+#[derive(Debug, Default)]
+pub struct Lowering {
+    preludes: RefCell<Vec<String>>,
+    breaks: RefCell<Vec<Option<BlockTarget>>>,
+    continues: RefCell<Vec<Option<String>>>,
+    in_constant: Cell<bool>,
+}
+
+impl Lowering {
+    pub fn new() -> Self {
+        Lowering::default()
+    }
+    pub fn capture(&self, f: impl FnOnce() -> String) -> (String, String) {
+        self.preludes.borrow_mut().push(String::new());
+        let s = f();
+        let prelude = self.preludes.borrow_mut().pop().unwrap();
+        (prelude, s)
+    }
+    pub fn statement(&self, f: impl FnOnce() -> String) -> String {
+        let (prelude, s) = self.capture(f);
+        format!("{}{}", prelude, s)
+    }
+    pub fn constant(&self, f: impl FnOnce() -> String) -> String {
+        let before = self.in_constant.replace(true);
+        let (prelude, s) = self.capture(f);
+        self.in_constant.set(before);
+        if prelude.is_empty() {
+            s
+        }
+        else {
+            // a constant initializer has no statement to put the prelude in front of
+            format!("[](){{\n{}return {};\n}}()", prelude, s)
+        }
+    }
+    pub fn declare(&self, ty: String, tmp: &str) -> String {
+        // variables in a constexpr function must be initialized before C++20
+        if self.in_constant.get() { format!("{} {}{{}};\n", ty, tmp) } else { format!("{} {};\n", ty, tmp) }
+    }
+    fn with_prelude<R>(&self, f: impl FnOnce(&mut String) -> R) -> R {
+        f(self.preludes.borrow_mut().last_mut().expect("expression lowered outside of a statement"))
+    }
+    pub fn hoist(&self, s: String) {
+        self.with_prelude(|prelude| prelude.push_str(&s))
+    }
+    pub fn bind(&self, tag: usize, place: String) -> String {
+        let tmp = temporary_name(tag);
+        self.hoist(format!("auto& {} = {};\n", tmp, place));
+        tmp
+    }
+    pub fn in_loop(&self, target: Option<BlockTarget>, f: impl FnOnce() -> String) -> String {
+        self.breaks.borrow_mut().push(target);
+        let s = f();
+        self.breaks.borrow_mut().pop();
+        s
+    }
+    pub fn break_target(&self) -> Option<BlockTarget> {
+        self.breaks.borrow().last().cloned().flatten()
+    }
+    pub fn in_loop_body(&self, label: Option<String>, f: impl FnOnce() -> String) -> String {
+        self.continues.borrow_mut().push(label);
+        let s = f();
+        self.continues.borrow_mut().pop();
+        s
+    }
+    pub fn continue_label(&self) -> Option<String> {
+        self.continues.borrow().last().cloned().flatten()
+    }
+    pub fn operands<T>(&self, items: &[T], f: impl Fn(&T) -> String) -> Vec<String> {
+        let parts = items.iter().map(|item| self.capture(|| f(item))).collect::<Vec<_>>();
+        let last = parts.iter().rposition(|(prelude, _)| !prelude.is_empty()).unwrap_or(0);
+        self.with_prelude(|current| parts.into_iter().enumerate().map(|(i, (prelude, s))| {
+            current.push_str(&prelude);
+            if i < last && !is_literal(&s) {
+                let tmp = temporary_name(Tag::new().get_num());
+                current.push_str(&format!("auto {} = {};\n", tmp, s));
+                tmp
+            }
+            else {
+                s
+            }
+        }).collect())
+    }
+    pub fn temporary(&self, tag: usize, ty: String, body: impl FnOnce(&BlockTarget) -> String) -> String {
+        let tmp = temporary_name(tag);
+        let body = body(&BlockTarget::Assign(tmp.clone()));
+        self.hoist(format!("{}{}", self.declare(ty, &tmp), body));
+        tmp
+    }
+}
+
+impl BlockTarget {
+    pub fn wrap(&self, expr: String) -> String {
+        match *self {
+            BlockTarget::Return => format!("return {};\n", expr),
+            BlockTarget::Void => format!("{};\n", expr),
+            BlockTarget::Assign(ref tmp) => format!("{} = {};\n", tmp, expr),
+        }
+    }
+}
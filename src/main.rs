@@ -16,6 +16,7 @@ pub mod block;
 
 pub mod type_id;
 pub mod type_spec;
+pub mod type_alias;
 pub mod func_definition;
 
 pub mod full_content;
@@ -24,6 +25,12 @@ pub mod unify;
 
 pub mod trans;
 
+pub mod lowering;
+
+pub mod tree_shaking;
+
+pub mod snippet;
+
 pub mod traits;
 
 pub mod structs;
@@ -32,43 +39,103 @@ pub mod cpp_inline;
 
 pub mod mut_checker;
 
+pub mod diagnostics;
+
+pub mod comment;
+
+pub mod module;
+
+pub mod manifest;
+
 use std::path::*;
 
 //use crate::trans::Transpile;
 
-use nom::IResult;
-use nom::bytes::complete::*;
-use nom::character::complete::*;
-use nom::sequence::*;
-use nom::multi::*;
-
-fn get_import_path() -> Result<Vec<PathBuf>, String> {
-    let paths = std::env::var("NIU_IMPORT_PATH").unwrap_or(format!(""));
-    let parse: IResult<&str, Vec<_>> = many0(tuple((is_not(";"), char(';'))))(paths.as_str());
-    let (s, paths) = parse.map_err(|e| format!("{:?}", e))?;
-    if s != "" {
-        Err(format!("get import path remaining {}", s))
-    }
-    else {
-        Ok(paths.into_iter().map(|(path, _)| Path::new(path).to_path_buf()).collect())
+use crate::manifest::{ Manifest, ImportRoot };
+use crate::snippet::{ OutputMode, DEFAULT_NAMESPACE };
+
+#[derive(Debug, Default)]
+struct Options {
+    input: Option<PathBuf>,
+    import_paths: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    manifest: Option<PathBuf>,
+    entry_items: Vec<String>,
+    emit_all: bool,
+    library: bool,
+    namespace: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut opts = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("{} needs an argument", flag));
+        match arg.as_str() {
+            "-I" => opts.import_paths.push(PathBuf::from(value("-I")?)),
+            "-o" => opts.output = Some(PathBuf::from(value("-o")?)),
+            "--manifest" => opts.manifest = Some(PathBuf::from(value("--manifest")?)),
+            "--entry-item" => opts.entry_items.push(value("--entry-item")?),
+            "--emit-all" => opts.emit_all = true,
+            "--library" => opts.library = true,
+            "--namespace" => opts.namespace = Some(value("--namespace")?),
+            flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+            _ if opts.input.is_some() => return Err(format!("unexpected argument {}", arg)),
+            _ => opts.input = Some(PathBuf::from(arg)),
+        }
     }
+    Ok(opts)
+}
+
+fn get_import_path() -> Vec<PathBuf> {
+    std::env::var("NIU_IMPORT_PATH").unwrap_or_default()
+        .split(';').filter(|path| !path.is_empty())
+        .map(PathBuf::from).collect()
 }
 
-fn type_check() -> Result<String, String> {
-    let args = std::env::args().collect::<Vec<_>>();
-    let filename = args.get(1).ok_or("no filepath")?;
-    let import_path = get_import_path()?;
-    let mut t = crate::full_content::parse_full_content_from_file(&filename, &import_path).map_err(|e| format!("{:?}", e))?;
+fn type_check() -> Result<(), String> {
+    let opts = parse_args(std::env::args().skip(1))?;
+    let manifest = match (opts.manifest.as_ref(), opts.input.as_ref()) {
+        (Some(path), _) => Some(Manifest::load(path)?),
+        (None, Some(input)) => Manifest::discover(input.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")))?,
+        (None, None) => Manifest::discover(Path::new("."))?,
+    };
+    let filename = opts.input.clone()
+        .or_else(|| manifest.as_ref().and_then(|m| m.entry.clone()))
+        .ok_or("no filepath")?;
+    let import_roots = opts.import_paths.iter().cloned()
+        .chain(get_import_path())
+        .map(ImportRoot::new)
+        .chain(manifest.iter().flat_map(|m| m.import_roots()))
+        .collect::<Vec<_>>();
+    let mut t = crate::full_content::parse_full_content_from_file(&filename, &import_roots)?;
     //log::debug!("{:?}", t);
     let mut ta = t.type_check()?;
     t.mut_check(&ta)?;
-    Ok(t.transpile(&mut ta))
+    let mode = if opts.library || manifest.as_ref().and_then(|m| m.library).unwrap_or(false) {
+        let namespace = opts.namespace.or_else(|| manifest.as_ref().and_then(|m| m.namespace.clone()));
+        OutputMode::Library(namespace.unwrap_or_else(|| DEFAULT_NAMESPACE.to_string()))
+    }
+    else {
+        OutputMode::Program
+    };
+    let tree_shaking = !opts.emit_all && manifest.as_ref().and_then(|m| m.tree_shaking).unwrap_or(true);
+    let roots = if !tree_shaking { Vec::new() }
+        else if !opts.entry_items.is_empty() { t.entry_items(&opts.entry_items)? }
+        else if mode == OutputMode::Program { t.main_items() }
+        else { Vec::new() };
+    let prog = t.transpile(&mut ta, &roots, &mode);
+    match opts.output.or_else(|| manifest.and_then(|m| m.output)) {
+        Some(out) => std::fs::write(&out, prog).map_err(|e| format!("cant write {:?}: {:?}", out, e)),
+        None => {
+            println!("{}", prog);
+            Ok(())
+        }
+    }
 }
 
 fn main() {
     env_logger::init();
-    match type_check() {
-        Ok(prog) => println!("{}", prog),
-        Err(err) => log::error!("{}", err),
+    if let Err(err) = type_check() {
+        log::error!("{}", err);
     }
 }
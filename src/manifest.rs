@@ -0,0 +1,201 @@
+use std::path::*;
+
+use nom::IResult;
+use nom::branch::*;
+use nom::bytes::complete::*;
+use nom::character::complete::*;
+use nom::combinator::*;
+use nom::multi::*;
+use nom::sequence::*;
+
+pub const MANIFEST_NAME: &str = "niu.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestValue {
+    Str(String),
+    Bool(bool),
+    Array(Vec<ManifestValue>),
+    Table(Vec<(String, ManifestValue)>),
+}
+
+impl ManifestValue {
+    fn type_name(&self) -> &'static str {
+        match *self {
+            ManifestValue::Str(_) => "string",
+            ManifestValue::Bool(_) => "bool",
+            ManifestValue::Array(_) => "array",
+            ManifestValue::Table(_) => "table",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportRoot {
+    pub name: Option<String>,
+    pub dir: PathBuf,
+}
+
+impl ImportRoot {
+    pub fn new(dir: PathBuf) -> Self {
+        ImportRoot { name: None, dir }
+    }
+    pub fn named(name: &str, dir: PathBuf) -> Self {
+        ImportRoot { name: Some(name.to_string()), dir }
+    }
+    pub fn resolve(&self, import: &str) -> Option<PathBuf> {
+        match self.name {
+            Some(ref name) => import.strip_prefix(name.as_str()).and_then(|rest| rest.strip_prefix('/')).map(|rest| self.dir.join(rest)),
+            None => Some(self.dir.join(import)),
+        }
+    }
+    pub fn canonicalize(&self) -> Option<Self> {
+        self.dir.canonicalize().ok().map(|dir| ImportRoot { name: self.name.clone(), dir })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    pub dir: PathBuf,
+    pub entry: Option<PathBuf>,
+    pub roots: Vec<PathBuf>,
+    pub dependencies: Vec<(String, PathBuf)>,
+    pub output: Option<PathBuf>,
+    pub tree_shaking: Option<bool>,
+    pub library: Option<bool>,
+    pub namespace: Option<String>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|_| format!("cant open {:?}", path))?;
+        let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let entries = match parse_manifest(&text) {
+            Ok(("", entries)) => entries,
+            Ok((s, _)) | Err(nom::Err::Error(nom::error::Error { input: s, .. })) | Err(nom::Err::Failure(nom::error::Error { input: s, .. })) => {
+                let line = text[..text.len() - s.len()].lines().count().max(1);
+                return Err(format!("{:?}:{}: manifest parse error", path, line))
+            }
+            Err(nom::Err::Incomplete(_)) => return Err(format!("{:?}: manifest parse error, incomplete input", path)),
+        };
+        let mut manifest = Manifest { dir: dir.clone(), ..Manifest::default() };
+        for (table, key, value) in entries {
+            match (table.as_str(), key.as_str(), value) {
+                ("package", "entry", ManifestValue::Str(entry)) => manifest.entry = Some(dir.join(entry)),
+                ("library", "roots", ManifestValue::Array(roots)) => {
+                    for root in roots {
+                        match root {
+                            ManifestValue::Str(root) => manifest.roots.push(dir.join(root)),
+                            value => return Err(format!("{:?}: library.roots must be an array of strings, but {} is found", path, value.type_name())),
+                        }
+                    }
+                }
+                ("dependencies", name, ManifestValue::Str(dep)) => manifest.dependencies.push((name.to_string(), dir.join(dep))),
+                ("dependencies", name, ManifestValue::Table(items)) => {
+                    match items.into_iter().find(|(k, _)| k == "path") {
+                        Some((_, ManifestValue::Str(dep))) => manifest.dependencies.push((name.to_string(), dir.join(dep))),
+                        _ => return Err(format!("{:?}: dependency {} needs a string path", path, name)),
+                    }
+                }
+                ("output", "path", ManifestValue::Str(out)) => manifest.output = Some(dir.join(out)),
+                ("output", "tree_shaking", ManifestValue::Bool(b)) => manifest.tree_shaking = Some(b),
+                ("output", "library", ManifestValue::Bool(b)) => manifest.library = Some(b),
+                ("output", "namespace", ManifestValue::Str(namespace)) => manifest.namespace = Some(namespace),
+                (table, key, value) => return Err(format!("{:?}: unexpected key {}.{} of {}", path, table, key, value.type_name())),
+            }
+        }
+        Ok(manifest)
+    }
+    pub fn discover(start: &Path) -> Result<Option<Self>, String> {
+        let start = start.canonicalize().map_err(|e| format!("{:?}", e))?;
+        match start.ancestors().map(|dir| dir.join(MANIFEST_NAME)).find(|path| path.is_file()) {
+            Some(path) => Manifest::load(&path).map(Some),
+            None => Ok(None),
+        }
+    }
+    pub fn import_roots(&self) -> Vec<ImportRoot> {
+        self.roots.iter().cloned().map(ImportRoot::new)
+            .chain(self.dependencies.iter().map(|(name, dir)| ImportRoot::named(name, dir.clone())))
+            .collect()
+    }
+}
+
+fn comment(s: &str) -> IResult<&str, &str> {
+    recognize(pair(char('#'), not_line_ending))(s)
+}
+
+fn blank(s: &str) -> IResult<&str, ()> {
+    let (s, _) = many0(alt((multispace1, comment)))(s)?;
+    Ok((s, ()))
+}
+
+fn inline_space(s: &str) -> IResult<&str, &str> {
+    take_while(|c| c == ' ' || c == '\t')(s)
+}
+
+fn parse_key(s: &str) -> IResult<&str, String> {
+    alt((
+        map(take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-'), |k: &str| k.to_string()),
+        parse_string,
+    ))(s)
+}
+
+fn parse_string(s: &str) -> IResult<&str, String> {
+    let (s, (_, chars, _)) = tuple((char('"'), many0(alt((
+                        preceded(char('\\'), alt((value('"', char('"')), value('\\', char('\\')), value('\n', char('n')), value('\t', char('t'))))),
+                        none_of("\"\\\n"),
+                    ))), char('"')))(s)?;
+    Ok((s, chars.into_iter().collect()))
+}
+
+fn parse_array(s: &str) -> IResult<&str, ManifestValue> {
+    let (s, (_, _, values, _, _, _)) = tuple((char('['), blank,
+            separated_list0(tuple((blank, char(','), blank)), parse_value),
+            blank, opt(tuple((char(','), blank))), char(']')))(s)?;
+    Ok((s, ManifestValue::Array(values)))
+}
+
+fn parse_inline_table(s: &str) -> IResult<&str, ManifestValue> {
+    let (s, (_, _, items, _, _)) = tuple((char('{'), inline_space,
+            separated_list0(tuple((inline_space, char(','), inline_space)), parse_key_value),
+            inline_space, char('}')))(s)?;
+    Ok((s, ManifestValue::Table(items)))
+}
+
+fn parse_value(s: &str) -> IResult<&str, ManifestValue> {
+    alt((
+        map(parse_string, ManifestValue::Str),
+        value(ManifestValue::Bool(true), tag("true")),
+        value(ManifestValue::Bool(false), tag("false")),
+        parse_array,
+        parse_inline_table,
+    ))(s)
+}
+
+fn parse_key_value(s: &str) -> IResult<&str, (String, ManifestValue)> {
+    let (s, (key, _, _, _, value)) = tuple((parse_key, inline_space, char('='), inline_space, parse_value))(s)?;
+    Ok((s, (key, value)))
+}
+
+fn parse_table_header(s: &str) -> IResult<&str, String> {
+    let (s, (_, _, key, _, _)) = tuple((char('['), inline_space, parse_key, inline_space, char(']')))(s)?;
+    Ok((s, key))
+}
+
+pub fn parse_manifest(s: &str) -> IResult<&str, Vec<(String, String, ManifestValue)>> {
+    let (s, (_, (top, tables))) = tuple((blank, pair(
+                many0(terminated(parse_key_value, blank)),
+                many0(pair(terminated(parse_table_header, blank), many0(terminated(parse_key_value, blank))))
+            )))(s)?;
+    let entries = top.into_iter().map(|(k, v)| (String::new(), k, v))
+        .chain(tables.into_iter().flat_map(|(table, items)| items.into_iter().map(move |(k, v)| (table.clone(), k, v))))
+        .collect();
+    Ok((s, entries))
+}
+
+#[test]
+fn parse_manifest_test() {
+    let (s, entries) = parse_manifest("# comment\n[package]\nentry = \"src/main.niu\"\n\n[library]\nroots = [\"lib\", \"../common\",]\n[dependencies]\nalgo = { path = \"../algo\" }\n").unwrap();
+    assert_eq!(s, "");
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[2].2, ManifestValue::Table(vec![("path".to_string(), ManifestValue::Str("../algo".to_string()))]));
+}
@@ -0,0 +1,260 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use nom::IResult;
+use nom::bytes::complete::*;
+use nom::combinator::*;
+use nom::multi::*;
+use nom::sequence::*;
+
+use crate::comment::multispace1;
+use crate::identifier::{ Identifier, parse_identifier, parse_keyword };
+use crate::manifest::ImportRoot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    Type,
+    Trait,
+    Func,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UseTarget {
+    Item(String),
+    Glob,
+}
+
+#[derive(Debug, Clone)]
+pub struct UseDeclaration {
+    pub module: Vec<Identifier>,
+    pub target: UseTarget,
+}
+
+impl UseDeclaration {
+    pub fn module_path(&self) -> String {
+        self.module.iter().map(|id| id.into_string()).collect::<Vec<_>>().join("::")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleScope {
+    pub module: String,
+    names: HashMap<(ItemKind, String), Vec<String>>,
+}
+
+#[derive(Debug, Default)]
+struct ModuleTable {
+    items: HashMap<String, HashMap<(ItemKind, String), bool>>,
+}
+
+thread_local! {
+    static MODULES: RefCell<ModuleTable> = RefCell::new(ModuleTable::default());
+}
+
+pub fn is_prelude_module(module: &str) -> bool {
+    module == "std" || module.starts_with("std::")
+}
+
+pub fn module_path_from_file(file: &Path, roots: &[ImportRoot]) -> String {
+    match roots.iter().find_map(|root| file.strip_prefix(&root.dir).ok().map(|rel| (root, rel))) {
+        Some((root, rel)) => root.name.iter().cloned()
+            .chain(rel.with_extension("").components().map(|c| c.as_os_str().to_string_lossy().to_string()))
+            .collect::<Vec<_>>().join("::"),
+        None => file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+    }
+}
+
+pub fn split_module_path(name: &str) -> (Option<&str>, &str) {
+    match name.rsplit_once("::") {
+        Some((module, short)) => (Some(module), short),
+        None => (None, name),
+    }
+}
+
+pub fn transpile_in_namespace(name: &str, decl: impl FnOnce(&str) -> String) -> String {
+    match split_module_path(name) {
+        (Some(module), short) => format!("namespace {} {{\n{}}}\n", module, decl(short)),
+        (None, short) => decl(short),
+    }
+}
+
+pub fn regist_module(module: &str) {
+    MODULES.with(|m| {
+        m.borrow_mut().items.entry(module.to_string()).or_default();
+    })
+}
+
+pub fn regist_module_item(module: &str, kind: ItemKind, name: &str, is_pub: bool) -> Result<(), String> {
+    MODULES.with(|m| {
+        let mut m = m.borrow_mut();
+        let items = m.items.entry(module.to_string()).or_default();
+        match items.insert((kind, name.to_string()), is_pub) {
+            Some(_) => Err(format!("{} is defined multiple times in module {}", name, module)),
+            None => Ok(()),
+        }
+    })
+}
+
+pub fn is_module(path: &str) -> bool {
+    MODULES.with(|m| m.borrow().items.contains_key(path))
+}
+
+pub fn qualified_name(module: &str, name: &str) -> String {
+    if module.is_empty() || is_prelude_module(module) {
+        name.to_string()
+    }
+    else {
+        format!("{}::{}", module, name)
+    }
+}
+
+fn public_items(module: &str) -> Vec<(ItemKind, String)> {
+    MODULES.with(|m| m.borrow().items.get(module)
+        .map(|items| items.iter().filter(|(_, is_pub)| **is_pub).map(|(key, _)| key.clone()).collect())
+        .unwrap_or_default())
+}
+
+impl ModuleScope {
+    pub fn new(module: &str) -> Self {
+        ModuleScope { module: module.to_string(), names: HashMap::new() }
+    }
+    pub fn build(module: &str, uses: &[UseDeclaration], imports: &[String]) -> Result<Self, String> {
+        let mut scope = ModuleScope::new(module);
+        let mut explicit = HashMap::new();
+        let mut globs: HashMap<(ItemKind, String), Vec<String>> = HashMap::new();
+        let add_glob = |target: &str, globs: &mut HashMap<(ItemKind, String), Vec<String>>| {
+            for (kind, name) in public_items(target) {
+                let qualified = qualified_name(target, &name);
+                let candidates = globs.entry((kind, name)).or_default();
+                if !candidates.contains(&qualified) {
+                    candidates.push(qualified);
+                }
+            }
+        };
+        for import in imports.iter() {
+            if !is_prelude_module(import) && import != module {
+                add_glob(import, &mut globs);
+            }
+        }
+        for u in uses.iter() {
+            let target = u.module_path();
+            if !is_module(&target) {
+                return Err(u.module[0].get_span().merge(&u.module[u.module.len() - 1].get_span()).error_message(&format!("module {} is not found", target)))
+            }
+            match u.target {
+                UseTarget::Glob => add_glob(&target, &mut globs),
+                UseTarget::Item(ref name) => {
+                    let found = MODULES.with(|m| m.borrow().items[&target].iter()
+                        .filter(|((_, n), _)| n == name)
+                        .map(|((kind, _), is_pub)| (*kind, *is_pub)).collect::<Vec<_>>());
+                    if found.is_empty() {
+                        return Err(u.module[0].get_span().error_message(&format!("module {} doesnt have item {}", target, name)))
+                    }
+                    for (kind, is_pub) in found {
+                        if !is_pub && target != module {
+                            return Err(u.module[0].get_span().error_message(&format!("{} is private in module {}", name, target)))
+                        }
+                        explicit.insert((kind, name.clone()), vec![qualified_name(&target, name)]);
+                    }
+                }
+            }
+        }
+        let own = MODULES.with(|m| m.borrow().items.get(module).map(|items| items.keys().cloned().collect::<Vec<_>>()).unwrap_or_default());
+        scope.names = globs;
+        scope.names.extend(explicit);
+        for (kind, name) in own {
+            let qualified = qualified_name(module, &name);
+            scope.names.insert((kind, name), vec![qualified]);
+        }
+        Ok(scope)
+    }
+}
+
+pub trait Resolve {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String>;
+}
+
+impl<T: Resolve> Resolve for Vec<T> {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.iter_mut().try_for_each(|x| x.resolve(scope))
+    }
+}
+
+impl<T: Resolve> Resolve for Option<T> {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.iter_mut().try_for_each(|x| x.resolve(scope))
+    }
+}
+
+impl<T: Resolve> Resolve for Box<T> {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.as_mut().resolve(scope)
+    }
+}
+
+impl ModuleScope {
+    pub fn resolve_item(&self, kind: ItemKind, id: &mut Identifier) -> Result<(), String> {
+        let span = id.get_span();
+        let name = match split_module_path(&id.name) {
+            (Some(module), name) => {
+                if !is_module(module) {
+                    return Err(span.error_message(&format!("module {} is not found", module)))
+                }
+                let is_pub = MODULES.with(|m| m.borrow().items[module].get(&(kind, name.to_string())).cloned());
+                if is_pub == Some(false) && module != self.module {
+                    return Err(span.error_message(&format!("{} is private in module {}", name, module)))
+                }
+                qualified_name(module, name)
+            }
+            (None, name) => match self.names.get(&(kind, name.to_string())) {
+                Some(candidates) if candidates.len() > 1 => {
+                    return Err(span.error_message(&format!("{} is ambiguous, candidates are {}", name, candidates.join(", "))))
+                }
+                Some(candidates) => candidates[0].clone(),
+                None => return Ok(()),
+            }
+        };
+        id.name = name;
+        Ok(())
+    }
+}
+
+fn parse_item_path(s: &str) -> IResult<&str, Vec<Identifier>> {
+    let (s, (head, tails)) = tuple((parse_identifier, many0(preceded(tag("::"), parse_identifier))))(s)?;
+    Ok((s, std::iter::once(head).chain(tails).collect()))
+}
+
+fn join_item_path(mut segs: Vec<Identifier>) -> Identifier {
+    if segs.len() == 1 {
+        return segs.pop().unwrap()
+    }
+    let span = segs[0].get_span().merge(&segs[segs.len() - 1].get_span());
+    Identifier::from_str(&segs.iter().map(|id| id.into_string()).collect::<Vec<_>>().join("::")).with_span(span)
+}
+
+pub fn parse_item_identifier(s: &str) -> IResult<&str, Identifier> {
+    let (s, segs) = parse_item_path(s)?;
+    Ok((s, join_item_path(segs)))
+}
+
+pub fn parse_item_member(s: &str) -> IResult<&str, (Identifier, Identifier)> {
+    let (rest, mut segs) = parse_item_path(s)?;
+    if segs.len() < 2 {
+        return Err(nom::Err::Error(nom::error::Error::new(s, nom::error::ErrorKind::Tag)))
+    }
+    let member = segs.pop().unwrap();
+    Ok((rest, (join_item_path(segs), member)))
+}
+
+pub fn parse_visibility(s: &str) -> IResult<&str, bool> {
+    let (s, vis) = opt(terminated(parse_keyword("pub"), multispace1))(s)?;
+    Ok((s, vis.is_some()))
+}
+
+#[test]
+fn parse_item_identifier_test() {
+    assert_eq!(parse_item_identifier("math::modint::Modint::new()").map(|(s, id)| (s, id.name)), Ok(("()", "math::modint::Modint::new".to_string())));
+    assert_eq!(parse_item_member("Modint::new()").map(|(s, (ty, id))| (s, ty.name, id.name)), Ok(("()", "Modint".to_string(), "new".to_string())));
+    assert!(parse_item_member("init()").is_err());
+}
@@ -41,6 +41,6 @@ impl VariablesInfo {
                     );
             }
         }
-        Err(format!("not found variable {:?}", var))
+        Err(var.get_span().error_message(&format!("not found variable {}", var.name)))
     }
 }
@@ -0,0 +1,88 @@
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputMode {
+    Program,
+    Library(String),
+}
+
+pub const DEFAULT_NAMESPACE: &str = "niu";
+
+const STD_HEADERS: [(&str, &[&str]); 15] = [
+    ("cstdint", &["int_fast64_t", "uint_fast64_t", "int32_t", "uint32_t", "int64_t", "uint64_t", "uint8_t"]),
+    ("cstddef", &["size_t"]),
+    ("type_traits", &["true_type", "false_type", "enable_if", "enable_if_t", "conjunction", "conjunction_v", "decay_t", "is_same", "is_same_v", "is_invocable_r", "void_t", "integral_constant"]),
+    ("utility", &["declval", "move", "swap", "pair", "make_pair", "in_place_index"]),
+    ("tuple", &["tuple", "get", "make_tuple", "tie", "apply"]),
+    ("variant", &["variant", "holds_alternative"]),
+    ("array", &["array"]),
+    ("vector", &["vector"]),
+    ("string", &["string", "to_string"]),
+    ("functional", &["function", "hash"]),
+    ("algorithm", &["sort", "min", "max", "reverse", "lower_bound", "upper_bound", "fill"]),
+    ("numeric", &["gcd", "lcm", "accumulate", "iota"]),
+    ("cmath", &["sqrt", "pow", "abs"]),
+    ("iostream", &["cin", "cout", "cerr", "endl"]),
+    ("map", &["map"]),
+];
+
+fn std_names(code: &str) -> BTreeSet<&str> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    code.match_indices("std::")
+        .filter(|(i, _)| !code[..*i].ends_with(is_ident))
+        .map(|(i, m)| {
+            let rest = &code[i + m.len()..];
+            &rest[..rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len())]
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+pub fn required_headers(code: &str) -> Vec<&'static str> {
+    let mut headers = BTreeSet::new();
+    for name in std_names(code) {
+        match STD_HEADERS.iter().find(|(_, names)| names.contains(&name)) {
+            Some((header, _)) => { headers.insert(*header); }
+            None => {
+                log::warn!("no header is known for std::{}, bits/stdc++.h is included", name);
+                headers.insert("bits/stdc++.h");
+            }
+        }
+    }
+    headers.into_iter().collect()
+}
+
+// FNV-1a keeps guards stable across compilers, unlike std's DefaultHasher
+fn body_hash(body: &str) -> u64 {
+    body.bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+// the body hash keeps same-named items from different sources from hiding each other
+pub fn guard_declaration(namespace: &str, key: &str, body: &str) -> String {
+    let guard = format!("NIU_{}_{}_{:016x}", namespace, key, body_hash(body)).chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect::<String>();
+    let newline = if body.ends_with('\n') { "" } else { "\n" };
+    format!("#ifndef {0}\n#define {0}\n{1}{2}#endif // {0}\n", guard, body, newline)
+}
+
+pub fn transpile_output(mode: &OutputMode, body: &str) -> String {
+    match *mode {
+        OutputMode::Program => format!("#include <bits/stdc++.h>\n\n{}", body),
+        OutputMode::Library(ref namespace) => {
+            let includes = required_headers(body).into_iter().map(|header| format!("#include <{}>\n", header)).collect::<Vec<_>>().join("");
+            format!("#pragma once\n{}\nnamespace {} {{\n{}}} // namespace {}\n", includes, namespace, body, namespace)
+        }
+    }
+}
+
+#[test]
+fn guard_declaration_test() {
+    let guard = format!("NIU_NIU_TRAIT_INDEX_0_{:016X}", body_hash("struct Index;\n"));
+    assert_eq!(guard_declaration("niu", "trait Index 0", "struct Index;\n"), format!("#ifndef {0}\n#define {0}\nstruct Index;\n#endif // {0}\n", guard));
+    assert_ne!(guard_declaration("niu", "util helper 0", "int helper() { return 1; }"), guard_declaration("niu", "util helper 0", "int helper() { return 2; }"));
+}
+
+#[test]
+fn required_headers_test() {
+    let code = "std::vector<std::uint_fast64_t> v; std::tuple<bool> t; mystd::foo(); std::get<0>(t);";
+    assert_eq!(required_headers(code), vec!["cstdint", "tuple", "vector"]);
+}
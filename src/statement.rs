@@ -1,8 +1,10 @@
 use nom::branch::*;
-use nom::bytes::complete::*;
+use nom::combinator::*;
+use nom::sequence::*;
 use nom::IResult;
 
-use crate::identifier::Tag;
+use crate::comment::multispace1;
+use crate::identifier::{ Tag, parse_keyword };
 use crate::expression::{ Expression, parse_expression };
 use crate::let_declaration::{ LetDeclaration, parse_let_declaration };
 use crate::mut_checker::MutCheck;
@@ -11,14 +13,30 @@ use crate::mut_checker::VariablesInfo;
 use crate::substitute::*;
 use crate::unify::*;
 use crate::trans::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 pub enum Statement {
     Expression(Expression, Tag),
     LetDeclaration(LetDeclaration),
     Substitute(Substitute),
-    Break,
-    Continue,
+    Break(Option<Expression>, Tag),
+    Continue(Tag),
+    Return(Option<Expression>, Tag),
+}
+
+impl Statement {
+    pub fn get_span(&self) -> Span {
+        match *self {
+            Statement::Expression(_, ref tag) => tag.get_span(),
+            Statement::LetDeclaration(ref l) => l.span,
+            Statement::Substitute(ref s) => s.span,
+            Statement::Break(_, ref tag) => tag.get_span(),
+            Statement::Continue(ref tag) => tag.get_span(),
+            Statement::Return(_, ref tag) => tag.get_span(),
+        }
+    }
 }
 
 impl GenType for Statement {
@@ -35,8 +53,38 @@ impl GenType for Statement {
             Statement::Substitute(ref s) => {
                 s.gen_type(equs, trs)?;
             }
-            Statement::Break => {}
-            Statement::Continue => {}
+            Statement::Break(ref e, ref tag) => {
+                let value_type = match *e {
+                    Some(ref e) => Some(e.gen_type(equs, trs)?),
+                    None => None,
+                };
+                match (equs.get_break_type()?, value_type) {
+                    (Some(break_type), value_type) => {
+                        let alpha = tag.generate_type_variable("BreakType", 0, equs);
+                        equs.add_equation(alpha.clone(), break_type);
+                        equs.add_equation(alpha, value_type.unwrap_or(Type::from_str("void")));
+                    }
+                    (None, Some(_)) => {
+                        return Err(tag.get_span().error_message("break with value is only allowed in loop"))
+                    }
+                    (None, None) => {}
+                }
+            }
+            Statement::Continue(ref tag) => {
+                if !equs.in_loop() {
+                    return Err(tag.get_span().error_message("continue outside of loop"))
+                }
+            }
+            Statement::Return(ref e, ref tag) => {
+                let (return_type, is_main) = equs.get_return_type()?;
+                let value_type = match *e {
+                    Some(ref e) => e.gen_type(equs, trs)?,
+                    None => Type::from_str("void"),
+                };
+                let alpha = tag.generate_type_variable(if is_main { "MainReturnType" } else { "ReturnType" }, 0, equs);
+                equs.add_equation(alpha.clone(), return_type);
+                equs.add_equation(alpha, value_type);
+            }
         };
         Ok(Type::End)
     }
@@ -48,8 +96,29 @@ impl Transpile for Statement {
             Statement::Expression(ref e, _) => e.transpile(ta),
             Statement::LetDeclaration(ref l) => l.transpile(ta),
             Statement::Substitute(ref s) => s.transpile(ta),
-            Statement::Break => format!("break"),
-            Statement::Continue => format!("continue"),
+            Statement::Break(None, _) => format!("break"),
+            Statement::Break(Some(ref e), ref tag) => {
+                if let Some(target) = ta.lowering.break_target() {
+                    return format!("{{ {}break; }}", target.wrap(e.transpile(ta)))
+                }
+                match ta.try_annotation(tag.get_num(), "BreakType", 0) {
+                    Some(ty) if ty != Type::from_str("void") => format!("return {}", e.transpile(ta)),
+                    _ => format!("{{ {}; break; }}", e.transpile(ta)),
+                }
+            }
+            Statement::Continue(_) => match ta.lowering.continue_label() {
+                Some(label) => format!("goto {}", label),
+                None => format!("continue"),
+            },
+            Statement::Return(None, ref tag) => {
+                if ta.try_annotation(tag.get_num(), "MainReturnType", 0).is_some() {
+                    "return 0".to_string()
+                }
+                else {
+                    "return".to_string()
+                }
+            }
+            Statement::Return(Some(ref e), _) => format!("return {}", e.transpile(ta)),
         }
     }
 }
@@ -60,15 +129,33 @@ impl MutCheck for Statement {
             Statement::Expression(ref e, _) => e.mut_check(ta, vars),
             Statement::LetDeclaration(ref l) => l.mut_check(ta, vars),
             Statement::Substitute(ref s) => s.mut_check(ta, vars),
-            Statement::Break => Ok(MutResult::NoType),
-            Statement::Continue => Ok(MutResult::NoType),
+            Statement::Break(ref e, _) | Statement::Return(ref e, _) => {
+                if let Some(ref e) = *e {
+                    e.mut_check(ta, vars)?;
+                }
+                Ok(MutResult::NoType)
+            }
+            Statement::Continue(_) => Ok(MutResult::NoType),
+        }
+    }
+}
+
+impl Resolve for Statement {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        match *self {
+            Statement::Expression(ref mut expr, _) => expr.resolve(scope),
+            Statement::LetDeclaration(ref mut l) => l.resolve(scope),
+            Statement::Substitute(ref mut s) => s.resolve(scope),
+            Statement::Break(ref mut expr, _) | Statement::Return(ref mut expr, _) => expr.resolve(scope),
+            Statement::Continue(_) => Ok(()),
         }
     }
 }
 
 pub fn parse_expression_to_statement(s: &str) -> IResult<&str, Statement> {
     let (s, expr) = parse_expression(s)?;
-    Ok((s, Statement::Expression(expr, Tag::new())))
+    let span = expr.get_span();
+    Ok((s, Statement::Expression(expr, Tag::with_span(span))))
 }
 
 pub fn parse_let_declaration_to_statement(s: &str) -> IResult<&str, Statement> {
@@ -82,20 +169,29 @@ pub fn parse_substitute_to_statement(s: &str) -> IResult<&str, Statement> {
 }
 
 pub fn parse_break_to_statement(s: &str) -> IResult<&str, Statement> {
-    let (s, _) = tag("break")(s)?;
-    Ok((s, Statement::Break))
+    let start = s;
+    let (s, (_, value)) = tuple((parse_keyword("break"), opt(preceded(multispace1, parse_expression))))(s)?;
+    Ok((s, Statement::Break(value, Tag::with_span(Span::between(start, s)))))
 }
 pub fn parse_continue_to_statement(s: &str) -> IResult<&str, Statement> {
-    let (s, _) = tag("continue")(s)?;
-    Ok((s, Statement::Continue))
+    let start = s;
+    let (s, _) = parse_keyword("continue")(s)?;
+    Ok((s, Statement::Continue(Tag::with_span(Span::between(start, s)))))
+}
+pub fn parse_return_to_statement(s: &str) -> IResult<&str, Statement> {
+    let start = s;
+    let (s, (_, value)) = tuple((parse_keyword("return"), opt(preceded(multispace1, parse_expression))))(s)?;
+    Ok((s, Statement::Return(value, Tag::with_span(Span::between(start, s)))))
 }
 
 pub fn parse_statement(s: &str) -> IResult<&str, Statement> {
-    alt((parse_break_to_statement, parse_continue_to_statement, parse_let_declaration_to_statement, parse_substitute_to_statement, parse_expression_to_statement))(s)
+    alt((parse_break_to_statement, parse_continue_to_statement, parse_return_to_statement, parse_let_declaration_to_statement, parse_substitute_to_statement, parse_expression_to_statement))(s)
 }
 
 #[test]
 fn parse_statement_test() {
     log::debug!("{:?}", parse_statement("let x = 1 + 2;"));
     log::debug!("{:?}", parse_statement("clamp(x, y, z);"));
+    log::debug!("{:?}", parse_statement("return x + 1;"));
+    log::debug!("{:?}", parse_statement("break;"));
 }
@@ -1,8 +1,14 @@
 pub mod struct_definition;
 pub use struct_definition::*;
 
+pub mod enum_definition;
+pub use enum_definition::*;
+
 pub mod struct_instantiation;
 pub use struct_instantiation::*;
 
 pub mod impl_self;
 pub use impl_self::*;
+
+pub mod derive;
+pub use derive::*;
@@ -0,0 +1,173 @@
+use nom::IResult;
+use nom::bytes::complete::*;
+use nom::character::complete::*;
+use nom::multi::*;
+use nom::sequence::*;
+use nom::combinator::*;
+
+use crate::comment::multispace0;
+use crate::traits::*;
+use crate::structs::*;
+use crate::diagnostics::set_current_source;
+
+use crate::type_spec::{ TypeSpec, GenericsTypeMap };
+use crate::unify::*;
+use crate::trans::*;
+use crate::snippet::OutputMode;
+
+impl StructDefinition {
+    fn derive_fields(&self) -> Result<Vec<(String, TypeSpec)>, String> {
+        match self.member_def.member {
+            StructMember::MemberInfo(MemberInfo { ref members_order, ref members }) => {
+                Ok(members_order.iter().map(|mem| (mem.into_string(), members[mem].clone())).collect())
+            }
+            _ => Err(self.derives[0].id.get_span().error_message(&format!("cant derive traits for {}, it is not a struct with members", self.member_def.struct_id.id.name))),
+        }
+    }
+
+    pub fn derive_impls(&self) -> Result<Vec<ImplDefinition>, String> {
+        if self.derives.is_empty() {
+            return Ok(Vec::new())
+        }
+        let struct_name = self.member_def.struct_id.id.into_string();
+        let fields = self.derive_fields()?;
+        let generics = &self.member_def.generics;
+        let impl_generics = if generics.is_empty() {
+            String::new()
+        }
+        else {
+            format!("<{}>", generics.iter().map(|g| match g.const_type {
+                Some(ref ty) => format!("const {}: {}", g.id.into_string(), ty),
+                None => g.id.into_string(),
+            }).collect::<Vec<_>>().join(", "))
+        };
+        let self_ty = if generics.is_empty() {
+            struct_name.clone()
+        }
+        else {
+            format!("{}<{}>", struct_name, generics.iter().map(|g| g.id.into_string()).collect::<Vec<_>>().join(", "))
+        };
+
+        let mut impls = Vec::new();
+        for trait_id in self.derives.iter() {
+            let trait_name = trait_id.id.into_string();
+            let method = match trait_name.as_str() {
+                "Clone" => {
+                    let inits = fields.iter().map(|(f, _)| format!("{0}: self.{0}.clone()", f)).collect::<Vec<_>>().join(", ");
+                    format!("fn clone(self: &Self) -> Self {{ {} {{ {} }} }}", struct_name, inits)
+                }
+                "Default" => {
+                    let inits = fields.iter().map(|(f, ty)| format!("{}: {}#Default::default_value()", f, ty)).collect::<Vec<_>>().join(", ");
+                    format!("fn default_value() -> Self {{ {} {{ {} }} }}", struct_name, inits)
+                }
+                "Eq" => {
+                    let conds = fields.iter().map(|(f, _)| format!("a.{0} == b.{0}", f)).collect::<Vec<_>>();
+                    let body = if conds.is_empty() { "true".to_string() } else { conds.join(" && ") };
+                    format!("fn eq(a: Self, b: Self) -> bool {{ {} }}", body)
+                }
+                "Ord" => {
+                    let body = fields.iter().rev().fold(None, |acc, (f, _)| match acc {
+                        None => Some(format!("a.{0} < b.{0}", f)),
+                        Some(rest) => Some(format!("a.{0} < b.{0} || a.{0} == b.{0} && ({1})", f, rest)),
+                    }).unwrap_or_else(|| "false".to_string());
+                    format!("fn lt(a: Self, b: Self) -> bool {{ {} }}", body)
+                }
+                "Hash" => {
+                    let steps = fields.iter().map(|(f, _)| format!("h = h * 1000003 ^ self.{}.hash(); ", f)).collect::<Vec<_>>().join("");
+                    format!("fn hash(self: &Self) -> u64 {{ let mut h = 0; {}h }}", steps)
+                }
+                _ => return Err(trait_id.id.get_span().error_message(&format!("cant derive trait {} for {}", trait_name, struct_name))),
+            };
+            let bounds = generics.iter().filter(|g| !g.is_const()).map(|g| format!("{}: {}", g.id.into_string(), trait_name)).collect::<Vec<_>>();
+            let where_str = if bounds.is_empty() { String::new() } else { format!("where {} ", bounds.join(", ")) };
+            let src = format!("impl{} {} for {} {}{{ {} }}", impl_generics, trait_name, self_ty, where_str, method);
+
+            let before = set_current_source(None);
+            let result = parse_impl_definition(&src);
+            set_current_source(before);
+            let mut impl_def = match result {
+                Ok(("", impl_def)) => impl_def,
+                Ok((rest, _)) => return Err(trait_id.id.get_span().error_message(&format!("failed to derive trait {} for {}, unexpected {:?}\n{}", trait_name, struct_name, rest, src))),
+                Err(err) => return Err(trait_id.id.get_span().error_message(&format!("failed to derive trait {} for {}, {}\n{}", trait_name, struct_name, err, src))),
+            };
+            impl_def.where_sec.append(self.member_def.where_sec.clone());
+            impls.push(impl_def);
+        }
+        Ok(impls)
+    }
+
+    pub fn check_derives(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> Result<(), String> {
+        if self.derives.is_empty() {
+            return Ok(())
+        }
+        let fields = self.derive_fields()?;
+        for (trait_id, impl_def) in self.derives.iter().zip(self.derive_impls()?) {
+            if trait_id.id.name == "Hash" {
+                self.check_derive_hash_ops(trait_id, trs)?;
+            }
+            let mut gen_trs = trs.into_scope();
+            for id in impl_def.generics.iter() {
+                gen_trs.regist_generics_type(id)?;
+            }
+            equs.into_scope();
+            let trait_gen = TraitGenerics { trait_id: trait_id.clone(), generics: Vec::new() };
+            let result = impl_def.generics.iter().try_for_each(|id| id.regist_const_variable(equs, &gen_trs))
+                .and_then(|_| impl_def.where_sec.regist_candidate(equs, &mut gen_trs))
+                .and_then(|_| fields.iter().try_for_each(|(name, spec)| {
+                    let ty = spec.generics_to_type(&GenericsTypeMap::empty(), equs, &gen_trs)?;
+                    match gen_trs.match_to_impls_for_type(&trait_gen, &ty) {
+                        Err(0) => Err(trait_id.id.get_span().error_message(&format!("cant derive trait {} for {}, field {} of type {} does not implement {}",
+                                    trait_id.id.name, self.member_def.struct_id.id.name, name, spec, trait_id.id.name))),
+                        _ => Ok(()),
+                    }
+                }));
+            equs.out_scope();
+            result?;
+        }
+        Ok(())
+    }
+
+    // the derived hash combines the fields with `*` and `^` on u64, which come from std/u64.niu
+    fn check_derive_hash_ops(&self, trait_id: &TraitId, trs: &TraitsInfo) -> Result<(), String> {
+        let u64_ty = Type::from_str("u64");
+        for ope in ["Mul", "BitXor"].iter() {
+            let ope_gen = TraitGenerics { trait_id: TraitId::from_str(ope), generics: vec![u64_ty.clone()] };
+            let found = trs.get_traitinfo(&ope_gen.trait_id).is_some() && !matches!(trs.match_to_impls_for_type(&ope_gen, &u64_ty), Err(0));
+            if !found {
+                return Err(trait_id.id.get_span().error_message(&format!("cant derive trait Hash for {}, it needs impl {} for u64, import \"std/u64.niu\"",
+                            self.member_def.struct_id.id.name, ope_gen)))
+            }
+        }
+        Ok(())
+    }
+
+    pub fn transpile_derive_hash(&self, ta: &TypeAnnotation, mode: &OutputMode) -> String {
+        if !self.derives.iter().any(|tr| tr.id.into_string() == "Hash") {
+            return String::new()
+        }
+        let template = self.member_def.generics.iter().map(|g| g.transpile_template_param(ta)).collect::<Vec<_>>().join(", ");
+        let self_ty = self.transpile_self_type();
+        ta.references.use_trait("Hash");
+        match *mode {
+            OutputMode::Program => {
+                format!("namespace std {{ template<{}> struct hash<{1}> {{ std::size_t operator()(const {1}& x) const {{ return Hash<{1}>::hash(&x); }} }}; }}\n", template, self_ty)
+            }
+            OutputMode::Library(ref namespace) => {
+                format!("}} // namespace {0}\ntemplate<{1}> struct std::hash<{0}::{2}> {{ std::size_t operator()(const {0}::{2}& x) const {{ return {0}::Hash<{0}::{2}>::hash(&x); }} }};\nnamespace {0} {{\n", namespace, template, self_ty)
+            }
+        }
+    }
+}
+
+pub fn parse_derive_attributes(s: &str) -> IResult<&str, Vec<TraitId>> {
+    let (s, attrs) = many0(tuple((
+                tag("#["), multispace0, tag("derive"), multispace0, char('('), multispace0,
+                separated_list0(tuple((multispace0, char(','), multispace0)), parse_trait_id),
+                multispace0, opt(char(',')), multispace0, char(')'), multispace0, char(']'), multispace0)))(s)?;
+    Ok((s, attrs.into_iter().flat_map(|(_, _, _, _, _, _, ids, _, _, _, _, _, _, _)| ids).collect()))
+}
+
+#[test]
+fn parse_derive_attributes_test() {
+    log::debug!("{:?}", parse_derive_attributes("#[derive(Clone, Eq, Ord)] #[derive(Hash,)] struct"));
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use nom::IResult;
+use nom::bytes::complete::*;
+use nom::character::complete::*;
+use nom::multi::*;
+use nom::sequence::*;
+use nom::combinator::*;
+
+use crate::comment::{ multispace0, multispace1, parse_doc_comments };
+use crate::identifier::{ Identifier, parse_identifier, Tag };
+use crate::type_id::*;
+use crate::type_spec::*;
+use crate::structs::*;
+use crate::func_definition::*;
+use crate::unify::*;
+use crate::trans::*;
+use crate::module::parse_visibility;
+
+#[derive(Debug, Clone)]
+pub struct EnumInfo {
+    pub variants_order: Vec<Identifier>,
+    pub variants: HashMap<Identifier, Vec<TypeSpec>>,
+}
+
+impl EnumInfo {
+    pub fn get_variant_index(&self, id: &Identifier) -> Option<usize> {
+        self.variants_order.iter().position(|v| v == id)
+    }
+    pub fn transpile_variant_type(&self, ta: &TypeAnnotation) -> String {
+        let alts = self.variants_order.iter().map(|v| {
+            let tys = self.variants[v].iter().map(|ty| ty.transpile(ta)).collect::<Vec<_>>().join(", ");
+            format!("std::tuple<{}>", tys)
+        }).collect::<Vec<_>>().join(", ");
+        format!("std::variant<{}>", alts)
+    }
+    pub fn transpile_constructors(&self, ta: &TypeAnnotation) -> String {
+        self.variants_order.iter().enumerate().map(|(i, v)| {
+            let tys = &self.variants[v];
+            let params = tys.iter().enumerate().map(|(j, ty)| format!("{} v{}", ty.transpile(ta), j)).collect::<Vec<_>>().join(", ");
+            let args = std::iter::once(format!("std::in_place_index<{}>", i)).chain((0..tys.len()).map(|j| format!("v{}", j))).collect::<Vec<_>>().join(", ");
+            format!("static Self {}({}) {{ return Self(Variant({})); }}\n", v.into_string(), params, args)
+        }).collect::<Vec<_>>().join("")
+    }
+}
+
+impl StructMemberDefinition {
+    pub fn get_variant_types(&self, equs: &mut TypeEquations, trs: &TraitsInfo, gens: &[Type], id: &Identifier) -> Result<Vec<Type>, String> {
+        match self.member {
+            StructMember::Enum(EnumInfo { ref variants, .. }) => {
+                match variants.get(id) {
+                    Some(specs) => {
+                        let mp = self.generics.iter().cloned().zip(gens.iter().cloned()).collect();
+                        let gen_mp = GenericsTypeMap::empty();
+                        let gen_mp = gen_mp.next(mp);
+                        specs.iter().map(|spec| spec.generics_to_type(&gen_mp, equs, trs)).collect()
+                    }
+                    None => Err(id.get_span().error_message(&format!("enum {} doesnt have variant {}", self.struct_id.id.name, id.name))),
+                }
+            }
+            _ => Err(id.get_span().error_message(&format!("type {} is not enum", self.struct_id.id.name))),
+        }
+    }
+    pub fn get_enum_info(&self) -> Option<&EnumInfo> {
+        match self.member {
+            StructMember::Enum(ref info) => Some(info),
+            _ => None,
+        }
+    }
+}
+
+pub fn search_enum_type(sign: &TypeSign, equs: &mut TypeEquations, trs: &TraitsInfo) -> Result<Option<(Type, StructMemberDefinition)>, String> {
+    let ty = if sign.id == TypeId::from_str("Self") {
+        match equs.get_self_type() {
+            Ok(ty) => ty,
+            Err(_) => return Ok(None),
+        }
+    }
+    else {
+        match trs.search_typeid(&sign.id) {
+            Ok(StructDefinitionInfo::Def(def)) if def.get_enum_info().is_some() => {}
+            _ => return Ok(None),
+        }
+        sign.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?
+    };
+    match ty {
+        Type::Generics(ref id, _) => {
+            match trs.search_typeid(id) {
+                Ok(StructDefinitionInfo::Def(def)) if def.get_enum_info().is_some() => Ok(Some((ty.clone(), def.clone()))),
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+pub fn gen_type_for_variant(spec: &TypeSpec, variant: &Identifier, args: Vec<Type>, equs: &mut TypeEquations, trs: &TraitsInfo) -> Result<Option<Type>, String> {
+    let sign = match spec {
+        TypeSpec::TypeSign(sign) => sign,
+        _ => return Ok(None),
+    };
+    let (enum_ty, def) = match search_enum_type(sign, equs, trs)? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+    if def.get_enum_info().unwrap().get_variant_index(variant).is_none() {
+        return Ok(None)
+    }
+    let gens = match enum_ty {
+        Type::Generics(_, ref gens) => gens.clone(),
+        _ => unreachable!(),
+    };
+    let payload = def.get_variant_types(equs, trs, &gens, variant)?;
+    if payload.len() != args.len() {
+        return Err(variant.get_span().error_message(&format!("variant {}::{} takes {} arguments, but {} given", def.struct_id.id.name, variant.name, payload.len(), args.len())))
+    }
+    for (p, a) in payload.into_iter().zip(args) {
+        equs.add_equation(p, a);
+    }
+    let alpha = variant.generate_type_variable("EnumType", 0, equs);
+    equs.add_equation(alpha.clone(), enum_ty);
+    Ok(Some(alpha))
+}
+
+fn parse_variant(s: &str) -> IResult<&str, (Identifier, Vec<TypeSpec>)> {
+    let (s, (id, _, op)) = tuple((parse_identifier, multispace0,
+            opt(tuple((char('('), multispace0, separated_list0(tuple((multispace0, char(','), multispace0)), parse_type_spec), multispace0, opt(tuple((char(','), multispace0))), char(')'))))))(s)?;
+    let tys = op.map(|(_, _, tys, _, _, _)| tys).unwrap_or(Vec::new());
+    Ok((s, (id, tys)))
+}
+
+fn parse_enum_variants(s: &str) -> IResult<&str, StructMember> {
+    let (s, (_, _, vs, _, _, _)) = tuple((char('{'), multispace0,
+                separated_list1(tuple((multispace0, char(','), multispace0)), parse_variant),
+                multispace0, opt(tuple((char(','), multispace0))), char('}')))(s)?;
+    let variants_order = vs.iter().map(|(id, _)| id.clone()).collect();
+    let variants = vs.into_iter().collect();
+    Ok((s, StructMember::Enum(EnumInfo { variants_order, variants })))
+}
+
+pub fn parse_enum_member_definition(s: &str) -> IResult<&str, StructMemberDefinition> {
+    let (s, (_, _, _, struct_id, _, generics, _, where_sec, _, member)) =
+        tuple((parse_visibility, tag("enum"), multispace1, parse_type_id, multispace0, parse_generics_annotation, multispace0, parse_where_section, multispace0, parse_enum_variants))(s)?;
+    Ok((s, StructMemberDefinition { struct_id, generics, member, where_sec }))
+}
+
+pub fn parse_enum_definition(s: &str) -> IResult<&str, StructDefinition> {
+    let (s, (doc, member_def, funcs)) = tuple((parse_doc_comments, parse_enum_member_definition,
+            opt(tuple((multispace0, char('{'), multispace0, many0(tuple((parse_func_definition, multispace0))), char('}'))))))(s)?;
+    let require_methods = funcs.map(|(_, _, _, funcs, _)| funcs.into_iter().map(|(func, _)| (func.func_id.clone(), func)).collect()).unwrap_or(HashMap::new());
+    let impl_self = ImplSelfDefinition {
+        generics: member_def.generics.clone(),
+        impl_ty: TypeSpec::TypeSign(TypeSign {
+            id: member_def.struct_id.clone(),
+            gens: member_def.generics.iter().map(TypeSpec::from_id).collect(),
+        }),
+        where_sec: member_def.where_sec.clone(),
+        require_methods,
+        tag: Tag::new(),
+    };
+    Ok((s, StructDefinition {
+        member_def,
+        impl_self,
+        derives: Vec::new(),
+        doc,
+    }))
+}
+
+#[test]
+fn parse_enum_definition_test() {
+    log::debug!("{:?}", parse_enum_definition("enum Option<T> { Some(T), None, }"));
+    log::debug!("{:?}", parse_enum_definition("enum Edge { To(u64, i64), Nothing } { fn nothing() -> Self { Edge::Nothing } }"));
+}
@@ -7,6 +7,7 @@ use nom::multi::*;
 use nom::sequence::*;
 use nom::IResult;
 
+use crate::comment::{ multispace0, multispace1 };
 use crate::identifier::*;
 use crate::type_id::*;
 use crate::type_spec::*;
@@ -16,6 +17,7 @@ use crate::trans::*;
 //use crate::unary_expr::Variable;
 use crate::traits::*;
 use crate::func_definition::*;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 pub struct ImplSelfDefinition {
@@ -50,15 +52,18 @@ impl ImplSelfDefinition {
         for ty_id in self.generics.iter() {
             trs.regist_generics_type(ty_id)?;
         }
+        equs.into_scope();
+        for ty_id in self.generics.iter() {
+            ty_id.regist_const_variable(equs, &trs)?;
+        }
         self.where_sec.regist_candidate(equs, &mut trs)?;
         let next_self_type = self.impl_ty.generate_type_no_auto_generics(equs, &trs)?;
         let next_self_type = Some(next_self_type);
         let before_self_type = equs.set_self_type(next_self_type);
-        for def in self.require_methods.values() {
-            def.unify_definition(equs, &trs)?;
-        }
+        let result = self.require_methods.values().try_for_each(|def| def.unify_definition(equs, &trs));
         equs.set_self_type(before_self_type);
-        Ok(())
+        equs.out_scope();
+        result
     }
     pub fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<(), String> {
         for def in self.require_methods.values() {
@@ -68,6 +73,15 @@ impl ImplSelfDefinition {
     }
 }
 
+impl Resolve for ImplSelfDefinition {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.generics.resolve(scope)?;
+        self.impl_ty.resolve(scope)?;
+        self.where_sec.resolve(scope)?;
+        self.require_methods.values_mut().try_for_each(|func| func.resolve(scope))
+    }
+}
+
 impl ImplSelfCandidate {
     pub fn generate_equations_for_call_equation(&self, call_eq: &CallEquation, trs: &TraitsInfo) -> Result<TypeEquations, String> {
         if call_eq.trait_gen != None {
@@ -160,14 +174,14 @@ impl ImplSelfCandidate {
 }
 
 fn parse_generics_args(s: &str) -> IResult<&str, Vec<TypeId>> {
-    let (s, op) = opt(tuple((multispace0, char('<'), multispace0, separated_list0(tuple((multispace0, char(','), multispace0)), parse_type_id), multispace0, char('>'))))(s)?;
+    let (s, op) = opt(tuple((multispace0, char('<'), multispace0, separated_list0(tuple((multispace0, char(','), multispace0)), parse_generics_param), multispace0, char('>'))))(s)?;
     Ok((s, op.map(|(_, _, _, res, _, _)| res).unwrap_or(Vec::new())))
 }
 
 pub fn parse_impl_self_definition(s: &str) -> IResult<&str, ImplSelfDefinition> {
     let (s, (_, generics, _, impl_ty, _, where_sec, _, _, _, many_methods, _, _)) = 
         tuple((tag("impl"), parse_generics_args,
-            space1, parse_type_spec,
+            multispace1, parse_type_spec,
             multispace0, parse_where_section,
             multispace0, char('{'), multispace0,
             many0(tuple((parse_func_definition, multispace0))),
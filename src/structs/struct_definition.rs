@@ -8,6 +8,7 @@ use nom::sequence::*;
 use nom::combinator::*;
 use nom::branch::*;
 
+use crate::comment::{ multispace0, multispace1, parse_doc_comments, transpile_doc_comments };
 use crate::identifier::{ Identifier, parse_identifier, Tag };
 use crate::type_id::*;
 use crate::type_spec::*;
@@ -20,6 +21,7 @@ use crate::unify::*;
 
 use crate::trans::*;
 use crate::mut_checker::*;
+use crate::module::{ ModuleScope, Resolve, parse_visibility, split_module_path, transpile_in_namespace };
 
 #[derive(Debug, Clone)]
 pub struct MemberInfo {
@@ -31,6 +33,7 @@ pub struct MemberInfo {
 pub enum StructMember {
     MemberInfo(MemberInfo),
     CppInline(CppInline),
+    Enum(EnumInfo),
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +48,8 @@ pub struct StructMemberDefinition {
 pub struct StructDefinition {
     pub member_def: StructMemberDefinition,
     pub impl_self: ImplSelfDefinition,
+    pub derives: Vec<TraitId>,
+    pub doc: Vec<String>,
 }
 
 
@@ -76,34 +81,46 @@ impl StructDefinition {
     }
     pub fn transpile_definition(&self, ta: &TypeAnnotation) -> String {
         match self.member_def.member {
-            StructMember::MemberInfo(MemberInfo { .. }) => {
+            StructMember::MemberInfo(MemberInfo { .. }) | StructMember::Enum(_) => {
                 let template = if self.member_def.generics.len() > 0 {
                     format!("template <{}> ",
-                            self.member_def.generics.iter().map(|gen| format!("class {}", gen.transpile(ta)))
+                            self.member_def.generics.iter().map(|gen| gen.transpile_template_param(ta))
                             .chain(std::iter::once(format!("class = void"))).collect::<Vec<_>>().join(", ")
                             )
                 }
                 else {
                     format!("")
                 };
-                format!("{}struct {};\n", template, self.member_def.struct_id.transpile(ta))
+                transpile_in_namespace(&self.member_def.struct_id.transpile(ta), |name| format!("{}struct {};\n", template, name))
             }
             _ => format!(""),
         }
     }
+    pub fn transpile_member_types(&self, ta: &TypeAnnotation) -> Vec<String> {
+        match self.member_def.member {
+            StructMember::MemberInfo(MemberInfo { ref members_order, ref members }) => members_order.iter().map(|mem| members[mem].transpile(ta)).collect(),
+            StructMember::Enum(ref info) => vec![info.transpile_variant_type(ta)],
+            _ => Vec::new(),
+        }
+    }
     pub fn transpile(&self, ta: &TypeAnnotation, opes: Vec<String>) -> String {
         let binary_operators = BINARY_OPERATOR_TRAITS.iter().cloned().collect::<HashMap<_, _>>();
         match self.member_def.member {
-            StructMember::MemberInfo(MemberInfo { ref members_order, ref members }) => {
+            StructMember::MemberInfo(_) | StructMember::Enum(_) => {
                 let template = if self.member_def.generics.len() > 0 {
-                    format!("template <{}> ", self.member_def.generics.iter().map(|gen| format!("class {}", gen.transpile(ta))).collect::<Vec<_>>().join(", "))
+                    format!("template <{}> ", self.member_def.generics.iter().map(|gen| gen.transpile_template_param(ta)).collect::<Vec<_>>().join(", "))
                 }
                 else {
                     format!("")
                 };
-                let impl_type = format!("{}<{}>", self.member_def.struct_id.transpile(ta),
-                    self.member_def.generics.iter().map(|gen| format!("{}", gen.transpile(ta)))
-                        .chain(std::iter::once(self.member_def.where_sec.transpile(ta))).collect::<Vec<_>>().join(", "));
+                let impl_type = if !self.member_def.generics.is_empty() {
+                    format!("{}<{}>", self.member_def.struct_id.transpile(ta),
+                        self.member_def.generics.iter().map(|gen| format!("{}", gen.transpile(ta)))
+                            .chain(std::iter::once(self.member_def.where_sec.transpile(ta))).collect::<Vec<_>>().join(", "))
+                }
+                else {
+                    self.member_def.struct_id.transpile(ta)
+                };
                 let self_type_generics = if self.member_def.generics.len() > 0 {
                     format!("<{}>", self.member_def.generics.iter().map(|gen| format!("{}", gen.transpile(ta))).collect::<Vec<_>>().join(", "))
                 }
@@ -111,20 +128,42 @@ impl StructDefinition {
                     format!("")
                 };
                 let self_type = format!("using Self = {}{};", self.member_def.struct_id.transpile(ta), self_type_generics);
-                let members_str = members_order.iter().map(|mem| members.get_key_value(mem).unwrap()).map(|(mem, ty)| format!("{} {};", ty.transpile(ta), mem.into_string())).collect::<Vec<_>>().join("\n");
-                let constructor = format!("{}({}):{} {{ }}",
-                    self.member_def.struct_id.transpile(ta),
-                    members_order.iter().map(|mem| members.get_key_value(mem).unwrap())
-                        .map(|(mem, ty)| format!("{} {}", ty.transpile(ta), mem.into_string())).collect::<Vec<_>>().join(", "),
-                    members_order.iter().map(|mem| members.get_key_value(mem).unwrap())
-                        .map(|(mem, _)| format!("{}({})", mem.into_string(), mem.into_string())).collect::<Vec<_>>().join(", ")
-                );
-                let methods = self.impl_self.require_methods.iter().map(|(_, func)| format!("{}", func.transpile(ta, true))).collect::<Vec<_>>().join("\n");
+                let (members_str, constructor) = match self.member_def.member {
+                    StructMember::MemberInfo(MemberInfo { ref members_order, ref members }) => {
+                        let members_str = members_order.iter().map(|mem| members.get_key_value(mem).unwrap()).map(|(mem, ty)| format!("{} {};", ty.transpile(ta), mem.into_string())).collect::<Vec<_>>().join("\n");
+                        let initializers = members_order.iter().map(|mem| members.get_key_value(mem).unwrap())
+                            .map(|(mem, _)| format!("{}({})", mem.into_string(), mem.into_string())).collect::<Vec<_>>().join(", ");
+                        let name = split_module_path(&self.member_def.struct_id.transpile(ta)).1.to_string();
+                        let default_constructor = if members_order.is_empty() { String::new() } else { format!("{}() = default;\n", name) };
+                        // a one-member constructor must not act as a conversion, otherwise
+                        // `a.q == b.q` could silently pick the operators of the outer struct
+                        let explicit = if members_order.len() == 1 { "explicit " } else { "" };
+                        let constructor = format!("{}{}{}({}){} {{ }}",
+                            default_constructor,
+                            explicit,
+                            name,
+                            members_order.iter().map(|mem| members.get_key_value(mem).unwrap())
+                                .map(|(mem, ty)| format!("{} {}", ty.transpile(ta), mem.into_string())).collect::<Vec<_>>().join(", "),
+                            if initializers.is_empty() { initializers } else { format!(":{}", initializers) }
+                        );
+                        (members_str, constructor)
+                    }
+                    StructMember::Enum(ref info) => {
+                        let members_str = format!("using Variant = {};\nVariant data;", info.transpile_variant_type(ta));
+                        let constructor = format!("{0}() = default;\n{0}(Variant data): data(data) {{ }}\n{1}", split_module_path(&self.member_def.struct_id.transpile(ta)).1, info.transpile_constructors(ta));
+                        (members_str, constructor)
+                    }
+                    _ => unreachable!(),
+                };
+                let methods = self.impl_self.require_methods.iter().map(|(_, func)| format!("{}{}", func.transpile_doc(), func.transpile(ta, true))).collect::<Vec<_>>().join("\n");
                 let operators = opes.into_iter().map(|ope| match ope.as_str() {
                     "Index" => {
+                        ta.references.use_trait("Index");
                         format!("typename std::enable_if<Index<Self>::value, const typename Index<Self>::Output&>::type operator[](typename Index<Self>::Arg k) const {{ return *Index<Self>::index(this, k); }}\n")
                     }
                     "IndexMut" => {
+                        ta.references.use_trait("Index");
+                        ta.references.use_trait("IndexMut");
                         format!("typename std::enable_if<IndexMut<Self>::value, typename Index<Self>::Output&>::type operator[](typename Index<Self>::Arg k) {{ return *IndexMut<Self>::index_mut(this, k); }}\n")
                     }
                     /* bin_ope if binary_operators.contains_key(bin_ope) => {
@@ -134,7 +173,7 @@ impl StructDefinition {
                     _ => "".to_string(),
                 }).collect::<Vec<_>>().join("");
 
-                format!("{}struct {} {{\n{}\n{}\n{}\n{}{}}} ;\n", template, impl_type, self_type, members_str, constructor, methods, operators)
+                format!("{}{}struct {} {{\n{}\n{}\n{}\n{}{}}} ;\n", transpile_doc_comments(&self.doc), template, impl_type, self_type, members_str, constructor, methods, operators)
             }
             _ => format!(""),
         }
@@ -156,23 +195,47 @@ impl StructMemberDefinition {
                         let mp = self.generics.iter().cloned().zip(gens.iter().cloned()).collect();
                         spec.generics_to_type(&GenericsTypeMap::empty().next(mp), equs, trs)
                     }
-                    None => Err(format!("{:?} < {:?} >doesnt have member {:?}", self.struct_id, gens, id)),
+                    None => Err(format!("{} doesnt have member {}", Type::Generics(self.struct_id.clone(), gens.clone()), id.name)),
                 }
             }
             StructMember::CppInline(_) => {
-                Err(format!("{:?} is inline struct", self.struct_id))
+                Err(format!("{} is inline struct", self.struct_id))
+            }
+            StructMember::Enum(_) => {
+                Err(format!("{} is enum", self.struct_id))
             }
         }
     }
 }
 
+impl Resolve for StructMemberDefinition {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.struct_id.resolve(scope)?;
+        self.generics.resolve(scope)?;
+        self.where_sec.resolve(scope)?;
+        match self.member {
+            StructMember::MemberInfo(ref mut info) => info.members.values_mut().try_for_each(|spec| spec.resolve(scope)),
+            StructMember::CppInline(ref mut inline) => inline.resolve(scope),
+            StructMember::Enum(ref mut info) => info.variants.values_mut().try_for_each(|specs| specs.resolve(scope)),
+        }
+    }
+}
+
+impl Resolve for StructDefinition {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.member_def.resolve(scope)?;
+        self.impl_self.resolve(scope)?;
+        self.derives.resolve(scope)
+    }
+}
+
 fn parse_member(s: &str) -> IResult<&str, (Identifier, TypeSpec)> {
-    let (s, (id, _, _, _, ty)) = tuple((parse_identifier, multispace0, char(':'), multispace0, parse_type_spec))(s)?;
+    let (s, (_, id, _, _, _, ty)) = tuple((parse_doc_comments, parse_identifier, multispace0, char(':'), multispace0, parse_type_spec))(s)?;
     Ok((s, (id, ty)))
 }
 
-fn parse_generics_annotation(s: &str) -> IResult<&str, Vec<TypeId>> {
-    let (s, op) = opt(tuple((char('<'), multispace0, parse_type_id, multispace0, many0(tuple((char(','), multispace0, parse_type_id, multispace0))), opt(tuple((multispace0, char(',')))), multispace0, char('>'))))(s)?;
+pub fn parse_generics_annotation(s: &str) -> IResult<&str, Vec<TypeId>> {
+    let (s, op) = opt(tuple((char('<'), multispace0, parse_generics_param, multispace0, many0(tuple((char(','), multispace0, parse_generics_param, multispace0))), opt(tuple((multispace0, char(',')))), multispace0, char('>'))))(s)?;
     let v = match op {
         None => Vec::new(),
         Some((_, _, ty, _, m0, _, _, _)) => {
@@ -210,13 +273,13 @@ fn parse_struct_cpp_inline(s: &str) -> IResult<&str, StructMember> {
 }
 
 pub fn parse_struct_member_definition(s: &str) -> IResult<&str, StructMemberDefinition> {
-    let (s, (_, _, struct_id, _, generics, _, where_sec, _, member)) =
-        tuple((tag("struct"), space1, parse_type_id, multispace0, parse_generics_annotation, multispace0, parse_where_section, multispace0, alt((parse_struct_members, parse_struct_cpp_inline))))(s)?;
+    let (s, (_, _, _, struct_id, _, generics, _, where_sec, _, member)) =
+        tuple((parse_visibility, tag("struct"), multispace1, parse_type_id, multispace0, parse_generics_annotation, multispace0, parse_where_section, multispace0, alt((parse_struct_members, parse_struct_cpp_inline))))(s)?;
     Ok((s, StructMemberDefinition { struct_id, generics, member, where_sec }))
 }
 
 pub fn parse_struct_definition(s: &str) -> IResult<&str, StructDefinition> {
-    let (s, (member_def, _, _, _, funcs, _)) = tuple((parse_struct_member_definition, multispace0, char('{'), multispace0,
+    let (s, (doc, derives, member_def, _, _, _, funcs, _)) = tuple((parse_doc_comments, parse_derive_attributes, parse_struct_member_definition, multispace0, char('{'), multispace0,
             many0(tuple((parse_func_definition, multispace0))), char('}')))(s)?;
     let require_methods = funcs.into_iter().map(|(func, _)| (func.func_id.clone(), func)).collect();
     let impl_self = ImplSelfDefinition {
@@ -232,6 +295,8 @@ pub fn parse_struct_definition(s: &str) -> IResult<&str, StructDefinition> {
     Ok((s, StructDefinition {
         member_def,
         impl_self,
+        derives,
+        doc,
     }))
 }
 
@@ -243,6 +308,7 @@ fn parse_struct_definition_test() {
 #[test]
 fn parse_struct_definition2_test() {
     log::debug!("{:?}", parse_struct_definition("struct MyStruct<S, T> { a: S, b: T }"));
+    log::debug!("{:?}", parse_struct_definition("#[derive(Clone, Eq)] struct MyStruct<T> { a: T } {}"));
 }
 
 /*#[test]
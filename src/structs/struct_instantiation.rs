@@ -6,6 +6,7 @@ use nom::multi::*;
 use nom::sequence::*; 
 use nom::combinator::*;
 
+use crate::comment::multispace0;
 use crate::identifier::{ Identifier, parse_identifier, Tag };
 use crate::type_id::*;
 use crate::type_spec::*;
@@ -15,6 +16,7 @@ use crate::unary_expr::*;
 use crate::trans::*;
 use crate::unify::*;
 use crate::mut_checker::*;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 pub struct StructInstantiation {
@@ -61,10 +63,10 @@ pub fn parse_struct_instantiation(s: &str) -> IResult<&str, UnaryExpr> {
 
 impl Transpile for StructInstantiation {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        let args = ta.get_struct_members_order(&self.struct_id).iter()
+        let members = ta.get_struct_members_order(&self.struct_id).iter()
             .map(|mem| self.members.get(mem).unwrap())
-            .map(|exp| exp.transpile(ta))
-            .collect::<Vec<_>>().join(", ");
+            .collect::<Vec<_>>();
+        let args = ta.lowering.operands(&members, |exp| exp.transpile(ta)).join(", ");
         format!("{}({})", ta.annotation(self.tag.get_num(), "InstantiationType", 0).transpile(ta), args)
     }
 }
@@ -78,6 +80,13 @@ impl MutCheck for StructInstantiation {
     }
 }
 
+impl Resolve for StructInstantiation {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.struct_id.resolve(scope)?;
+        self.members.values_mut().try_for_each(|expr| expr.resolve(scope))
+    }
+}
+
 #[test]
 fn parse_struct_instantiation_test() {
     log::debug!("{:?}", parse_struct_instantiation("MyStruct { a: 1i64 + 2i64, b: val, }"));
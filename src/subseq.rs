@@ -7,6 +7,7 @@ use nom::sequence::*;
 use nom::branch::*;
 use nom::IResult;
 
+use crate::comment::multispace0;
 use crate::expression::{ Expression, parse_expression };
 use crate::unary_expr::UnaryExpr;
 use crate::traits::*;
@@ -15,6 +16,9 @@ use crate::type_spec::*;
 use crate::trans::*;
 use crate::mut_checker::*;
 use crate::identifier::*;
+use crate::structs::gen_type_for_variant;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 pub enum Subseq {
@@ -23,6 +27,16 @@ pub enum Subseq {
     Index(IndexCall),
 }
 
+impl Resolve for Subseq {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        match *self {
+            Subseq::Call(ref mut call) => call.args.resolve(scope),
+            Subseq::Index(ref mut index) => index.arg.resolve(scope),
+            Subseq::Member(_) => Ok(()),
+        }
+    }
+}
+
 pub fn subseq_gen_type(uexpr: &UnaryExpr, subseq: &Subseq, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
     match *subseq {
         Subseq::Call(ref call) => {
@@ -42,14 +56,24 @@ pub fn subseq_gen_type(uexpr: &UnaryExpr, subseq: &Subseq, equs: &mut TypeEquati
                     }))
                 }
                 UnaryExpr::TraitMethod(spec, trait_op, func_id) => {
-                    let caller = spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
                     let args = call.args.iter().map(|arg| arg.gen_type(equs, trs)).collect::<Result<Vec<_>, String>>()?;
+                    if trait_op.is_none() {
+                        if let Some(enum_ty) = gen_type_for_variant(spec, func_id, args.clone(), equs, trs)? {
+                            return Ok(enum_ty)
+                        }
+                    }
+                    let caller = spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+                    let method = TraitMethodIdentifier { id: func_id.clone() };
+                    let trait_gen = match trait_op {
+                        Some(trait_spec) => {
+                            let trait_gen = trait_spec.generate_trait_generics_with_no_map(equs, trs)?;
+                            Some(trs.find_member_trait(&trait_gen, &caller, &|tr| tr.required_methods.contains_key(&method))?)
+                        }
+                        None => None,
+                    };
                     Ok(Type::CallEquation(CallEquation {
                         caller_type: Some(Box::new(caller)),
-                        trait_gen: match trait_op {
-                            Some(trait_spec) => Some(trait_spec.generate_trait_generics_with_no_map(equs, trs)?),
-                            None => None,
-                        },
+                        trait_gen,
                         func_id: func_id.clone(),
                         args,
                         tag: call.tag.clone(),
@@ -109,7 +133,7 @@ pub fn subseq_transpile(uexpr: &UnaryExpr, subseq: &Subseq, ta: &TypeAnnotation)
                 if let Type::Func(_, _, info) = ty {
                     match info {
                         FuncTypeInfo::TraitFunc(trait_id, generics_cnt, tag) => {
-                            let args = call.args.iter().map(|arg| arg.transpile(ta));
+                            let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter();
                             let args = std::iter::once(caller_trans).chain(args).collect::<Vec<_>>().join(", ");
                             let ty = std::iter::once(ta.annotation(tag.get_num(), "SelfType", 0)).chain(
                                 (0..generics_cnt).map(|i| ta.annotation(tag.get_num(), "TraitGenerics", i)))
@@ -118,12 +142,12 @@ pub fn subseq_transpile(uexpr: &UnaryExpr, subseq: &Subseq, ta: &TypeAnnotation)
                         }
                         FuncTypeInfo::SelfFunc(tag) => {
                             let ty = ta.annotation(tag.get_num(), "SelfType", 0).transpile(ta);
-                            let args = call.args.iter().map(|arg| arg.transpile(ta));
+                            let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter();
                             let args = std::iter::once(caller_trans).chain(args).collect::<Vec<_>>().join(", ");
                             format!("{}::{}({})", ty, mem.mem_id.into_string(), args)
                         }
                         FuncTypeInfo::CppInline(cppinline, ids) => {
-                            let args = call.args.iter().map(|arg| arg.transpile(ta));
+                            let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter();
                             let args = std::iter::once(caller_trans).chain(args);
                             let mp = ids.into_iter().zip(args.into_iter()).collect::<HashMap<_, _>>();
                             cppinline.transpile(ta, &mp)
@@ -138,12 +162,16 @@ pub fn subseq_transpile(uexpr: &UnaryExpr, subseq: &Subseq, ta: &TypeAnnotation)
                 }
             }
             else if let UnaryExpr::TraitMethod(_, _, method_id) = uexpr {
+                if let Some(enum_ty) = ta.try_annotation(method_id.get_tag_number(), "EnumType", 0) {
+                    let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter().collect::<Vec<_>>().join(", ");
+                    return format!("{}::{}({})", enum_ty.transpile(ta), method_id.into_string(), args)
+                }
                 let ty = ta.annotation(call.tag.get_num(), "FuncTypeInfo", 0);
                 //if let Type::Func(_, _, Some((trait_id, ty))) = ty {
                 if let Type::Func(_, _, info) = ty {
                     match info {
                         FuncTypeInfo::TraitFunc(trait_id, generics_cnt, tag) => {
-                            let args = call.args.iter().map(|arg| arg.transpile(ta));
+                            let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter();
                             let args = args.collect::<Vec<_>>().join(", ");
                             let ty = std::iter::once(ta.annotation(tag.get_num(), "SelfType", 0)).chain(
                                 (0..generics_cnt).map(|i| ta.annotation(tag.get_num(), "TraitGenerics", i)))
@@ -152,12 +180,12 @@ pub fn subseq_transpile(uexpr: &UnaryExpr, subseq: &Subseq, ta: &TypeAnnotation)
                         }
                         FuncTypeInfo::SelfFunc(tag) => {
                             let ty = ta.annotation(tag.get_num(), "SelfType", 0).transpile(ta);
-                            let args = call.args.iter().map(|arg| arg.transpile(ta));
+                            let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter();
                             let args = args.collect::<Vec<_>>().join(", ");
                             format!("{}::{}({})", ty, method_id.into_string(), args)
                         }
                         FuncTypeInfo::CppInline(cppinline, ids) => {
-                            let args = call.args.iter().map(|arg| arg.transpile(ta));
+                            let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter();
                             let mp = ids.into_iter().zip(args.into_iter()).collect::<HashMap<_, _>>();
                             cppinline.transpile(ta, &mp)
                         }
@@ -173,13 +201,13 @@ pub fn subseq_transpile(uexpr: &UnaryExpr, subseq: &Subseq, ta: &TypeAnnotation)
             else {
                 match ta.annotation(call.tag.get_num(), "FuncTypeInfo", 0) {
                     Type::Func(_, _, FuncTypeInfo::CppInline(cppinline, ids)) => {
-                        let args = call.args.iter().map(|arg| arg.transpile(ta));
+                        let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter();
                         let mp = ids.into_iter().zip(args.into_iter()).collect::<HashMap<_, _>>();
                         cppinline.transpile(ta, &mp)
                     }
                     _ => {
                         let caller = uexpr.transpile(ta);
-                        let args = call.args.iter().map(|arg| arg.transpile(ta)).collect::<Vec<_>>().join(", ");
+                        let args = ta.lowering.operands(&call.args, |arg| arg.transpile(ta)).into_iter().collect::<Vec<_>>().join(", ");
                         format!("{}({})", caller, args)
                     }
                 }
@@ -188,6 +216,8 @@ pub fn subseq_transpile(uexpr: &UnaryExpr, subseq: &Subseq, ta: &TypeAnnotation)
         Subseq::Member(ref mem) => {
             let caller = uexpr.transpile(ta);
             match ta.annotation(mem.mem_id.get_tag_number(), "StructType", 0) {
+                Type::Tuple(_) => format!("std::get<{}>({})", mem.mem_id.into_string(), caller),
+                Type::Ref(ty) | Type::MutRef(ty) if matches!(*ty, Type::Tuple(_)) => format!("std::get<{}>(*{})", mem.mem_id.into_string(), caller),
                 Type::Ref(_) => format!("{}->{}", caller, mem.mem_id.into_string()),
                 Type::MutRef(_) => format!("{}->{}", caller, mem.mem_id.into_string()),
                 _ => format!("{}.{}", caller, mem.mem_id.into_string())
@@ -307,11 +337,22 @@ pub struct Member {
     pub mem_id: Identifier,
 }
 
+pub fn method_call(caller: UnaryExpr, mem_id: Identifier, args: Vec<Expression>) -> UnaryExpr {
+    let member = UnaryExpr::Subseq(Box::new(caller), Subseq::Member(Member { mem_id }));
+    UnaryExpr::Subseq(Box::new(member), Subseq::Call(Call { args, tag: Tag::new() }))
+}
+
 fn parse_member(s: &str) -> IResult<&str, Subseq> {
-    let (s, (_, _, mem_id)) = tuple((char('.'), multispace0, parse_identifier))(s)?;
+    let (s, (_, _, mem_id)) = tuple((char('.'), multispace0, alt((parse_identifier, parse_tuple_index))))(s)?;
     Ok((s, Subseq::Member(Member { mem_id })))
 }
 
+fn parse_tuple_index(s: &str) -> IResult<&str, Identifier> {
+    let start = s;
+    let (s, index) = digit1(s)?;
+    Ok((s, Identifier::from_str(index).with_span(Span::between(start, s))))
+}
+
 #[test]
 fn parse_call_test() {
     log::debug!("{:?}", parse_call("()"));
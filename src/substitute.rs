@@ -1,35 +1,80 @@
 //use nom::branch::*;
 use nom::IResult;
-use nom::character::complete::*;
 use nom::sequence::*;
 use nom::bytes::complete::*;
 
+use crate::comment::multispace0;
 use crate::expression::{ Expression, parse_expression };
+use crate::identifier::Tag;
+use crate::traits::*;
+use crate::type_id::is_primitive_number;
 use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
+use crate::diagnostics::Span;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug)]
 pub struct Substitute {
     pub into_expr: Expression,
     pub value: Expression,
+    pub compound: Option<(&'static str, Tag)>,
+    pub span: Span,
 }
 
 impl GenType for Substitute {
     fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
         let left = self.into_expr.gen_type(equs, trs)?;
         let right = self.value.gen_type(equs, trs)?;
-        equs.add_equation(left, right);
+        match self.compound {
+            None => equs.add_equation(left, right),
+            Some((ope, ref tag)) => {
+                let (assign_trait, _, binary_trait) = find_compound_assign_operator(ope).unwrap();
+                let alpha = tag.generate_not_void_type_variable("CompoundAssignType", 0, equs);
+                equs.add_equation(alpha.clone(), left);
+                let beta = tag.generate_not_void_type_variable("CompoundAssignType", 1, equs);
+                equs.add_equation(beta.clone(), right);
+                let before = equs.set_span(self.span);
+                equs.add_compound_assign(alpha, beta, CompoundAssignEquation {
+                    assign_trait: TraitId::from_str(assign_trait),
+                    binary_trait: TraitId::from_str(binary_trait),
+                    tag: tag.clone(),
+                });
+                equs.set_span(before);
+            }
+        }
         Ok(Type::End)
     }
 }
 
 impl Transpile for Substitute {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        format!("{} = {}",
-                self.into_expr.transpile(ta),
-                self.value.transpile(ta)
-        )
+        match self.compound {
+            None => {
+                format!("{} = {}",
+                        self.into_expr.transpile(ta),
+                        self.value.transpile(ta)
+                )
+            }
+            Some((ope, ref tag)) => {
+                let (assign_trait, assign_method, _) = find_compound_assign_operator(ope).unwrap();
+                let left = ta.annotation(tag.get_num(), "CompoundAssignType", 0);
+                let into_expr = self.into_expr.transpile(ta);
+                if is_primitive_number(&left) {
+                    format!("{} {} {}", into_expr, ope, self.value.transpile(ta))
+                }
+                else if ta.try_annotation(tag.get_num(), "CompoundAssignTrait", 0).is_some() {
+                    let right = ta.annotation(tag.get_num(), "CompoundAssignType", 1);
+                    ta.references.use_trait(assign_trait);
+                    format!("{}<{}, {}>::{}(&{}, {})", assign_trait, left.transpile(ta), right.transpile(ta), assign_method, into_expr, self.value.transpile(ta))
+                }
+                else {
+                    let binary = &ope[..ope.len() - 1];
+                    let place = ta.lowering.bind(tag.get_num(), into_expr);
+                    format!("{} = {} {} {}", place, place, binary, self.value.transpile(ta))
+                }
+            }
+        }
     }
 }
 
@@ -41,12 +86,37 @@ impl MutCheck for Substitute {
             Ok(MutResult::NoType)
         }
         else {
-            Err(format!("{:?} is not mutable", self.into_expr))
+            Err(self.into_expr.get_span().error_message("left side of substitution is not mutable"))
         }
     }
 }
 
+impl Resolve for Substitute {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.into_expr.resolve(scope)?;
+        self.value.resolve(scope)
+    }
+}
+
+fn parse_substitute_operator(s: &str) -> IResult<&str, Option<&'static str>> {
+    if let Some(ope) = COMPOUND_ASSIGN_OPERATORS.iter().map(|(ope, _)| *ope).find(|ope| s.starts_with(ope)) {
+        Ok((&s[ope.len()..], Some(ope)))
+    }
+    else {
+        let (s, _) = tag("=")(s)?;
+        Ok((s, None))
+    }
+}
+
 pub fn parse_substitute(s: &str) -> IResult<&str, Substitute> {
-    let (s, (into_expr, _, _e, _, value)) = tuple((parse_expression, multispace0, tag("="), multispace0, parse_expression))(s)?;
-    Ok((s, Substitute { into_expr, value, }))
+    let start = s;
+    let (s, (into_expr, _, ope, _, value)) = tuple((parse_expression, multispace0, parse_substitute_operator, multispace0, parse_expression))(s)?;
+    Ok((s, Substitute { into_expr, value, compound: ope.map(|ope| (ope, Tag::new())), span: Span::between(start, s) }))
+}
+
+#[test]
+fn parse_substitute_test() {
+    log::debug!("{:?}", parse_substitute("x = x + 1").ok());
+    log::debug!("{:?}", parse_substitute("x += 1").ok());
+    log::debug!("{:?}", parse_substitute("*x <<= 1").ok());
 }
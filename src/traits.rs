@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet };
 
 pub mod associated_type;
 pub use associated_type::*;
@@ -12,26 +12,62 @@ pub use trait_method::*;
 
 use nom::bytes::complete::*;
 use nom::character::complete::*;
-//use nom::branch::*;
+use nom::branch::*;
 use nom::combinator::*;
 use nom::multi::*;
 use nom::sequence::*;
 use nom::IResult;
 
-use crate::identifier::{ Identifier, parse_identifier, Tag };
+use crate::comment::{ multispace0, multispace1, parse_doc_comments, transpile_doc_comments };
+use crate::identifier::{ Identifier, Tag };
 //use crate::unary_expr::Variable;
 use crate::unify::where_section::*;
 use crate::unify::*;
 use crate::trans::*;
+use crate::mut_checker::*;
 use crate::func_definition::*;
 use crate::type_spec::*;
 use crate::type_id::*;
+use crate::module::{ ItemKind, ModuleScope, Resolve, parse_item_identifier, parse_visibility, transpile_in_namespace };
 
 pub const BINARY_OPERATOR_TRAITS : [(&'static str, (&'static str, &'static str)); 10] = [
             ("BitOr", ("operator|", "|")), ("BitXor", ("operator^", "^")), ("BitAnd", ("operator&", "&")),
             ("Shl", ("operator<<", "<<")), ("Shr", ("operator>>", ">>")), ("Add", ("operator+", "+")),
             ("Sub", ("operator-", "-")), ("Mul", ("operator*", "*")), ("Div", ("operator/", "/")), ("Rem", ("operator%", "%"))
         ];
+pub const UNARY_OPERATOR_TRAITS : [(&str, (&str, &str)); 2] = [
+            ("Neg", ("operator-", "-")), ("Not", ("operator!", "!")),
+        ];
+pub const COMPARISON_OPERATOR_TRAITS : [(&str, (&str, &str)); 2] = [
+            ("Eq", ("operator==", "==")), ("Ord", ("operator<", "<")),
+        ];
+pub fn find_comparison_operator(tr: &str) -> Option<(&'static str, &'static str)> {
+    COMPARISON_OPERATOR_TRAITS.iter().find_map(|(tr_id, val)|
+        if *tr_id == tr { Some(*val) }
+        else { None }
+    )
+}
+pub const COMPOUND_ASSIGN_OPERATORS : [(&str, (&str, &str, &str)); 10] = [
+            ("<<=", ("ShlAssign", "shl_assign", "Shl")), (">>=", ("ShrAssign", "shr_assign", "Shr")),
+            ("|=", ("BitOrAssign", "bit_or_assign", "BitOr")), ("^=", ("BitXorAssign", "bit_xor_assign", "BitXor")), ("&=", ("BitAndAssign", "bit_and_assign", "BitAnd")),
+            ("+=", ("AddAssign", "add_assign", "Add")), ("-=", ("SubAssign", "sub_assign", "Sub")),
+            ("*=", ("MulAssign", "mul_assign", "Mul")), ("/=", ("DivAssign", "div_assign", "Div")), ("%=", ("RemAssign", "rem_assign", "Rem")),
+        ];
+pub fn find_compound_assign_operator(ope: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    COMPOUND_ASSIGN_OPERATORS.iter().find_map(|(ope_str, val)|
+        if *ope_str == ope { Some(*val) }
+        else { None }
+    )
+}
+pub fn find_unary_operator(tr: &str) -> Option<(&'static str, &'static str)> {
+    UNARY_OPERATOR_TRAITS.iter().find_map(|(tr_id, val)|
+        if *tr_id == tr { Some(*val) }
+        else { None }
+    )
+}
+pub fn find_operator(tr: &str) -> Option<(&'static str, &'static str)> {
+    find_binary_operator(tr).or_else(|| find_unary_operator(tr)).or_else(|| find_comparison_operator(tr))
+}
 pub fn find_binary_operator(tr: &str) -> Option<(&'static str, &'static str)> {
     BINARY_OPERATOR_TRAITS.iter().find_map(|(tr_id, val)|
         if *tr_id == tr { Some(*val) }
@@ -44,6 +80,12 @@ pub struct TraitId {
     pub id: Identifier,
 }
 
+impl std::fmt::Display for TraitId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.id.name)
+    }
+}
+
 impl TraitId {
     pub fn from_str(s: &str) -> TraitId {
         TraitId { id: Identifier::from_str(s) }
@@ -51,13 +93,20 @@ impl TraitId {
 }
 
 impl Transpile for TraitId {
-    fn transpile(&self, _: &TypeAnnotation) -> String {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        ta.references.use_trait(&self.id.into_string());
         self.id.into_string()
     }
 }
 
+impl Resolve for TraitId {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        scope.resolve_item(ItemKind::Trait, &mut self.id)
+    }
+}
+
 pub fn parse_trait_id(s: &str) -> IResult<&str, TraitId> {
-    let (s, id) = parse_identifier(s)?;
+    let (s, id) = parse_item_identifier(s)?;
     Ok((s, TraitId { id }))
 }
 
@@ -83,6 +132,23 @@ impl TraitSpec {
     }
 }
 
+impl std::fmt::Display for TraitSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.trait_id.id.into_string())?;
+        if !self.generics.is_empty() {
+            write!(f, "<{}>", self.generics.iter().map(|g| g.to_string()).collect::<Vec<_>>().join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Resolve for TraitSpec {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.trait_id.resolve(scope)?;
+        self.generics.resolve(scope)
+    }
+}
+
 fn parse_generics_args(s: &str) -> IResult<&str, Vec<TypeId>> {
     let (s, op) = opt(tuple((multispace0, char('<'), multispace0, separated_list0(tuple((multispace0, char(','), multispace0)), parse_type_id), multispace0, char('>'))))(s)?;
     Ok((s, op.map(|(_, _, _, res, _, _)| res).unwrap_or(Vec::new())))
@@ -96,13 +162,16 @@ pub fn parse_trait_spec(s: &str) -> IResult<&str, TraitSpec> {
     Ok((s, TraitSpec { trait_id, generics }))
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct TraitDefinition {
     pub trait_id: TraitId,
     pub generics: Vec<TypeId>,
     pub where_sec: WhereSection,
     pub asso_ids: Vec<AssociatedTypeIdentifier>,
+    pub asso_consts: HashMap<AssociatedTypeIdentifier, TypeSpec>,
     pub required_methods: HashMap<TraitMethodIdentifier, FuncDefinitionInfo>,
+    pub default_methods: HashMap<TraitMethodIdentifier, FuncDefinition>,
+    pub doc: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -111,7 +180,9 @@ pub struct TraitDefinitionInfo {
     pub generics: Vec<TypeId>,
     pub where_sec: WhereSection,
     pub asso_ids: Vec<AssociatedTypeIdentifier>,
+    pub asso_consts: HashMap<AssociatedTypeIdentifier, TypeSpec>,
     pub required_methods: HashMap<TraitMethodIdentifier, FuncDefinitionInfo>,
+    pub default_methods: HashSet<TraitMethodIdentifier>,
 }
 
 impl TraitDefinition {
@@ -121,17 +192,47 @@ impl TraitDefinition {
             generics: self.generics.clone(),
             where_sec: self.where_sec.clone(),
             asso_ids: self.asso_ids.clone(),
+            asso_consts: self.asso_consts.clone(),
             required_methods: self.required_methods.clone(),
+            default_methods: self.default_methods.keys().cloned().collect(),
         })
     }
+    pub fn get_default_method(&self, method_id: &TraitMethodIdentifier) -> Option<&FuncDefinition> {
+        self.default_methods.get(method_id)
+    }
+    pub fn unify_default_methods(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> Result<(), String> {
+        if self.default_methods.is_empty() {
+            return Ok(())
+        }
+        let mut trs = trs.into_scope();
+        let self_id = TypeId::from_str("Self");
+        trs.regist_generics_type(&self_id)?;
+        for g in self.generics.iter() {
+            trs.regist_generics_type(g)?;
+        }
+        let self_ty = Type::Generics(self_id, Vec::new());
+        let trait_gen = TraitGenerics { trait_id: self.trait_id.clone(), generics: self.generics.iter().map(|g| Type::Generics(g.clone(), Vec::new())).collect() };
+        let before_self_type = equs.set_self_type(Some(self_ty.clone()));
+        let result = self.where_sec.regist_candidate(equs, &mut trs)
+            .and_then(|_| trs.regist_param_candidate(self_ty, &trait_gen, HashMap::new()))
+            .and_then(|_| self.default_methods.values().try_for_each(|def| def.unify_definition(equs, &trs)));
+        equs.set_self_type(before_self_type);
+        result
+    }
+    pub fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<(), String> {
+        for def in self.default_methods.values() {
+            def.mut_check(ta, vars)?;
+        }
+        Ok(())
+    }
 }
 
 impl Transpile for TraitDefinition {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
-        match find_binary_operator(self.trait_id.id.into_string().as_str()) {
+        match find_operator(self.trait_id.id.into_string().as_str()) {
             None => {
                 let generics = self.generics.iter().map(|g| format!(", class {}", g.transpile(ta))).collect::<Vec<_>>().join("");
-                format!("template<class Self{}, class = void> struct {}: std::false_type {{ }};\n", generics, self.trait_id.transpile(ta))
+                transpile_in_namespace(&self.trait_id.transpile(ta), |name| format!("{}template<class Self{}, class = void> struct {}: std::false_type {{ }};\n", transpile_doc_comments(&self.doc), generics, name))
             }
             Some(_) => {
                 format!("")
@@ -140,28 +241,54 @@ impl Transpile for TraitDefinition {
     }
 }
 
+impl Resolve for TraitDefinition {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.trait_id.resolve(scope)?;
+        self.generics.resolve(scope)?;
+        self.where_sec.resolve(scope)?;
+        self.asso_consts.values_mut().try_for_each(|spec| spec.resolve(scope))?;
+        self.required_methods.values_mut().try_for_each(|info| info.resolve(scope))?;
+        self.default_methods.values_mut().try_for_each(|func| func.resolve(scope))
+    }
+}
+
 pub fn parse_trait_definition(s: &str) -> IResult<&str, TraitDefinition> {
-    let (s, (_, _, trait_id, _, generics, _, where_sec, _, _, _, many_types, many_methods, _, _)) = 
-        tuple((tag("trait"), space1, parse_trait_id,
+    let (s, (doc, _, _, trait_id, _, generics, _, supertraits, _, mut where_sec, _, _, _, many_types, many_consts, many_methods, _, _)) = 
+        tuple((parse_doc_comments, preceded(parse_visibility, tag("trait")), multispace1, parse_trait_id,
             multispace0, parse_generics_args,
+            multispace0, opt(tuple((char(':'), multispace0, separated_list1(tuple((multispace0, char('+'), multispace0)), parse_trait_spec)))),
             multispace0, parse_where_section, multispace0, char('{'), multispace0,
-            many0(tuple((tag("type"), space1, parse_associated_type_identifier, multispace0, char(';'), multispace0))),
-            many0(tuple((parse_func_definition_info, multispace0, char(';'), multispace0))),
+            many0(tuple((tag("type"), multispace1, parse_associated_type_identifier, multispace0, char(';'), multispace0))),
+            many0(tuple((tag("const"), multispace1, parse_associated_type_identifier, multispace0, char(':'), multispace0, parse_type_spec, multispace0, char(';'), multispace0))),
+            many0(tuple((parse_trait_method_item, multispace0))),
             multispace0, char('}')))(s)?;
+    if let Some((_, _, supertraits)) = supertraits {
+        where_sec.add_supertraits(supertraits);
+    }
     let asso_ids = many_types.into_iter().map(|(_, _, id, _, _, _)| id).collect();
+    let asso_consts = many_consts.into_iter().map(|(_, _, id, _, _, _, ty, _, _, _)| (id, ty)).collect();
     //let required_methods = many_methods.into_iter().map(|(info, _, _, _)| (TraitMethodIdentifier { id: info.func_id.clone() }, info)).collect();
-    let required_methods = match find_binary_operator(trait_id.id.into_string().as_str()) {
+    let required_methods = match find_operator(trait_id.id.into_string().as_str()) {
         None => {
-            many_methods.into_iter().map(|(func, _, _, _)| (TraitMethodIdentifier { id: func.func_id.clone() }, func)).collect()
+            many_methods.iter().map(|((func, _), _)| (TraitMethodIdentifier { id: func.func_id.clone() }, func.clone())).collect()
         }
         Some((_, ope)) => {
-            many_methods.into_iter().map(|(mut func, _, _, _)| {
+            many_methods.iter().map(|((func, _), _)| {
+                let mut func = func.clone();
                 func.func_id = Identifier::from_str(format!("operator{}", ope).as_str());
                 (TraitMethodIdentifier { id: func.func_id.clone() }, func)
             }).collect()
         }
     };
-    Ok((s, TraitDefinition { trait_id, generics, where_sec, asso_ids, required_methods }))
+    let default_methods = many_methods.into_iter().filter_map(|((_, def), _)| def).map(|def| (TraitMethodIdentifier { id: def.func_id.clone() }, def)).collect();
+    Ok((s, TraitDefinition { trait_id, generics, where_sec, asso_ids, asso_consts, required_methods, default_methods, doc }))
+}
+
+fn parse_trait_method_item(s: &str) -> IResult<&str, (FuncDefinitionInfo, Option<FuncDefinition>)> {
+    alt((
+        map(tuple((parse_func_definition_info, multispace0, char(';'))), |(info, _, _)| (info, None)),
+        map(parse_func_definition, |def| (def.get_func_info().1, Some(def))),
+    ))(s)
 }
 
 
@@ -169,4 +296,7 @@ pub fn parse_trait_definition(s: &str) -> IResult<&str, TraitDefinition> {
 #[test]
 fn parse_trait_definition_test() {
     log::debug!("{:?}", parse_trait_definition("trait MyTrait { type Output; type Input; }"));
+    log::debug!("{:?}", parse_trait_definition("trait Mod { const M: u64; fn inv(a: u64) -> u64; }"));
+    log::debug!("{:?}", parse_trait_definition("trait Group: Monoid + Inv<Self> where Self: Clone { fn inv(a: Self) -> Self; }"));
+    log::debug!("{:?}", parse_trait_definition("trait Monoid { fn e() -> Self; fn op(a: Self, b: Self) -> Self; fn twice(a: Self) -> Self { Self#Monoid::op(a, a) } }"));
 }
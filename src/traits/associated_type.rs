@@ -1,10 +1,12 @@
 use nom::IResult;
 use nom::sequence::*;
 
+use crate::comment::multispace0;
 use crate::identifier::*;
 use crate::traits::*;
 
 use crate::trans::*;
+use crate::module::{ ModuleScope, Resolve, parse_item_member };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AssociatedTypeIdentifier {
@@ -30,9 +32,22 @@ pub struct AssociatedType {
     pub type_id: AssociatedTypeIdentifier,
 }
 
+impl Resolve for AssociatedType {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.trait_spec.resolve(scope)
+    }
+}
+
+fn parse_associated_type_path(s: &str) -> IResult<&str, AssociatedType> {
+    let (s, (trait_id, id)) = parse_item_member(s)?;
+    Ok((s, AssociatedType { trait_spec: TraitSpec { trait_id: TraitId { id: trait_id }, generics: Vec::new() }, type_id: AssociatedTypeIdentifier { id } }))
+}
+
 pub fn parse_associated_type(s: &str) -> IResult<&str, AssociatedType> {
-    let (s, (trait_spec, _, _, _, type_id)) = tuple((parse_trait_spec, multispace0, tag("::"), multispace0, parse_associated_type_identifier))(s)?;
-    Ok((s, AssociatedType { trait_spec, type_id }))
+    if let Ok((s, (trait_spec, _, _, _, type_id))) = tuple((parse_trait_spec, multispace0, tag("::"), multispace0, parse_associated_type_identifier))(s) {
+        return Ok((s, AssociatedType { trait_spec, type_id }))
+    }
+    parse_associated_type_path(s)
 }
 
 
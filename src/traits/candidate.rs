@@ -7,6 +7,7 @@ use nom::multi::*;
 use nom::sequence::*;
 use nom::IResult;
 
+use crate::comment::{ multispace0, multispace1 };
 use crate::identifier::*;
 use crate::type_id::*;
 use crate::type_spec::*;
@@ -17,6 +18,8 @@ use crate::mut_checker::*;
 use crate::traits::*;
 use crate::func_definition::*;
 use crate::structs::*;
+use crate::expression::{ Expression, parse_expression };
+use crate::module::{ ModuleScope, Resolve };
 
 
 #[derive(Debug, Clone)]
@@ -111,6 +114,7 @@ pub struct ImplDefinition {
     pub impl_ty: TypeSpec,
     pub where_sec: WhereSection,
     pub asso_defs: HashMap<AssociatedTypeIdentifier, TypeSpec>,
+    pub asso_consts: HashMap<AssociatedTypeIdentifier, (TypeSpec, Expression)>,
     pub require_methods: HashMap<TraitMethodIdentifier, FuncDefinition>,
 }
 
@@ -137,40 +141,55 @@ impl ImplDefinition {
         for ty_id in self.generics.iter() {
             trs.regist_generics_type(ty_id)?;
         }
+        equs.into_scope();
+        for ty_id in self.generics.iter() {
+            ty_id.regist_const_variable(equs, &trs)?;
+        }
         self.where_sec.regist_candidate(equs, &mut trs)?;
         let next_self_type = Some(self.impl_ty.generate_type_no_auto_generics(equs, &trs)?);
         let before_self_type = equs.set_self_type(next_self_type);
-        for def in self.require_methods.values() {
-            def.unify_definition(equs, &trs)?;
-        }
+        let result = self.asso_consts.values().try_for_each(|(spec, value)| {
+            let const_ty = spec.generics_to_type(&GenericsTypeMap::empty(), equs, &trs)?;
+            let value_ty = value.gen_type(equs, &trs)?;
+            equs.add_equation(const_ty, value_ty);
+            equs.unify(&trs).map_err(|err| err.to_string())
+        }).and_then(|_| self.require_methods.values().try_for_each(|def| def.unify_definition(equs, &trs)));
         equs.set_self_type(before_self_type);
-        Ok(())
+        equs.out_scope();
+        result
     }
 
     pub fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<(), String> {
+        for (_, value) in self.asso_consts.values() {
+            value.mut_check(ta, vars)?;
+        }
         for def in self.require_methods.values() {
             def.mut_check(ta, vars)?;
         }
         Ok(())
     }
-    pub fn transpile_functions(&self, ta: &TypeAnnotation) -> String {
-        match find_binary_operator(self.trait_spec.trait_id.id.into_string().as_str()) {
+    pub fn transpile_functions(&self, ta: &TypeAnnotation, tr: Option<&TraitDefinition>) -> String {
+        match find_operator(self.trait_spec.trait_id.id.into_string().as_str()) {
             None => {
-                let generics = self.generics.iter().map(|id| format!("class {}", id.transpile(ta))).collect::<Vec<_>>().join(", ");
+                let generics = self.generics.iter().map(|id| id.transpile_template_param(ta)).collect::<Vec<_>>().join(", ");
                 let templates = if generics == "" { format!("") } else { format!("template<{}> ", generics) };
                 let where_str = self.where_sec.transpile(ta);
                 let generics_param = std::iter::once(self.impl_ty.transpile(ta)).chain(self.trait_spec.generics.iter().map(|g| g.transpile(ta)))
                     .collect::<Vec<_>>().join(", ");
                 let class_str = format!("{}<{}, {}>::", self.trait_spec.trait_id.transpile(ta), generics_param, where_str);
                 let require_methods = self.require_methods.iter().map(|(_, def)| {
-                    format!("{}{}", templates, def.transpile_for_impl(ta, &class_str, false))
-                }).collect::<Vec<_>>().join("\n");
-                require_methods
+                    format!("{}{}{}", def.transpile_doc(), templates, def.transpile_for_impl(ta, &class_str, false))
+                });
+                let default_methods = self.omitted_default_methods(tr).into_iter().map(|def| {
+                    format!("{}{}", templates, def.transpile_for_default_impl(ta, &class_str))
+                });
+                require_methods.chain(default_methods).collect::<Vec<_>>().join("\n")
             }
             Some((func, _)) => {
-                
-                let generics = self.generics.iter().map(|id| format!("class {}", id.transpile(ta)))
-                    .chain(std::iter::once(format!("class = {}", self.where_sec.transpile(ta))))
+                // the default template argument lives on the forward declaration,
+                // so bodies emitted earlier can already call this operator
+                let generics = self.generics.iter().map(|id| id.transpile_template_param(ta))
+                    .chain(std::iter::once(format!("class")))
                     .collect::<Vec<_>>().join(", ");
                 let templates = format!("template<{}> ", generics);
                 let require_methods = self.require_methods.iter().map(|(_, def)| {
@@ -179,7 +198,7 @@ impl ImplDefinition {
                         format!("")
                     }
                     else {
-                        format!("{}{}\n", templates, func)
+                        format!("{}{}{}\n", def.transpile_doc(), templates, func)
                     }
                 }).collect::<Vec<_>>().join("");
                 require_methods
@@ -188,8 +207,23 @@ impl ImplDefinition {
     }
 }
 
+impl Resolve for ImplDefinition {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.generics.resolve(scope)?;
+        self.trait_spec.resolve(scope)?;
+        self.impl_ty.resolve(scope)?;
+        self.where_sec.resolve(scope)?;
+        self.asso_defs.values_mut().try_for_each(|spec| spec.resolve(scope))?;
+        self.asso_consts.values_mut().try_for_each(|(spec, expr)| {
+            spec.resolve(scope)?;
+            expr.resolve(scope)
+        })?;
+        self.require_methods.values_mut().try_for_each(|func| func.resolve(scope))
+    }
+}
+
 fn parse_generics_args(s: &str) -> IResult<&str, Vec<TypeId>> {
-    let (s, op) = opt(tuple((multispace0, char('<'), multispace0, separated_list0(tuple((multispace0, char(','), multispace0)), parse_type_id), multispace0, char('>'))))(s)?;
+    let (s, op) = opt(tuple((multispace0, char('<'), multispace0, separated_list0(tuple((multispace0, char(','), multispace0)), parse_generics_param), multispace0, char('>'))))(s)?;
     Ok((s, op.map(|(_, _, _, res, _, _)| res).unwrap_or(Vec::new())))
 }
 
@@ -199,17 +233,19 @@ fn parse_generics_args(s: &str) -> IResult<&str, Vec<TypeId>> {
 }*/
 
 pub fn parse_impl_definition(s: &str) -> IResult<&str, ImplDefinition> {
-    let (s, (_, generics, _, trait_spec, _, _, _, impl_ty, _, where_sec, _, _, _, many_types, many_methods, _, _)) = 
+    let (s, (_, generics, _, trait_spec, _, _, _, impl_ty, _, where_sec, _, _, _, many_types, many_consts, many_methods, _, _)) = 
         tuple((tag("impl"), parse_generics_args,
-            space1, parse_trait_spec,
-            space1, tag("for"), space1, parse_type_spec,
+            multispace1, parse_trait_spec,
+            multispace1, tag("for"), multispace1, parse_type_spec,
             multispace0, parse_where_section,
             multispace0, char('{'), multispace0,
-            many0(tuple((tag("type"), space1, parse_associated_type_identifier, multispace0, char('='), multispace0, parse_type_spec, multispace0, char(';'), multispace0))),
+            many0(tuple((tag("type"), multispace1, parse_associated_type_identifier, multispace0, char('='), multispace0, parse_type_spec, multispace0, char(';'), multispace0))),
+            many0(tuple((tag("const"), multispace1, parse_associated_type_identifier, multispace0, char(':'), multispace0, parse_type_spec, multispace0, char('='), multispace0, parse_expression, multispace0, char(';'), multispace0))),
             many0(tuple((parse_func_definition, multispace0))),
             multispace0, char('}')))(s)?;
     let asso_defs = many_types.into_iter().map(|(_, _, id, _, _, _, ty, _, _, _)| (id, ty)).collect();
-    let require_methods = match find_binary_operator(trait_spec.trait_id.id.into_string().as_str()) {
+    let asso_consts = many_consts.into_iter().map(|(_, _, id, _, _, _, ty, _, _, _, value, _, _, _)| (id, (ty, value))).collect();
+    let require_methods = match find_operator(trait_spec.trait_id.id.into_string().as_str()) {
         None => {
             many_methods.into_iter().map(|(func, _)| (TraitMethodIdentifier { id: func.func_id.clone() }, func)).collect()
         }
@@ -220,41 +256,80 @@ pub fn parse_impl_definition(s: &str) -> IResult<&str, ImplDefinition> {
             }).collect()
         }
     };
-    Ok((s, ImplDefinition { generics, trait_spec, impl_ty, where_sec, asso_defs, require_methods }))
+    Ok((s, ImplDefinition { generics, trait_spec, impl_ty, where_sec, asso_defs, asso_consts, require_methods }))
 }
 
-impl Transpile for ImplDefinition {
-    fn transpile(&self, ta: &TypeAnnotation) -> String {
+impl ImplDefinition {
+    fn omitted_default_methods<'a>(&self, tr: Option<&'a TraitDefinition>) -> Vec<&'a FuncDefinition> {
+        tr.map(|tr| tr.default_methods.iter()
+                .filter(|(id, _)| !self.require_methods.contains_key(id))
+                .map(|(_, def)| def).collect())
+            .unwrap_or_default()
+    }
 
-        match find_binary_operator(self.trait_spec.trait_id.id.into_string().as_str()) {
+    fn transpile_supertraits(&self, ta: &TypeAnnotation, tr: &TraitDefinition) -> Vec<String> {
+        let trait_gens = tr.generics.iter().map(TypeSpec::from_id).zip(self.trait_spec.generics.iter()).collect::<Vec<_>>();
+        tr.where_sec.self_supertraits()
+            .filter(|sup| find_operator(sup.trait_id.id.into_string().as_str()).is_none())
+            .map(|sup| {
+                let generics = std::iter::once(self.impl_ty.transpile(ta)).chain(sup.generics.iter().map(|g| {
+                    match trait_gens.iter().find(|(id, _)| id == g) {
+                        Some((_, spec)) => spec.transpile(ta),
+                        None => g.transpile(ta),
+                    }
+                })).collect::<Vec<_>>().join(", ");
+                format!("{}<{}>", sup.trait_id.transpile(ta), generics)
+            }).collect()
+    }
+    pub fn transpile_definition(&self, ta: &TypeAnnotation, tr: Option<&TraitDefinition>) -> String {
+
+        match find_operator(self.trait_spec.trait_id.id.into_string().as_str()) {
             None => {
-                let generics = self.generics.iter().map(|id| format!("class {}", id.transpile(ta))).collect::<Vec<_>>().join(", ");
+                let generics = self.generics.iter().map(|id| id.transpile_template_param(ta)).collect::<Vec<_>>().join(", ");
                 let where_str = self.where_sec.transpile(ta);
                 let templates = format!("template<{}> ", generics);
                 let generics_param = std::iter::once(self.impl_ty.transpile(ta)).chain(self.trait_spec.generics.iter().map(|g| g.transpile(ta)))
                     .collect::<Vec<_>>().join(", ");
+                let bases = match tr {
+                    Some(tr) => self.transpile_supertraits(ta, tr),
+                    None => Vec::new(),
+                };
+                let bases = if bases.is_empty() { "std::true_type".to_string() } else { bases.join(", ") };
                 let impl_def = if self.where_sec.is_empty() {
-                    format!("{}struct {}<{}, void>: std::true_type", templates, self.trait_spec.trait_id.transpile(ta), generics_param.clone())
+                    format!("{}struct {}<{}, void>: {}", templates, self.trait_spec.trait_id.transpile(ta), generics_param.clone(), bases)
                 }
                 else {
-                    format!("{}struct {}<{}, {}>: std::true_type", templates, self.trait_spec.trait_id.transpile(ta), generics_param.clone(), where_str)
+                    format!("{}struct {}<{}, {}>: {}", templates, self.trait_spec.trait_id.transpile(ta), generics_param.clone(), where_str, bases)
                 };
                 let asso_defs = self.asso_defs.iter().map(|(id, spec)| {
                     format!("using {} = {};\n", id.transpile(ta), spec.transpile(ta))
                 }).collect::<Vec<_>>().join(" ");
-                let require_methods = self.require_methods.iter().map(|(_, def)| {
+                let asso_consts = self.asso_consts.iter().map(|(id, (spec, value))| {
+                    format!("static constexpr {} {} = {};\n", spec.transpile(ta), id.transpile(ta), ta.lowering.constant(|| value.transpile(ta)))
+                }).collect::<Vec<_>>().join("");
+                let default_methods = self.omitted_default_methods(tr);
+                let trait_generics = match tr {
+                    Some(tr) if !default_methods.is_empty() => {
+                        tr.generics.iter().zip(self.trait_spec.generics.iter())
+                            .filter(|(id, _)| !self.generics.contains(id))
+                            .map(|(id, spec)| format!("using {} = {};\n", id.transpile(ta), spec.transpile(ta)))
+                            .collect::<Vec<_>>().join("")
+                    }
+                    _ => String::new(),
+                };
+                let require_methods = self.require_methods.values().chain(default_methods).map(|def| {
                     let def_str = def.transpile_definition_only(ta, "", true);
                     format!("{};", def_str)
                 }).collect::<Vec<_>>().join("\n");
-                format!("{} {{\nusing Self = {};\n{}\n{}\n}};\n", impl_def, self.impl_ty.transpile(ta), asso_defs, require_methods)
+                format!("{} {{\nusing Self = {};\n{}{}\n{}{}\n}};\n", impl_def, self.impl_ty.transpile(ta), trait_generics, asso_defs, asso_consts, require_methods)
             }
             Some((func, _)) => {
                 if let FuncBlock::CppInline(_) = self.require_methods[&TraitMethodIdentifier { id: Identifier::from_str(func) }].block {
                     format!("")
                 }
                 else {
-                    let generics = self.generics.iter().map(|id| format!("class {}", id.transpile(ta)))
-                        .chain(std::iter::once(format!("class")))
+                    let generics = self.generics.iter().map(|id| id.transpile_template_param(ta))
+                        .chain(std::iter::once(format!("class = {}", self.where_sec.transpile(ta))))
                         .collect::<Vec<_>>().join(", ");
                     let templates = format!("template<{}> ", generics);
                     let require_methods = self.require_methods.iter().map(|(_, def)| {
@@ -315,10 +390,7 @@ impl ImplCandidate {
         let impl_ty = self.impl_ty.generics_to_type(&gen_mp, &mut equs, trs).unwrap();
         equs.add_equation(impl_ty, self_type.clone());
         self.where_sec.regist_equations(&gen_mp, &mut equs, trs)?;
-        let func_ty = self.require_methods
-            .get(&TraitMethodIdentifier { id: call_eq.func_id.clone() })
-            .ok_or(format!("require methods doesnt have {:?}", call_eq.func_id))?
-            .generate_type(&gen_mp, &mut equs, trs, &call_eq.func_id)?;
+        let func_ty = self.generate_method_type(&mut equs, trs, &TraitMethodIdentifier { id: call_eq.func_id.clone() }, &gen_mp, &self_trait_gen)?;
         match func_ty {
             Type::Func(args, ret, info) => {
                 let alpha = call_eq.tag.generate_type_variable("FuncTypeInfo", 0, &mut equs);
@@ -391,13 +463,13 @@ impl ImplCandidate {
         let mp = GenericsTypeMap::empty();
         let gen_mp = mp.next(gen_mp);
         let before_self_type = equs.set_self_type(Some(ty.clone()));
-        let func_ty = self.require_methods.get(&method_id).unwrap().generate_type(&gen_mp, equs, trs, &method_id.id).unwrap();
+        let self_trait_gen = self.trait_spec.generate_trait_generics(equs, trs, &gen_mp).unwrap();
+        let func_ty = self.generate_method_type(equs, trs, method_id, &gen_mp, &self_trait_gen).unwrap();
         let res = match func_ty {
             Type::Func(args, ret, FuncTypeInfo::None) => {
                 let tag = Tag::new();
                 let alpha = tag.generate_type_variable("SelfType", 0, equs);
                 equs.add_equation(alpha, ty.clone());
-                let self_trait_gen = self.trait_spec.generate_trait_generics(equs, trs, &gen_mp).unwrap();
                 let generics_cnt = self_trait_gen.generics.len();
                 for (i, g) in self_trait_gen.generics.into_iter().enumerate() {
                     let beta = tag.generate_type_variable("TraitGenerics", i, equs);
@@ -411,6 +483,21 @@ impl ImplCandidate {
         res
     }
 
+    fn generate_method_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo, method_id: &TraitMethodIdentifier, gen_mp: &GenericsTypeMap, self_trait_gen: &TraitGenerics) -> TResult {
+        if let Some(info) = self.require_methods.get(method_id) {
+            return info.generate_type(gen_mp, equs, trs, &method_id.id)
+        }
+        match trs.get_traitinfo(&self.trait_spec.trait_id) {
+            Some(tr) if tr.default_methods.contains(method_id) => {
+                let trait_gen_mp = tr.generics.iter().cloned().zip(self_trait_gen.generics.iter().cloned()).collect::<HashMap<_, _>>();
+                let mp = GenericsTypeMap::empty();
+                let trait_gen_mp = mp.next(trait_gen_mp);
+                tr.required_methods[method_id].generate_type(&trait_gen_mp, equs, trs, &method_id.id)
+            }
+            _ => Err(format!("require methods doesnt have {:?}", method_id.id)),
+        }
+    }
+
     pub fn get_trait_id(&self) -> TraitId {
         self.trait_spec.trait_id.clone()
     }
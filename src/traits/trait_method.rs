@@ -1,6 +1,7 @@
 use nom::IResult;
 use nom::sequence::*;
 
+use crate::comment::multispace0;
 use crate::identifier::*;
 use crate::traits::*;
 
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{ HashSet, HashMap };
 
 use crate::type_id::TypeId;
 use crate::identifier::Identifier;
@@ -7,6 +7,8 @@ use crate::func_definition::{ FuncDefinition, FuncDefinitionInfo};
 use crate::structs::*;
 use crate::cpp_inline::*;
 use crate::unify::*;
+use crate::lowering::Lowering;
+use crate::tree_shaking::{ References, ItemIndex, ItemRef };
 
 #[derive(Debug)]
 pub struct TypeAnnotation {
@@ -14,11 +16,14 @@ pub struct TypeAnnotation {
     structs: HashMap<TypeId, (Vec<TypeId>, StructMember)>,
     theta: HashMap<(usize, &'static str, usize), Type>,
     pub self_type: Option<String>,
+    pub lowering: Lowering,
+    pub references: References,
+    selected_impls: HashMap<ItemIndex, HashSet<ItemRef>>,
 }
 
 impl TypeAnnotation {
     pub fn new() -> Self {
-        Self { func: HashMap::new(), structs: HashMap::new(), theta: HashMap::new(), self_type: None }
+        Self { func: HashMap::new(), structs: HashMap::new(), theta: HashMap::new(), self_type: None, lowering: Lowering::new(), references: References::new(), selected_impls: HashMap::new() }
     }
     pub fn insert(&mut self, tv: TypeVariable, t: Type) {
         let TypeVariable::Counter(i, label, num) = tv;
@@ -31,6 +36,12 @@ impl TypeAnnotation {
     pub fn regist_structs_info(&mut self, st: &StructMemberDefinition) {
         self.structs.insert(st.struct_id.clone(), (st.generics.clone(), st.member.clone()));
     }
+    pub fn regist_selected_impls(&mut self, index: ItemIndex, tags: HashSet<usize>) {
+        self.selected_impls.entry(index).or_default().extend(tags.into_iter().map(ItemRef::Impl));
+    }
+    pub fn selected_impls(&self, index: ItemIndex) -> HashSet<ItemRef> {
+        self.selected_impls.get(&index).cloned().unwrap_or_default()
+    }
     pub fn size(&self) -> usize {
         self.theta.len() 
     }
@@ -43,11 +54,15 @@ impl TypeAnnotation {
             }
         }
     }
+    pub fn try_annotation(&self, i: usize, label: &'static str, num: usize) -> Option<Type> {
+        self.theta.get(&(i, label, num)).cloned()
+    }
     pub fn self_type_annotation(&self) -> &str {
         self.self_type.as_ref().unwrap().as_str()
     }
     pub fn trans_variable(&self, var: &Variable) -> String {
         if let Some(f) = self.func.get(var).cloned() {
+            self.references.use_func(&var.id.into_string());
             format!("{}{}", var.id.into_string(), f.get_generics_annotation(self, &var.id))
         }
         else {
@@ -62,6 +77,14 @@ impl TypeAnnotation {
             unreachable!("dont have member {:?}", tyid)
         }
     }
+    pub fn get_enum_variant_index(&self, tyid: &TypeId, id: &Identifier) -> usize {
+        if let StructMember::Enum(ref info) = self.structs.get(tyid).unwrap().1 {
+            info.get_variant_index(id).unwrap()
+        }
+        else {
+            unreachable!("dont have variant {:?}", tyid)
+        }
+    }
     pub fn is_inline_struct(&self, tyid: &TypeId) -> Option<(&Vec<TypeId>, &CppInline)> {
         if let Some((ref gens, StructMember::CppInline(ref cppinline))) = self.structs.get(tyid) {
             Some((&gens, cppinline))
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::collections::{ BinaryHeap, HashMap, HashSet };
+use std::cmp::Reverse;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum ItemRef {
+    Type(String),
+    Trait(String),
+    Func(String),
+    // an impl is named by the tag of its trait identifier, as recorded by type checking
+    Impl(usize),
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ItemIndex {
+    Struct(usize),
+    Trait(usize),
+    Alias(usize),
+    Impl(usize),
+    Func(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct References {
+    used: RefCell<HashSet<ItemRef>>,
+}
+
+impl References {
+    pub fn new() -> Self {
+        References::default()
+    }
+    pub fn use_type(&self, name: &str) {
+        self.used.borrow_mut().insert(ItemRef::Type(name.to_string()));
+    }
+    pub fn use_trait(&self, name: &str) {
+        self.used.borrow_mut().insert(ItemRef::Trait(name.to_string()));
+    }
+    pub fn use_func(&self, name: &str) {
+        self.used.borrow_mut().insert(ItemRef::Func(name.to_string()));
+    }
+    pub fn take(&self) -> HashSet<ItemRef> {
+        std::mem::take(&mut *self.used.borrow_mut())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    names: HashMap<ItemRef, Vec<ItemIndex>>,
+    deps: HashMap<ItemIndex, HashSet<ItemRef>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        DependencyGraph::default()
+    }
+    pub fn regist_item(&mut self, name: ItemRef, index: ItemIndex) {
+        self.names.entry(name).or_default().push(index);
+    }
+    pub fn add_dependencies(&mut self, index: ItemIndex, refs: HashSet<ItemRef>) {
+        self.deps.entry(index).or_default().extend(refs);
+    }
+    pub fn contains(&self, name: &ItemRef) -> bool {
+        self.names.contains_key(name)
+    }
+    pub fn dependency_order(&self, items: &[(ItemIndex, &HashSet<ItemRef>)]) -> Vec<usize> {
+        let position = items.iter().enumerate().map(|(k, (index, _))| (*index, k)).collect::<HashMap<_, _>>();
+        let mut dependents = vec![Vec::new(); items.len()];
+        let mut degree = vec![0; items.len()];
+        for (k, (_, refs)) in items.iter().enumerate() {
+            let deps = refs.iter()
+                .flat_map(|name| self.names.get(name).into_iter().flatten())
+                .filter_map(|index| position.get(index).cloned())
+                .filter(|&d| d != k)
+                .collect::<HashSet<_>>();
+            for d in deps {
+                dependents[d].push(k);
+                degree[k] += 1;
+            }
+        }
+        let mut que = (0..items.len()).filter(|&k| degree[k] == 0).map(Reverse).collect::<BinaryHeap<_>>();
+        let mut order = Vec::new();
+        while let Some(Reverse(k)) = que.pop() {
+            order.push(k);
+            for &next in dependents[k].iter() {
+                degree[next] -= 1;
+                if degree[next] == 0 {
+                    que.push(Reverse(next));
+                }
+            }
+        }
+        // items on a cycle keep the declaration order
+        order.extend((0..items.len()).filter(|&k| degree[k] > 0));
+        order
+    }
+    pub fn reachable(&self, roots: &[ItemRef]) -> HashSet<ItemIndex> {
+        let mut used = HashSet::new();
+        let mut reached = HashSet::new();
+        let mut que = roots.to_vec();
+        while let Some(name) = que.pop() {
+            if !used.insert(name.clone()) {
+                continue;
+            }
+            for index in self.names.get(&name).into_iter().flatten() {
+                if reached.insert(*index) {
+                    que.extend(self.deps.get(index).into_iter().flatten().cloned());
+                }
+            }
+        }
+        reached
+    }
+}
+
+#[test]
+fn reachable_test() {
+    let mut graph = DependencyGraph::new();
+    graph.regist_item(ItemRef::Func("main".to_string()), ItemIndex::Func(0));
+    graph.regist_item(ItemRef::Func("unused".to_string()), ItemIndex::Func(1));
+    graph.regist_item(ItemRef::Type("Point".to_string()), ItemIndex::Struct(0));
+    graph.regist_item(ItemRef::Trait("Show".to_string()), ItemIndex::Trait(0));
+    graph.regist_item(ItemRef::Impl(10), ItemIndex::Impl(0));
+    graph.regist_item(ItemRef::Impl(11), ItemIndex::Impl(1));
+    graph.regist_item(ItemRef::Impl(12), ItemIndex::Impl(2));
+    graph.add_dependencies(ItemIndex::Func(0), vec![ItemRef::Type("Point".to_string()), ItemRef::Trait("Show".to_string()), ItemRef::Impl(10)].into_iter().collect());
+    graph.add_dependencies(ItemIndex::Impl(0), vec![ItemRef::Impl(12)].into_iter().collect());
+    graph.add_dependencies(ItemIndex::Func(1), vec![ItemRef::Impl(11)].into_iter().collect());
+    let reached = graph.reachable(&[ItemRef::Func("main".to_string())]);
+    assert!(reached.contains(&ItemIndex::Struct(0)));
+    assert!(reached.contains(&ItemIndex::Impl(0)));
+    assert!(reached.contains(&ItemIndex::Impl(2)));
+    assert!(!reached.contains(&ItemIndex::Impl(1)));
+    assert!(!reached.contains(&ItemIndex::Func(1)));
+}
+
+#[test]
+fn dependency_order_test() {
+    let mut graph = DependencyGraph::new();
+    graph.regist_item(ItemRef::Type("A".to_string()), ItemIndex::Struct(0));
+    graph.regist_item(ItemRef::Type("B".to_string()), ItemIndex::Struct(1));
+    graph.regist_item(ItemRef::Type("C".to_string()), ItemIndex::Struct(2));
+    let a_refs = vec![ItemRef::Type("A".to_string()), ItemRef::Type("B".to_string())].into_iter().collect();
+    let b_refs = vec![ItemRef::Type("C".to_string()), ItemRef::Type("i64".to_string())].into_iter().collect();
+    let c_refs = HashSet::new();
+    let items = vec![(ItemIndex::Struct(0), &a_refs), (ItemIndex::Struct(1), &b_refs), (ItemIndex::Struct(2), &c_refs)];
+    assert_eq!(graph.dependency_order(&items), vec![2, 1, 0]);
+    let b_refs = vec![ItemRef::Type("A".to_string())].into_iter().collect();
+    let items = vec![(ItemIndex::Struct(0), &a_refs), (ItemIndex::Struct(1), &b_refs), (ItemIndex::Struct(2), &c_refs)];
+    assert_eq!(graph.dependency_order(&items), vec![2, 0, 1]);
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use nom::IResult;
+use nom::bytes::complete::*;
+use nom::character::complete::*;
+use nom::sequence::*;
+
+use crate::comment::{ multispace0, multispace1, parse_doc_comments, transpile_doc_comments };
+use crate::type_id::*;
+use crate::type_spec::*;
+use crate::structs::parse_generics_annotation;
+use crate::unify::*;
+use crate::trans::*;
+use crate::module::{ ModuleScope, Resolve, parse_visibility, transpile_in_namespace };
+
+#[derive(Debug, Clone)]
+pub struct TypeAliasDefinition {
+    pub alias_id: TypeId,
+    pub generics: Vec<TypeId>,
+    pub spec: TypeSpec,
+    pub doc: Vec<String>,
+}
+
+impl TypeAliasDefinition {
+    pub fn get_id(&self) -> TypeId {
+        self.alias_id.clone()
+    }
+    pub fn expand(&self, id: &TypeId, gens: Vec<Type>, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        let gens = if gens.len() == self.generics.len() {
+            gens
+        }
+        else if gens.is_empty() {
+            (0..self.generics.len()).map(|i| id.id.generate_type_variable("Generics", i, equs)).collect()
+        }
+        else {
+            return Err(id.id.get_span().error_message(&format!("type alias {} has {} generics but {} given", id.id.name, self.generics.len(), gens.len())))
+        };
+        let mp = self.generics.iter().cloned().zip(gens).collect::<HashMap<_, _>>();
+        let empty_gen_mp = GenericsTypeMap::empty();
+        let gen_mp = empty_gen_mp.next(mp);
+        self.spec.generics_to_type(&gen_mp, equs, trs)
+    }
+    pub fn check_definition(&self, trs: &TraitsInfo) -> Result<(), String> {
+        let mut trs = trs.into_scope();
+        for g in self.generics.iter() {
+            trs.regist_generics_type(g)?;
+        }
+        let mut equs = TypeEquations::new();
+        self.spec.generics_to_type(&GenericsTypeMap::empty(), &mut equs, &trs)?;
+        Ok(())
+    }
+    fn referenced_type_ids(&self) -> Vec<TypeId> {
+        let mut ids = Vec::new();
+        collect_type_ids(&self.spec, &mut ids);
+        ids
+    }
+}
+
+fn collect_type_ids(spec: &TypeSpec, ids: &mut Vec<TypeId>) {
+    match *spec {
+        TypeSpec::TypeSign(ref sign) => {
+            ids.push(sign.id.clone());
+            for g in sign.gens.iter() {
+                collect_type_ids(g, ids);
+            }
+        }
+        TypeSpec::Pointer(ref spec) | TypeSpec::MutPointer(ref spec) => collect_type_ids(spec, ids),
+        TypeSpec::Associated(ref spec, ref asso) => {
+            collect_type_ids(spec, ids);
+            for g in asso.trait_spec.generics.iter() {
+                collect_type_ids(g, ids);
+            }
+        }
+        TypeSpec::Tuple(ref specs) => {
+            for s in specs.iter() {
+                collect_type_ids(s, ids);
+            }
+        }
+        TypeSpec::Array(ref elem, ref len) => {
+            collect_type_ids(elem, ids);
+            collect_type_ids(len, ids);
+        }
+        TypeSpec::Const(_) => {}
+    }
+}
+
+pub fn sort_type_aliases(aliases: &[TypeAliasDefinition]) -> Result<Vec<&TypeAliasDefinition>, String> {
+    fn visit<'a>(alias: &'a TypeAliasDefinition, mp: &HashMap<TypeId, &'a TypeAliasDefinition>, state: &mut HashMap<TypeId, bool>, order: &mut Vec<&'a TypeAliasDefinition>) -> Result<(), String> {
+        match state.get(&alias.alias_id) {
+            Some(true) => return Ok(()),
+            Some(false) => return Err(alias.alias_id.id.get_span().error_message(&format!("type alias {} is cyclic", alias.alias_id.id.name))),
+            None => {}
+        }
+        state.insert(alias.alias_id.clone(), false);
+        for id in alias.referenced_type_ids() {
+            if let Some(next) = mp.get(&id) {
+                visit(next, mp, state, order)?;
+            }
+        }
+        state.insert(alias.alias_id.clone(), true);
+        order.push(alias);
+        Ok(())
+    }
+    let mp = aliases.iter().map(|alias| (alias.alias_id.clone(), alias)).collect::<HashMap<_, _>>();
+    let mut state = HashMap::new();
+    let mut order = Vec::new();
+    for alias in aliases.iter() {
+        visit(alias, &mp, &mut state, &mut order)?;
+    }
+    Ok(order)
+}
+
+impl Transpile for TypeAliasDefinition {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let template = if self.generics.is_empty() {
+            String::new()
+        }
+        else {
+            format!("template<{}> ", self.generics.iter().map(|g| g.transpile_template_param(ta)).collect::<Vec<_>>().join(", "))
+        };
+        transpile_in_namespace(&self.alias_id.transpile(ta), |name| format!("{}{}using {} = {};\n", transpile_doc_comments(&self.doc), template, name, self.spec.transpile(ta)))
+    }
+}
+
+impl Resolve for TypeAliasDefinition {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.alias_id.resolve(scope)?;
+        self.generics.resolve(scope)?;
+        self.spec.resolve(scope)
+    }
+}
+
+pub fn parse_type_alias_definition(s: &str) -> IResult<&str, TypeAliasDefinition> {
+    let (s, (doc, _, _, alias_id, _, generics, _, _, _, spec, _, _)) = tuple((parse_doc_comments, preceded(parse_visibility, tag("type")), multispace1, parse_type_id,
+            multispace0, parse_generics_annotation, multispace0, char('='), multispace0, parse_type_spec, multispace0, char(';')))(s)?;
+    Ok((s, TypeAliasDefinition { alias_id, generics, spec, doc }))
+}
+
+#[test]
+fn parse_type_alias_definition_test() {
+    log::debug!("{:?}", parse_type_alias_definition("type Grid<T> = Vec<Vec<T>>;"));
+    log::debug!("{:?}", parse_type_alias_definition("type Mint<const M: u64> = Modint<M>;"));
+}
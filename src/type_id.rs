@@ -1,17 +1,80 @@
 use nom::IResult;
+use nom::branch::*;
+use nom::bytes::complete::*;
+use nom::character::complete::*;
+use nom::sequence::*;
 
+use crate::comment::{ multispace0, multispace1 };
 use crate::identifier::{ Identifier, parse_identifier };
+use crate::type_spec::{ TypeSpec, GenericsTypeMap, parse_type_spec };
 use crate::unify::*;
+use crate::unary_expr::Variable;
+use crate::literal::int_fits;
+use crate::diagnostics::Span;
 use crate::trans::*;
+use crate::module::{ ItemKind, ModuleScope, Resolve, parse_item_identifier };
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub const PRIMITIVE_NUMBER_TYPES: [&str; 10] = ["i64", "u64", "i32", "u32", "i128", "u128", "usize", "u8", "f64", "char"];
+pub const INTEGER_TYPES: [&str; 8] = ["i64", "u64", "i32", "u32", "i128", "u128", "usize", "u8"];
+
+pub fn is_primitive_number(ty: &Type) -> bool {
+    match *ty {
+        Type::Generics(ref id, ref gens) => gens.is_empty() && PRIMITIVE_NUMBER_TYPES.contains(&id.id.name.as_str()),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TypeId {
     pub id: Identifier,
+    pub const_type: Option<Box<TypeSpec>>,
+}
+
+impl PartialEq for TypeId {
+    fn eq(&self, right: &Self) -> bool {
+        self.id == right.id
+    }
+}
+impl Eq for TypeId {}
+impl std::hash::Hash for TypeId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl std::fmt::Display for TypeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.id.name)
+    }
 }
 
 impl TypeId {
     pub fn from_str(s: &str) -> Self {
-        TypeId { id: Identifier::from_str(s) }
+        TypeId { id: Identifier::from_str(s), const_type: None }
+    }
+    pub fn is_const(&self) -> bool {
+        self.const_type.is_some()
+    }
+    pub fn transpile_template_param(&self, ta: &TypeAnnotation) -> String {
+        match self.const_type {
+            Some(ref ty) => format!("{} {}", ty.transpile(ta), self.transpile(ta)),
+            None => format!("class {}", self.transpile(ta)),
+        }
+    }
+    pub fn regist_const_variable(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> Result<(), String> {
+        if let Some(ref ty) = self.const_type {
+            let t = ty.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+            equs.regist_variable(Variable::from_identifier(self.id.clone()), t);
+        }
+        Ok(())
+    }
+    pub fn check_const_argument(&self, arg: &Type, span: &Span) -> Result<(), String> {
+        match (self.const_type.as_deref(), arg) {
+            (Some(TypeSpec::TypeSign(sign)), Type::Const(value)) if INTEGER_TYPES.contains(&sign.id.id.name.as_str()) && !int_fits(*value, &sign.id.id.name) => {
+                Err(span.error_message(&format!("const generic argument {} for {} does not fit in {}", value, self.id.name, sign.id.id.name)))
+            }
+            _ => Ok(()),
+        }
     }
     pub fn check_typeid(self, _trs: &TraitsInfo) -> TResult {
         unreachable!("TypeId::check_typeid");
@@ -26,10 +89,19 @@ impl GenType for TypeId {
 }
 
 impl Transpile for TypeId {
-    fn transpile(&self, _: &TypeAnnotation) -> String {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        ta.references.use_type(&self.id.into_string());
         match self.id.into_string().as_str() {
             "i64" => "std::int_fast64_t",
             "u64" => "std::uint_fast64_t",
+            "i32" => "std::int32_t",
+            "u32" => "std::uint32_t",
+            "i128" => "__int128",
+            "u128" => "unsigned __int128",
+            "usize" => "unsigned long long",
+            "u8" => "std::uint8_t",
+            "f64" => "double",
+            "char" => "char",
             "bool" => "bool",
             "void" => "void",
             s => s,
@@ -37,7 +109,29 @@ impl Transpile for TypeId {
     }
 }
 
+impl Resolve for TypeId {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        scope.resolve_item(ItemKind::Type, &mut self.id)?;
+        self.const_type.resolve(scope)
+    }
+}
+
 pub fn parse_type_id(s: &str) -> IResult<&str, TypeId> {
-    let (s, id) = parse_identifier(s)?;
-    Ok((s, TypeId { id }))
+    let (s, id) = parse_item_identifier(s)?;
+    Ok((s, TypeId { id, const_type: None }))
+}
+
+fn parse_const_generics_param(s: &str) -> IResult<&str, TypeId> {
+    let (s, (_, _, id, _, _, _, ty)) = tuple((tag("const"), multispace1, parse_identifier, multispace0, char(':'), multispace0, parse_type_spec))(s)?;
+    Ok((s, TypeId { id, const_type: Some(Box::new(ty)) }))
+}
+
+pub fn parse_generics_param(s: &str) -> IResult<&str, TypeId> {
+    alt((parse_const_generics_param, parse_type_id))(s)
+}
+
+#[test]
+fn parse_generics_param_test() {
+    assert!(parse_generics_param("const M: u64").unwrap().1.is_const());
+    assert!(!parse_generics_param("T").unwrap().1.is_const());
 }
@@ -8,11 +8,14 @@ use nom::multi::*;
 use nom::branch::*;
 use nom::bytes::complete::*;
 
+use crate::comment::multispace0;
 use crate::type_id::*;
+use crate::literal::int_fits;
 use crate::traits::*;
 
 use crate::unify::*;
 use crate::trans::*;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TypeSign {
@@ -66,6 +69,9 @@ impl TypeSign {
                 }
                 else  {
                     let gens = self.gens.iter().map(|gen| gen.generics_to_type(mp, equs, trs)).collect::<Result<_, _>>()?;
+                    if let Some(alias) = trs.get_type_alias(&self.id) {
+                        return alias.expand(&self.id, gens, equs, trs)
+                    }
                     trs.check_typeid_with_generics(
                         equs,
                         self.id.clone(),
@@ -85,9 +91,16 @@ impl TypeSign {
             }
         }
         else  {
+            let gens: Vec<_> = self.gens.iter().map(|gen| gen.generate_type_no_auto_generics(equs, trs)).collect::<Result<_, _>>()?;
+            if let Some(alias) = trs.get_type_alias(&self.id) {
+                if gens.len() != alias.generics.len() {
+                    return Err(self.id.id.get_span().error_message(&format!("type alias {} has {} generics but {} given", self.id.id.name, alias.generics.len(), gens.len())))
+                }
+                return alias.expand(&self.id, gens, &mut TypeEquations::new(), trs)
+            }
             trs.check_typeid_no_auto_generics(
                 self.id.clone(),
-                self.gens.iter().map(|gen| gen.generate_type_no_auto_generics(equs, trs)).collect::<Result<_, _>>()?,
+                gens,
                 trs
                 )
         }
@@ -99,7 +112,7 @@ impl TypeSign {
 }
 
 fn parse_generics_annotation(s: &str) -> IResult<&str, Vec<TypeSpec>> {
-    let (s, op) = opt(tuple((char('<'), multispace0, parse_type_spec, multispace0, many0(tuple((char(','), multispace0, parse_type_spec, multispace0))), opt(tuple((multispace0, char(',')))), multispace0, char('>'))))(s)?;
+    let (s, op) = opt(tuple((char('<'), multispace0, parse_generics_arg, multispace0, many0(tuple((char(','), multispace0, parse_generics_arg, multispace0))), opt(tuple((multispace0, char(',')))), multispace0, char('>'))))(s)?;
     let v = match op {
         None => Vec::new(),
         Some((_, _, ty, _, m0, _, _, _)) => {
@@ -139,6 +152,7 @@ pub fn parse_type_sign(s: &str) -> IResult<&str, TypeSign> {
 impl Transpile for TypeSign {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
         if let Some((ids, cppinline)) = ta.is_inline_struct(&self.id) {
+            ta.references.use_type(&self.id.id.into_string());
             let mp = ids.iter().cloned().zip(self.gens.iter().map(|g| g.transpile(ta))).collect::<HashMap<_, _>>();
             cppinline.transpile(ta, &mp)
         }
@@ -159,12 +173,24 @@ impl Transpile for TypeSign {
     }
 }
 
+impl Resolve for TypeSign {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        self.id.resolve(scope)?;
+        self.gens.resolve(scope)
+    }
+}
+
+const EMPTY_TUPLE_TYPE_ERROR: &str = "empty tuple type () is not supported, use void for no value";
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TypeSpec {
     TypeSign(TypeSign),
     Pointer(Box<TypeSpec>),
     MutPointer(Box<TypeSpec>),
     Associated(Box<TypeSpec>, AssociatedType),
+    Tuple(Vec<TypeSpec>),
+    Array(Box<TypeSpec>, Box<TypeSpec>),
+    Const(i128),
 }
 
 impl TypeSpec {
@@ -181,8 +207,19 @@ impl TypeSpec {
             }
             TypeSpec::Associated(ref spec, ref asso) => {
                 let trait_gen = asso.trait_spec.generate_trait_generics(equs, trs, mp)?;
-                Ok(Type::AssociatedType(Box::new(spec.as_ref().generics_to_type(mp, equs, trs)?), trait_gen, asso.type_id.clone()))
+                let ty = spec.as_ref().generics_to_type(mp, equs, trs)?;
+                let trait_gen = trs.find_member_trait(&trait_gen, &ty, &|tr| tr.asso_ids.contains(&asso.type_id))?;
+                Ok(Type::AssociatedType(Box::new(ty), trait_gen, asso.type_id.clone()))
+            }
+            TypeSpec::Tuple(ref specs) if specs.is_empty() => Err(EMPTY_TUPLE_TYPE_ERROR.to_string()),
+            TypeSpec::Tuple(ref specs) => {
+                Ok(Type::Tuple(specs.iter().map(|spec| spec.generics_to_type(mp, equs, trs)).collect::<Result<_, _>>()?))
+            }
+            TypeSpec::Array(ref elem, ref len) => {
+                len.check_const_range("u64")?;
+                Ok(Type::Array(Box::new(elem.generics_to_type(mp, equs, trs)?), Box::new(len.generics_to_type(mp, equs, trs)?)))
             }
+            TypeSpec::Const(value) => Ok(Type::Const(value)),
         }
     }
 
@@ -195,12 +232,23 @@ impl TypeSpec {
                 Ok(Type::Ref(Box::new(spec.as_ref().generate_type_no_auto_generics(equs, trs)?)))
             }
             TypeSpec::MutPointer(ref spec) => {
-                Ok(Type::Ref(Box::new(spec.as_ref().generate_type_no_auto_generics(equs, trs)?)))
+                Ok(Type::MutRef(Box::new(spec.as_ref().generate_type_no_auto_generics(equs, trs)?)))
             }
             TypeSpec::Associated(ref spec, ref asso) => {
                 let trait_gen = asso.trait_spec.generate_trait_generics_with_no_map(equs, trs)?;
-                Ok(Type::AssociatedType(Box::new(spec.as_ref().generate_type_no_auto_generics(equs, trs)?), trait_gen, asso.type_id.clone()))
+                let ty = spec.as_ref().generate_type_no_auto_generics(equs, trs)?;
+                let trait_gen = trs.find_member_trait(&trait_gen, &ty, &|tr| tr.asso_ids.contains(&asso.type_id))?;
+                Ok(Type::AssociatedType(Box::new(ty), trait_gen, asso.type_id.clone()))
+            }
+            TypeSpec::Tuple(ref specs) if specs.is_empty() => Err(EMPTY_TUPLE_TYPE_ERROR.to_string()),
+            TypeSpec::Tuple(ref specs) => {
+                Ok(Type::Tuple(specs.iter().map(|spec| spec.generate_type_no_auto_generics(equs, trs)).collect::<Result<_, _>>()?))
+            }
+            TypeSpec::Array(ref elem, ref len) => {
+                len.check_const_range("u64")?;
+                Ok(Type::Array(Box::new(elem.generate_type_no_auto_generics(equs, trs)?), Box::new(len.generate_type_no_auto_generics(equs, trs)?)))
             }
+            TypeSpec::Const(value) => Ok(Type::Const(value)),
         }
     }
 
@@ -214,6 +262,9 @@ impl TypeSpec {
                 spec.associated_type_depth()
             }
             TypeSpec::Associated(spec, _) => 1 + spec.associated_type_depth(),
+            TypeSpec::Tuple(specs) => specs.iter().map(|spec| spec.associated_type_depth()).max().unwrap_or(0),
+            TypeSpec::Array(elem, _) => elem.associated_type_depth(),
+            TypeSpec::Const(_) => 0,
         }
     }
 
@@ -227,6 +278,16 @@ impl TypeSpec {
                 Err(format!("cant get typeid from pointer {:?}", self))
             }
             TypeSpec::Associated(_, _) => Err(format!("cant get typeid from {:?}", self)),
+            TypeSpec::Tuple(_) => Err(format!("cant get typeid from tuple {:?}", self)),
+            TypeSpec::Array(_, _) => Err(format!("cant get typeid from array {:?}", self)),
+            TypeSpec::Const(_) => Err(format!("cant get typeid from const {:?}", self)),
+        }
+    }
+
+    pub fn check_const_range(&self, ty: &str) -> Result<(), String> {
+        match *self {
+            TypeSpec::Const(value) if !int_fits(value, ty) => Err(format!("const generic argument {} does not fit in {}", value, ty)),
+            _ => Ok(()),
         }
     }
 
@@ -238,6 +299,27 @@ impl TypeSpec {
     }
 }
 
+impl std::fmt::Display for TypeSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TypeSpec::TypeSign(ref sign) => {
+                write!(f, "{}", sign.id.id.into_string())?;
+                if !sign.gens.is_empty() {
+                    write!(f, "<{}>", sign.gens.iter().map(|g| g.to_string()).collect::<Vec<_>>().join(", "))?;
+                }
+                Ok(())
+            }
+            TypeSpec::Pointer(ref spec) => write!(f, "&{}", spec),
+            TypeSpec::MutPointer(ref spec) => write!(f, "&mut {}", spec),
+            TypeSpec::Associated(ref spec, ref asso) => write!(f, "{}#{}::{}", spec, asso.trait_spec, asso.type_id.id.into_string()),
+            TypeSpec::Tuple(ref specs) if specs.len() == 1 => write!(f, "({},)", specs[0]),
+            TypeSpec::Tuple(ref specs) => write!(f, "({})", specs.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(", ")),
+            TypeSpec::Array(ref elem, ref len) => write!(f, "[{}; {}]", elem, len),
+            TypeSpec::Const(value) => write!(f, "{}", value),
+        }
+    }
+}
+
 fn parse_type_spec_subseq(s: &str, prev: TypeSpec) -> IResult<&str, TypeSpec> {
     if let Ok((ss, (_, _, _, asso_ty))) = tuple((multispace0, char('#'), multispace0, parse_associated_type))(s) {
         parse_type_spec_subseq(ss, TypeSpec::Associated(Box::new(prev), asso_ty))
@@ -262,14 +344,52 @@ fn parse_type_spec_paren(s: &str) -> IResult<&str, TypeSpec> {
     Ok((s, spec))
 }
 
+fn parse_type_spec_empty_tuple(s: &str) -> IResult<&str, TypeSpec> {
+    let (s, _) = tuple((char('('), multispace0, char(')')))(s)?;
+    Ok((s, TypeSpec::Tuple(Vec::new())))
+}
+
+fn parse_type_spec_tuple(s: &str) -> IResult<&str, TypeSpec> {
+    if let Ok(res) = parse_type_spec_empty_tuple(s) {
+        return Ok(res)
+    }
+    let start = s;
+    let (s, (_, _, head, _, tails, comma, _, _)) = tuple((char('('), multispace0, parse_type_spec, multispace0,
+            many0(tuple((char(','), multispace0, parse_type_spec, multispace0))), opt(char(',')), multispace0, char(')')))(s)?;
+    if tails.is_empty() && comma.is_none() {
+        return Err(nom::Err::Error(nom::error::Error::new(start, nom::error::ErrorKind::Tag)))
+    }
+    let specs = std::iter::once(head).chain(tails.into_iter().map(|(_, _, spec, _)| spec)).collect();
+    Ok((s, TypeSpec::Tuple(specs)))
+}
+
+fn parse_type_spec_const(s: &str) -> IResult<&str, TypeSpec> {
+    let (s, value) = map_res(recognize(tuple((opt(char('-')), digit1))), |d: &str| d.parse::<i128>())(s)?;
+    Ok((s, TypeSpec::Const(value)))
+}
+
+pub fn parse_generics_arg(s: &str) -> IResult<&str, TypeSpec> {
+    alt((parse_type_spec_const, parse_type_spec))(s)
+}
+
+fn parse_type_spec_array(s: &str) -> IResult<&str, TypeSpec> {
+    let (s, (_, _, elem, _, _, _, len, _, _)) = tuple((char('['), multispace0, parse_type_spec, multispace0, char(';'), multispace0,
+            parse_generics_arg, multispace0, char(']')))(s)?;
+    Ok((s, TypeSpec::Array(Box::new(elem), Box::new(len))))
+}
+
 fn parse_type_spec_sign(s: &str) -> IResult<&str, TypeSpec> {
     let (s, sign) = parse_type_sign(s)?;
     let prev = TypeSpec::TypeSign(sign);
     parse_type_spec_subseq(s, prev)
 }
 
+pub fn parse_type_spec_compound(s: &str) -> IResult<&str, TypeSpec> {
+    alt((parse_type_spec_tuple, parse_type_spec_array))(s)
+}
+
 pub fn parse_type_spec(s: &str) -> IResult<&str, TypeSpec> {
-    alt((parse_type_spec_mutpointer, parse_type_spec_pointer, parse_type_spec_paren, parse_type_spec_sign))(s)
+    alt((parse_type_spec_mutpointer, parse_type_spec_pointer, parse_type_spec_tuple, parse_type_spec_paren, parse_type_spec_array, parse_type_spec_sign))(s)
 }
 
 /* 
@@ -295,17 +415,27 @@ impl Transpile for TypeSpec {
             TypeSpec::MutPointer(ref spec) => {
                 format!("{}*", spec.transpile(ta))
             }
+            TypeSpec::Tuple(ref specs) => {
+                format!("std::tuple<{}>", specs.iter().map(|spec| spec.transpile(ta)).collect::<Vec<_>>().join(", "))
+            }
+            TypeSpec::Array(ref elem, ref len) => {
+                format!("std::array<{}, {}>", elem.transpile(ta), len.transpile(ta))
+            }
+            TypeSpec::Const(value) => value.to_string(),
             TypeSpec::Associated(ref spec, AssociatedType { ref trait_spec, ref type_id } ) => {
-                match BINARY_OPERATOR_TRAITS.iter().find_map(|(tr_id, (_, ope))| {
+                match (BINARY_OPERATOR_TRAITS.iter().find_map(|(tr_id, (_, ope))| {
                     if *tr_id == trait_spec.trait_id.id.into_string() { Some(ope.to_string()) }
                     else { None }
-                }) {
-                    Some(ope) => {
+                }), find_unary_operator(&trait_spec.trait_id.id.into_string())) {
+                    (Some(ope), _) => {
                         let left = spec.transpile(ta);
                         let right = trait_spec.generics[0].transpile(ta);
                         format!("decltype(std::declval<{}>() {} std::declval<{}>())", left, ope, right)
                     }
-                    None => {
+                    (None, Some((_, ope))) => {
+                        format!("decltype({}std::declval<{}>())", ope, spec.transpile(ta))
+                    }
+                    (None, None) => {
                         let generics = std::iter::once(spec.transpile(ta)).chain(trait_spec.generics.iter().map(|g| g.transpile(ta)))
                             .collect::<Vec<_>>().join(", ");
                         format!("typename {}<{}>::{}", trait_spec.trait_id.transpile(ta), generics, type_id.transpile(ta))
@@ -318,6 +448,25 @@ impl Transpile for TypeSpec {
     }
 }
 
+impl Resolve for TypeSpec {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        match *self {
+            TypeSpec::TypeSign(ref mut sign) => sign.resolve(scope),
+            TypeSpec::Pointer(ref mut spec) | TypeSpec::MutPointer(ref mut spec) => spec.resolve(scope),
+            TypeSpec::Associated(ref mut spec, ref mut asso) => {
+                spec.resolve(scope)?;
+                asso.resolve(scope)
+            }
+            TypeSpec::Tuple(ref mut specs) => specs.resolve(scope),
+            TypeSpec::Array(ref mut elem, ref mut len) => {
+                elem.resolve(scope)?;
+                len.resolve(scope)
+            }
+            TypeSpec::Const(_) => Ok(()),
+        }
+    }
+}
+
 #[test]
 fn parse_type_spec_test() {
     log::debug!("{:?}", parse_type_spec("i64"));
@@ -325,6 +474,10 @@ fn parse_type_spec_test() {
     log::debug!("{:?}", parse_type_spec("Pair<Pair<i64, u64>, bool>"));
     log::debug!("{:?}", parse_type_spec("T#MyTrait::Output#MyTrait::Output"));
     log::debug!("{:?}", parse_type_spec("(i64)"));
+    log::debug!("{:?}", parse_type_spec("(i64, &Vec<u64>, (bool, char))"));
+    log::debug!("{:?}", parse_type_spec("[[i64; 2]; 2]"));
+    log::debug!("{:?}", parse_type_spec("Modint<998244353>"));
+    assert!(matches!(parse_type_spec("Offset<-3>"), Ok(("", TypeSpec::TypeSign(TypeSign { ref gens, .. }))) if gens == &vec![TypeSpec::Const(-3)]));
     log::debug!("{:?}", parse_type_spec("*i64"));
     log::debug!("{:?}", parse_type_spec("*(*i64)"));
     log::debug!("{:?}", parse_type_spec("*(T#MyTrait::Output)"));
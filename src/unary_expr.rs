@@ -6,8 +6,9 @@ use nom::branch::*;
 use nom::combinator::*;
 use nom::multi::*;
 
+use crate::comment::multispace0;
 use crate::literal::{ Literal, parse_literal };
-use crate::identifier::{ Identifier, parse_identifier };
+use crate::identifier::{ Identifier, Tag, parse_identifier };
 use crate::expression::{ Expression, parse_expression };
 use crate::subseq::*;
 use crate::block::{ parse_block, Block };
@@ -16,14 +17,20 @@ use crate::unify::*;
 use crate::trans::*;
 use crate::mut_checker::*;
 use crate::type_spec::*;
+use crate::type_id::TypeId;
 use crate::traits::*;
+use crate::diagnostics::Span;
+use crate::lowering::temporary_name;
+use crate::module::{ ItemKind, ModuleScope, Resolve, is_module, split_module_path, parse_item_identifier, parse_item_member };
 
 #[derive(Debug)]
 pub enum UnaryExpr {
     Variable(Variable),
     Literal(Literal),
     Parentheses(Parentheses),
-    Block(Block),
+    Tuple(TupleExpr),
+    Array(ArrayExpr),
+    Block(Block, Tag),
     Subseq(Box<UnaryExpr>, Subseq),
     StructInst(StructInstantiation),
     TraitMethod(TypeSpec, Option<TraitSpec>, Identifier),
@@ -35,17 +42,40 @@ impl GenType for UnaryExpr {
             UnaryExpr::Variable(ref v) => v.gen_type(equs, trs),
             UnaryExpr::Literal(ref l) => l.gen_type(equs, trs),
             UnaryExpr::Parentheses(ref p) => p.gen_type(equs, trs),
-            UnaryExpr::Block(ref b) => b.gen_type(equs, trs),
+            UnaryExpr::Tuple(ref t) => t.gen_type(equs, trs),
+            UnaryExpr::Array(ref a) => a.gen_type(equs, trs),
+            UnaryExpr::Block(ref b, ref tag) => {
+                let block_type = b.gen_type(equs, trs)?;
+                let alpha = tag.generate_type_variable("BlockType", 0, equs);
+                equs.add_equation(alpha.clone(), block_type);
+                Ok(alpha)
+            }
             UnaryExpr::Subseq(ref expr, ref s) => subseq_gen_type(expr.as_ref(), s, equs, trs),
             UnaryExpr::StructInst(ref inst) => inst.gen_type(equs, trs),
             UnaryExpr::TraitMethod(ref spec, ref trait_spec, ref mem_id) => {
-                let alpha = mem_id.generate_type_variable("FuncTypeInfo", 0, equs);
+                if trait_spec.is_none() {
+                    if let Some(enum_ty) = gen_type_for_variant(spec, mem_id, Vec::new(), equs, trs)? {
+                        return Ok(enum_ty)
+                    }
+                }
                 let trait_gen = match trait_spec {
                     Some(trait_spec) => {
-                        Some(trait_spec.generate_trait_generics(equs, trs, &GenericsTypeMap::empty())?)
+                        let trait_gen = trait_spec.generate_trait_generics(equs, trs, &GenericsTypeMap::empty())?;
+                        let ty = spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+                        let mem = AssociatedTypeIdentifier { id: mem_id.clone() };
+                        let method = TraitMethodIdentifier { id: mem_id.clone() };
+                        Some(trs.find_member_trait(&trait_gen, &ty, &|tr| tr.asso_consts.contains_key(&mem) || tr.required_methods.contains_key(&method))?)
                     }
                     None => None,
                 };
+                if let Some(ref trait_gen) = trait_gen {
+                    let ty = spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?;
+                    if let Some(const_ty) = trs.get_associated_const_type(equs, trait_gen, &ty, mem_id)? {
+                        equs.add_has_trait(ty, trait_gen.clone());
+                        return Ok(const_ty)
+                    }
+                }
+                let alpha = mem_id.generate_type_variable("FuncTypeInfo", 0, equs);
                 let right = Type::TraitMethod(Box::new(spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?), trait_gen.clone(), mem_id.clone());
                 equs.add_equation(alpha, right);
                 Ok(Type::TraitMethod(Box::new(spec.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?), trait_gen.clone(), mem_id.clone()))
@@ -60,13 +90,26 @@ impl Transpile for UnaryExpr {
             UnaryExpr::Variable(ref v) => v.transpile(ta),
             UnaryExpr::Literal(ref l) => l.transpile(ta),
             UnaryExpr::Parentheses(ref p) => p.transpile(ta),
-            UnaryExpr::Block(ref b) => format!("[&](){{ {} }}()", b.transpile(ta)),
+            UnaryExpr::Tuple(ref t) => t.transpile(ta),
+            UnaryExpr::Array(ref a) => a.transpile(ta),
+            UnaryExpr::Block(ref b, ref tag) => {
+                let ty = ta.annotation(tag.get_num(), "BlockType", 0);
+                if ty == Type::from_str("void") {
+                    format!("{{\n{}}}", b.transpile_for_void(ta))
+                }
+                else {
+                    ta.lowering.temporary(tag.get_num(), ty.transpile(ta), |target| format!("{{\n{}}}\n", b.transpile_to(ta, target)))
+                }
+            }
             UnaryExpr::Subseq(ref expr, ref s) => subseq_transpile(expr.as_ref(), s, ta),
             UnaryExpr::StructInst(ref inst) => inst.transpile(ta),
             UnaryExpr::TraitMethod(ref spec, Some(ref trait_spec), ref method_id) => {
                 format!("{}<{}>::{}", trait_spec.trait_id.transpile(ta), spec.transpile(ta), method_id.into_string())
             }
             UnaryExpr::TraitMethod(ref spec, _, ref method_id) => {
+                if let Some(enum_ty) = ta.try_annotation(method_id.get_tag_number(), "EnumType", 0) {
+                    return format!("{}::{}()", enum_ty.transpile(ta), method_id.into_string())
+                }
                 format!("{}::{}", spec.transpile(ta), method_id.into_string())
             }
         }
@@ -79,7 +122,9 @@ impl MutCheck for UnaryExpr {
             UnaryExpr::Variable(ref v) => v.mut_check(ta, vars),
             UnaryExpr::Literal(ref l) => l.mut_check(ta, vars),
             UnaryExpr::Parentheses(ref p) => p.mut_check(ta, vars),
-            UnaryExpr::Block(ref b) => b.mut_check(ta, vars),
+            UnaryExpr::Tuple(ref t) => t.mut_check(ta, vars),
+            UnaryExpr::Array(ref a) => a.mut_check(ta, vars),
+            UnaryExpr::Block(ref b, _) => b.mut_check(ta, vars),
             UnaryExpr::Subseq(ref expr, ref s) => subseq_mut_check(expr.as_ref(), s, ta, vars),
             UnaryExpr::StructInst(ref inst) => inst.mut_check(ta, vars),
             UnaryExpr::TraitMethod(ref spec, Some(ref trait_id), ref method_id) => {
@@ -92,12 +137,51 @@ impl MutCheck for UnaryExpr {
     }
 }
 
+impl Resolve for UnaryExpr {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        match *self {
+            UnaryExpr::Variable(ref mut v) => match split_module_path(&v.id.name) {
+                // `Type::method` is parsed as a path, it is a method when the prefix is not a module
+                (Some(prefix), name) if !is_module(prefix) => {
+                    let span = v.id.get_span();
+                    let mut sign = TypeSign { id: TypeId { id: Identifier::from_str(prefix).with_span(span), const_type: None }, gens: Vec::new() };
+                    sign.resolve(scope)?;
+                    let method = Identifier::from_str(name).with_span(span);
+                    *self = UnaryExpr::TraitMethod(TypeSpec::TypeSign(sign), None, method);
+                    Ok(())
+                }
+                _ => scope.resolve_item(ItemKind::Func, &mut v.id),
+            }
+            UnaryExpr::Literal(_) => Ok(()),
+            UnaryExpr::Parentheses(ref mut p) => p.expr.resolve(scope),
+            UnaryExpr::Tuple(ref mut t) => t.elems.resolve(scope),
+            UnaryExpr::Array(ArrayExpr::Elems(ref mut elems, _)) => elems.resolve(scope),
+            UnaryExpr::Array(ArrayExpr::Repeat(ref mut elem, ref mut len, _)) => {
+                elem.resolve(scope)?;
+                len.resolve(scope)
+            }
+            UnaryExpr::Block(ref mut b, _) => b.resolve(scope),
+            UnaryExpr::Subseq(ref mut expr, ref mut sub) => {
+                expr.resolve(scope)?;
+                sub.resolve(scope)
+            }
+            UnaryExpr::StructInst(ref mut inst) => inst.resolve(scope),
+            UnaryExpr::TraitMethod(ref mut spec, ref mut tr_spec, _) => {
+                spec.resolve(scope)?;
+                tr_spec.resolve(scope)
+            }
+        }
+    }
+}
+
 pub fn parse_unary_expr(s: &str) -> IResult<&str, UnaryExpr> {
     let (s, x) = alt((
             parse_unary_trait_method,
             parse_struct_instantiation,
             parse_literal,
+            parse_tuple_expr,
             parse_parentheses,
+            parse_array_expr,
             parse_bracket_block,
             parse_variable,
             ))(s)?;
@@ -140,7 +224,7 @@ impl MutCheck for Variable {
 }
 
 pub fn parse_variable(s: &str) -> IResult<&str, UnaryExpr> {
-    let(s, id) = parse_identifier(s)?;
+    let(s, id) = parse_item_identifier(s)?;
     Ok((s, UnaryExpr::Variable(Variable { id })))
 }
 
@@ -172,17 +256,158 @@ pub fn parse_parentheses(s: &str) -> IResult<&str, UnaryExpr> {
     Ok((s, UnaryExpr::Parentheses(Parentheses { expr })))
 }
 
+#[derive(Debug)]
+pub struct TupleExpr {
+    pub elems: Vec<Expression>,
+    tag: Tag,
+}
+
+impl GenType for TupleExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        if self.elems.is_empty() {
+            return Err(self.tag.get_span().error_message("empty tuple () is not supported, use void for no value"))
+        }
+        let elems = self.elems.iter().map(|elem| elem.gen_type(equs, trs)).collect::<Result<Vec<_>, String>>()?;
+        let alpha = self.tag.generate_type_variable("TupleType", 0, equs);
+        equs.add_equation(alpha.clone(), Type::Tuple(elems));
+        Ok(alpha)
+    }
+}
+
+impl Transpile for TupleExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        let elems = ta.lowering.operands(&self.elems, |elem| elem.transpile(ta)).join(", ");
+        format!("{}({})", ta.annotation(self.tag.get_num(), "TupleType", 0).transpile(ta), elems)
+    }
+}
+
+impl MutCheck for TupleExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        for elem in self.elems.iter() {
+            elem.mut_check(ta, vars)?;
+        }
+        Ok(MutResult::NotMut)
+    }
+}
+
+fn parse_empty_tuple_expr(s: &str) -> IResult<&str, UnaryExpr> {
+    let start = s;
+    let (s, _) = tuple((char('('), multispace0, char(')')))(s)?;
+    Ok((s, UnaryExpr::Tuple(TupleExpr { elems: Vec::new(), tag: Tag::with_span(Span::between(start, s)) })))
+}
+
+pub fn parse_tuple_expr(s: &str) -> IResult<&str, UnaryExpr> {
+    let start = s;
+    if let Ok(res) = parse_empty_tuple_expr(s) {
+        return Ok(res)
+    }
+    let (s, (_, _, head, _, tails, comma, _, _)) = tuple((char('('), multispace0, parse_expression, multispace0,
+            many0(tuple((char(','), multispace0, parse_expression, multispace0))), opt(char(',')), multispace0, char(')')))(s)?;
+    if tails.is_empty() && comma.is_none() {
+        return Err(nom::Err::Error(nom::error::Error::new(start, nom::error::ErrorKind::Tag)))
+    }
+    let elems = std::iter::once(head).chain(tails.into_iter().map(|(_, _, elem, _)| elem)).collect();
+    Ok((s, UnaryExpr::Tuple(TupleExpr { elems, tag: Tag::with_span(Span::between(start, s)) })))
+}
+
+#[derive(Debug)]
+pub enum ArrayExpr {
+    Elems(Vec<Expression>, Tag),
+    Repeat(Box<Expression>, TypeSpec, Tag),
+}
+
+impl GenType for ArrayExpr {
+    fn gen_type(&self, equs: &mut TypeEquations, trs: &TraitsInfo) -> TResult {
+        let (elems, len, tag) = match *self {
+            ArrayExpr::Elems(ref elems, ref tag) => (elems.iter().collect::<Vec<_>>(), Type::Const(elems.len() as i128), tag),
+            ArrayExpr::Repeat(ref elem, ref len, ref tag) => (vec![elem.as_ref()], len.generics_to_type(&GenericsTypeMap::empty(), equs, trs)?, tag),
+        };
+        let elem_type = tag.generate_type_variable("ArrayElemType", 0, equs);
+        for elem in elems {
+            let ty = elem.gen_type(equs, trs)?;
+            equs.add_equation(elem_type.clone(), ty);
+        }
+        let alpha = tag.generate_type_variable("ArrayType", 0, equs);
+        equs.add_equation(alpha.clone(), Type::Array(Box::new(elem_type), Box::new(len)));
+        Ok(alpha)
+    }
+}
+
+impl Transpile for ArrayExpr {
+    fn transpile(&self, ta: &TypeAnnotation) -> String {
+        match *self {
+            ArrayExpr::Elems(ref elems, ref tag) => {
+                let elems = ta.lowering.operands(elems, |elem| elem.transpile(ta)).join(", ");
+                format!("{}{{{}}}", ta.annotation(tag.get_num(), "ArrayType", 0).transpile(ta), elems)
+            }
+            ArrayExpr::Repeat(ref elem, _, ref tag) => {
+                let ty = ta.annotation(tag.get_num(), "ArrayType", 0).transpile(ta);
+                let elem = elem.transpile(ta);
+                let tmp = temporary_name(tag.get_num());
+                ta.lowering.hoist(format!("{}{}.fill({});\n", ta.lowering.declare(ty, &tmp), tmp, elem));
+                tmp
+            }
+        }
+    }
+}
+
+impl MutCheck for ArrayExpr {
+    fn mut_check(&self, ta: &TypeAnnotation, vars: &mut VariablesInfo) -> Result<MutResult, String> {
+        match *self {
+            ArrayExpr::Elems(ref elems, _) => {
+                for elem in elems.iter() {
+                    elem.mut_check(ta, vars)?;
+                }
+            }
+            ArrayExpr::Repeat(ref elem, _, _) => {
+                elem.mut_check(ta, vars)?;
+            }
+        }
+        Ok(MutResult::NotMut)
+    }
+}
+
+fn parse_array_repeat(s: &str) -> IResult<&str, UnaryExpr> {
+    let start = s;
+    let (s, (_, _, elem, _, _, _, len, _, _)) = tuple((char('['), multispace0, parse_expression, multispace0, char(';'), multispace0,
+            parse_generics_arg, multispace0, char(']')))(s)?;
+    Ok((s, UnaryExpr::Array(ArrayExpr::Repeat(Box::new(elem), len, Tag::with_span(Span::between(start, s))))))
+}
+
+fn parse_array_elems(s: &str) -> IResult<&str, UnaryExpr> {
+    let start = s;
+    let (s, (_, _, head, _, tails, _, _, _)) = tuple((char('['), multispace0, parse_expression, multispace0,
+            many0(tuple((char(','), multispace0, parse_expression, multispace0))), opt(char(',')), multispace0, char(']')))(s)?;
+    let elems = std::iter::once(head).chain(tails.into_iter().map(|(_, _, elem, _)| elem)).collect();
+    Ok((s, UnaryExpr::Array(ArrayExpr::Elems(elems, Tag::with_span(Span::between(start, s))))))
+}
+
+pub fn parse_array_expr(s: &str) -> IResult<&str, UnaryExpr> {
+    alt((parse_array_repeat, parse_array_elems))(s)
+}
+
 pub fn parse_bracket_block(s: &str) -> IResult<&str, UnaryExpr> {
+    let start = s;
     let(s, (_, _, block, _, _)) = tuple((char('{'), multispace0, parse_block, multispace0, char('}')))(s)?;
-    Ok((s, UnaryExpr::Block(block)))
+    Ok((s, UnaryExpr::Block(block, Tag::with_span(Span::between(start, s)))))
+}
+
+fn parse_trait_method_elem(s: &str) -> IResult<&str, (Option<TraitSpec>, Identifier)> {
+    alt((
+        map(tuple((opt(tuple((char('#'), multispace0, parse_trait_spec))), multispace0, tag("::"), multispace0, parse_identifier)),
+            |(op, _, _, _, id)| (op.map(|(_, _, tr_id)| tr_id), id)),
+        // `#path::Trait::method` is read greedily as one path, its last segment is the method
+        map(tuple((char('#'), multispace0, parse_item_member)),
+            |(_, _, (tr_id, id))| (Some(TraitSpec { trait_id: TraitId { id: tr_id }, generics: Vec::new() }), id)),
+    ))(s)
 }
 
 pub fn parse_unary_trait_method(ss: &str) -> IResult<&str, UnaryExpr> {
-    let (s, (typesign, _)) = tuple((parse_type_sign, multispace0))(ss)?;
-    let (s, elems) = many1(tuple((opt(tuple((char('#'), multispace0, parse_trait_spec))), multispace0, tag("::"), multispace0, parse_identifier, multispace0)))(s)?;
-    let mut elems = elems.into_iter().map(|(op, _, _, _, id, _)| (op.map(|(_, _, tr_id)| tr_id), id)).collect::<Vec<_>>();
+    let (s, (head, _)) = tuple((alt((map(parse_type_sign, TypeSpec::TypeSign), parse_type_spec_compound)), multispace0))(ss)?;
+    let (s, elems) = many1(terminated(parse_trait_method_elem, multispace0))(s)?;
+    let mut elems = elems.into_iter().collect::<Vec<_>>();
     let (tail_tr_op, tail_id) = elems.pop().unwrap();
-    let mut ty = TypeSpec::TypeSign(typesign);
+    let mut ty = head;
     for (op, ty_id) in elems.into_iter() {
         // TODO: remove unwrap
         ty = TypeSpec::Associated(Box::new(ty), AssociatedType { 
@@ -206,6 +431,18 @@ fn parse_parentheses_expr_test() {
     log::debug!("{:?}", parse_unary_expr("(1 + 2 + 3)"));
 }
 #[test]
+fn parse_array_expr_test() {
+    log::debug!("{:?}", parse_unary_expr("[0, 1, 0, -1]"));
+    log::debug!("{:?}", parse_unary_expr("[[0u64; 3]; 3][1][2]"));
+    log::debug!("{:?}", parse_unary_expr("(1, (2, 3)).1.0"));
+    assert!(matches!(parse_unary_expr("(x,)"), Ok(("", UnaryExpr::Tuple(TupleExpr { ref elems, .. }))) if elems.len() == 1));
+    assert!(matches!(parse_unary_expr("(x)"), Ok(("", UnaryExpr::Parentheses(_)))));
+    assert!(matches!(parse_unary_expr("[x; N]"), Ok(("", UnaryExpr::Array(ArrayExpr::Repeat(_, TypeSpec::TypeSign(_), _))))));
+}
+#[test]
 fn parse_trait_method_test() {
     log::debug!("{:?}", parse_unary_expr("i64#MyTrait.out"));
+    log::debug!("{:?}", parse_unary_expr("(i64, [u64; 3])#Default::default_value()"));
+    assert!(matches!(parse_unary_expr("Sum#module::sum::Monoid::op"), Ok(("", UnaryExpr::TraitMethod(_, Some(ref tr), ref id)))
+            if tr.trait_id.id.name == "module::sum::Monoid" && id.name == "op"));
 }
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::collections::{ HashSet, HashMap };
 
 use crate::identifier::*;
@@ -6,6 +7,8 @@ use crate::traits::*;
 use crate::type_spec::*;
 use crate::unify::*;
 use crate::type_id::*;
+use crate::type_alias::*;
+use crate::diagnostics::set_current_source;
 
 #[derive(Debug, Clone)]
 pub enum StructDefinitionInfo {
@@ -22,11 +25,51 @@ pub struct TraitsInfo<'a> {
     self_impls: HashMap<TypeId, Vec<SelectionCandidate>>,
     member_to_traits: HashMap<Identifier, HashSet<TraitId>>,
     member_to_self_impls: HashMap<Identifier, HashSet<TypeId>>,
+    fn_bounds: Vec<(Type, Type)>,
+    type_aliases: HashMap<TypeId, TypeAliasDefinition>,
     depth: usize,
     upper_info: Option<&'a TraitsInfo<'a>>,
+    selected_impls: RefCell<HashSet<usize>>,
 }
 
 
+const BUILTIN_ARRAY_IMPLS: [&str; 7] = [
+    "impl<T, const N: u64> Index for [T; N] { type Output = T; type Arg = u64; fn index(self: &Self, i: u64) -> &T $${(&(*$arg(self))[$arg(i)])}$$ }",
+    "impl<T, const N: u64> IndexMut for [T; N] { fn index_mut(self: &mut Self, i: u64) -> &mut T $${(&(*$arg(self))[$arg(i)])}$$ }",
+    "impl<T, const N: u64> Eq for [T; N] where T: Eq { fn eq(a: Self, b: Self) -> bool $${a == b}$$ }",
+    "impl<T, const N: u64> Ord for [T; N] where T: Ord { fn lt(a: Self, b: Self) -> bool $${a < b}$$ }",
+    "impl<T, const N: u64> Clone for [T; N] where T: Clone { fn clone(self: &Self) -> Self $${*$arg(self)}$$ }",
+    "impl<T, const N: u64> Default for [T; N] where T: Default { fn default_value() -> Self $${[]() { std::array<$ty(T), $ty(N)> a; a.fill(Default<$ty(T)>::default_value()); return a; }()}$$ }",
+    "impl<T, const N: u64> Hash for [T; N] where T: Hash { fn hash(self: &Self) -> u64 $${[](const auto& a) { std::uint_fast64_t h = 0; for (const auto& x : a) { h = h * 1000003 ^ Hash<$ty(T)>::hash(&x); } return h; }(*$arg(self))}$$ }",
+];
+
+const BUILTIN_TUPLE_IMPLS: [&str; 5] = ["Eq", "Ord", "Clone", "Default", "Hash"];
+
+const MAX_BUILTIN_TUPLE_LEN: usize = 8;
+
+fn builtin_tuple_method(trait_name: &str, params: &[String]) -> String {
+    match trait_name {
+        "Eq" => "fn eq(a: Self, b: Self) -> bool $${a == b}$$".to_string(),
+        "Ord" => "fn lt(a: Self, b: Self) -> bool $${a < b}$$".to_string(),
+        "Clone" => "fn clone(self: &Self) -> Self $${*$arg(self)}$$".to_string(),
+        "Hash" => "fn hash(self: &Self) -> u64 $${std::apply([](const auto&... x) { std::uint_fast64_t h = 0; ((h = h * 1000003 ^ Hash<std::decay_t<decltype(x)>>::hash(&x)), ...); return h; }, *$arg(self))}$$".to_string(),
+        _ => {
+            let values = params.iter().map(|p| format!("Default<$ty({})>::default_value()", p)).collect::<Vec<_>>().join(", ");
+            format!("fn default_value() -> Self $${{std::tuple<{}>({})}}$$", params.iter().map(|p| format!("$ty({})", p)).collect::<Vec<_>>().join(", "), values)
+        }
+    }
+}
+
+fn builtin_tuple_impls() -> Vec<String> {
+    (1..=MAX_BUILTIN_TUPLE_LEN).flat_map(|len| {
+        let params = (0..len).map(|i| format!("T{}", i)).collect::<Vec<_>>();
+        BUILTIN_TUPLE_IMPLS.iter().map(move |trait_name| {
+            let bounds = params.iter().map(|p| format!("{}: {}", p, trait_name)).collect::<Vec<_>>().join(", ");
+            format!("impl<{0}> {1} for ({0},) where {2} {{ {3} }}", params.join(", "), trait_name, bounds, builtin_tuple_method(trait_name, &params))
+        })
+    }).collect()
+}
+
 pub fn select_impls_by_priority<I: Iterator<Item=usize>>(priorities: I, len: usize) -> Option<usize> {
     let idx = priorities.into_iter().enumerate().max_by_key(|(_, x)| *x);
     match idx {
@@ -42,6 +85,14 @@ impl<'a> TraitsInfo<'a> {
             typeids: vec![
                 (TypeId::from_str("i64"), StructDefinitionInfo::Primitive),
                 (TypeId::from_str("u64"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("i32"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("u32"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("i128"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("u128"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("usize"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("u8"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("f64"), StructDefinitionInfo::Primitive),
+                (TypeId::from_str("char"), StructDefinitionInfo::Primitive),
                 (TypeId::from_str("bool"), StructDefinitionInfo::Primitive),
                 (TypeId::from_str("void"), StructDefinitionInfo::Primitive),
                 ].into_iter().collect(),
@@ -50,8 +101,11 @@ impl<'a> TraitsInfo<'a> {
             self_impls: HashMap::new(),
             member_to_traits: HashMap::new(),
             member_to_self_impls: HashMap::new(),
+            fn_bounds: Vec::new(),
+            type_aliases: HashMap::new(),
             depth: 0,
             upper_info: None,
+            selected_impls: RefCell::new(HashSet::new()),
         }
     }
     pub fn into_scope(&'a self) -> Self {
@@ -62,20 +116,47 @@ impl<'a> TraitsInfo<'a> {
             self_impls: HashMap::new(),
             member_to_traits: HashMap::new(),
             member_to_self_impls: HashMap::new(),
+            fn_bounds: Vec::new(),
+            type_aliases: HashMap::new(),
             depth: self.depth + 1,
             upper_info: Some(self),
+            selected_impls: RefCell::new(HashSet::new()),
         }
     }
     pub fn regist_structs_info(&mut self, st: &StructMemberDefinition) -> Result<(), String> {
         let id = st.get_id();
         match self.typeids.insert(id.clone(), StructDefinitionInfo::Def(st.clone())) {
-            Some(_) => Err(format!("duplicate struct definition: {:?}", id)),
+            Some(_) => Err(id.id.get_span().error_message(&format!("duplicate struct definition {}", id))),
             None => Ok(()),
         }
     }
+    pub fn regist_type_alias(&mut self, alias: &TypeAliasDefinition) -> Result<(), String> {
+        let id = alias.get_id();
+        if self.typeids.contains_key(&id) {
+            return Err(id.id.get_span().error_message(&format!("type alias {} conflicts with type {}", id.id.name, id.id.name)))
+        }
+        match self.type_aliases.insert(id.clone(), alias.clone()) {
+            Some(_) => Err(id.id.get_span().error_message(&format!("duplicate type alias definition {}", id))),
+            None => Ok(()),
+        }
+    }
+    pub fn get_type_alias(&self, id: &TypeId) -> Option<&TypeAliasDefinition> {
+        if self.typeids.contains_key(id) {
+            None
+        }
+        else if let Some(alias) = self.type_aliases.get(id) {
+            Some(alias)
+        }
+        else if let Some(trs) = self.upper_info {
+            trs.get_type_alias(id)
+        }
+        else {
+            None
+        }
+    }
     pub fn regist_generics_type(&mut self, generics_id: &TypeId) -> Result<(), String> {
         match self.typeids.insert(generics_id.clone(), StructDefinitionInfo::Generics) {
-            Some(_) => Err(format!("duplicate generics definition: {:?}", generics_id)),
+            Some(_) => Err(generics_id.id.get_span().error_message(&format!("duplicate generics definition {}", generics_id))),
             None => Ok(()),
         }
     }
@@ -99,7 +180,7 @@ impl<'a> TraitsInfo<'a> {
             trs.check_typeid_exist(id)
         }
         else {
-            Err(format!("not exist definition: {:?}", id))
+            Err(id.id.get_span().error_message(&format!("type {} is not defined", id.id.name)))
         }*/
     }
 
@@ -111,7 +192,7 @@ impl<'a> TraitsInfo<'a> {
             trs.get_struct_definition_info(id)
         }
         else {
-            Err(format!("type id {:?} is not found", id))
+            Err(id.id.get_span().error_message(&format!("type {} is not defined", id.id.name)))
         }
     }
     
@@ -122,6 +203,9 @@ impl<'a> TraitsInfo<'a> {
             match *def_info {
                 StructDefinitionInfo::Def(ref def) => {
                     if def.get_generics_len() == gens.len() {
+                        for (param, arg) in def.generics.iter().zip(gens.iter()) {
+                            param.check_const_argument(arg, &id.id.get_span())?;
+                        }
                         let gen_mp = GenericsTypeMap::empty();
                         let mp = def.generics.iter().cloned().zip(gens.iter().cloned()).collect::<HashMap<_, _>>();
                         let mp = gen_mp.next(mp);
@@ -137,7 +221,7 @@ impl<'a> TraitsInfo<'a> {
                         Ok(Type::Generics(id, gens))
                     }
                     else {
-                        Err(format!("type {:?} has {:?} generics but not match to {:?}", id, def.get_generics_len(), gens))
+                        Err(id.id.get_span().error_message(&format!("type {} has {} generics but {} given", id, def.get_generics_len(), gens.len())))
                     }
                 }
                 StructDefinitionInfo::Primitive => {
@@ -145,7 +229,7 @@ impl<'a> TraitsInfo<'a> {
                         Ok(Type::Generics(id, gens))
                     }
                     else {
-                        Err(format!("primitive type {:?} doesnt have generics argument", id))
+                        Err(id.id.get_span().error_message(&format!("type {} doesnt have generics argument", id)))
                     }
                 }
                 StructDefinitionInfo::Generics => {
@@ -153,7 +237,7 @@ impl<'a> TraitsInfo<'a> {
                         Ok(Type::Generics(id, gens))
                     }
                     else {
-                        Err(format!("primitive type {:?} doesnt have generics argument", id))
+                        Err(id.id.get_span().error_message(&format!("type {} doesnt have generics argument", id)))
                     }
                 }
             }
@@ -162,7 +246,7 @@ impl<'a> TraitsInfo<'a> {
             trs.check_typeid_with_generics(equs, id, gens, top_trs)
         }
         else {
-            Err(format!("not exist definition: {:?}", id))
+            Err(id.id.get_span().error_message(&format!("type {} is not defined", id.id.name)))
         }
     }
 
@@ -173,10 +257,13 @@ impl<'a> TraitsInfo<'a> {
             match *def_info {
                 StructDefinitionInfo::Def(ref def) => {
                     if def.get_generics_len() == gens.len() {
+                        for (param, arg) in def.generics.iter().zip(gens.iter()) {
+                            param.check_const_argument(arg, &id.id.get_span())?;
+                        }
                         Ok(Type::Generics(id, gens))
                     }
                     else {
-                        Err(format!("type {:?} has {:?} generics but not match to {:?}", id, def.get_generics_len(), gens))
+                        Err(id.id.get_span().error_message(&format!("type {} has {} generics but {} given", id, def.get_generics_len(), gens.len())))
                     }
                 }
                 StructDefinitionInfo::Primitive => {
@@ -184,7 +271,7 @@ impl<'a> TraitsInfo<'a> {
                         Ok(Type::Generics(id, gens))
                     }
                     else {
-                        Err(format!("primitive type {:?} doesnt have generics argument", id))
+                        Err(id.id.get_span().error_message(&format!("type {} doesnt have generics argument", id)))
                     }
                 }
                 StructDefinitionInfo::Generics => {
@@ -192,7 +279,7 @@ impl<'a> TraitsInfo<'a> {
                         Ok(Type::Generics(id, gens))
                     }
                     else {
-                        Err(format!("primitive type {:?} doesnt have generics argument", id))
+                        Err(id.id.get_span().error_message(&format!("type {} doesnt have generics argument", id)))
                     }
                 }
             }
@@ -201,7 +288,7 @@ impl<'a> TraitsInfo<'a> {
             trs.check_typeid_no_auto_generics(id, gens, top_trs)
         }
         else {
-            Err(format!("not exist definition: {:?}", id))
+            Err(id.id.get_span().error_message(&format!("type {} is not defined", id.id.name)))
         }
     }
 
@@ -212,7 +299,7 @@ impl<'a> TraitsInfo<'a> {
                     trs.check_trait(tr)
                 }
                 else {
-                    Err(format!("trait {:?} not found", tr))
+                    Err(tr.trait_id.id.get_span().error_message(&format!("trait {} is not defined", tr.trait_id.id.name)))
                 }
             }
             Some(tr_def) => {
@@ -220,7 +307,7 @@ impl<'a> TraitsInfo<'a> {
                     Ok(())
                 }
                 else {
-                    Err(format!("Generics of {:?} is not match to trait {:?}", tr, tr.trait_id))
+                    Err(tr.trait_id.id.get_span().error_message(&format!("trait {} needs {} generics arguments, but {} given", tr.trait_id.id.name, tr_def.generics.len(), tr.generics.len())))
                 }
             }
         }
@@ -228,6 +315,9 @@ impl<'a> TraitsInfo<'a> {
 
     pub fn regist_trait(&mut self, tr: &TraitDefinition) -> Result<(), String> {
         let (trait_id, trait_def) = tr.get_trait_id_pair();
+        if find_operator(trait_id.id.into_string().as_str()).is_some() && !trait_def.default_methods.is_empty() {
+            return Err(trait_id.id.get_span().error_message(&format!("operator trait {} cant have default methods", trait_id.id.name)))
+        }
         for (id, _) in trait_def.required_methods.iter() {
             match self.member_to_traits.get_mut(&id.id) {
                 Some(st) => {
@@ -239,7 +329,24 @@ impl<'a> TraitsInfo<'a> {
             }
         }
         self.traits.insert(trait_id.clone(), trait_def)
-            .map_or(Ok(()), |_| Err(format!("trait {:?} is already defined", trait_id)))
+            .map_or(Ok(()), |_| Err(trait_id.id.get_span().error_message(&format!("trait {} is already defined", trait_id))))
+    }
+
+    // impls are recorded at the outermost scope by the tag of their trait identifier
+    fn select_candidate(&self, cand: &SelectionCandidate) {
+        match self.upper_info {
+            Some(trs) => trs.select_candidate(cand),
+            None => if let SelectionCandidate::ImplCandidate(ref cand) = *cand {
+                self.selected_impls.borrow_mut().insert(cand.trait_spec.get_tag().get_num());
+            }
+        }
+    }
+
+    pub fn take_selected_impls(&self) -> HashSet<usize> {
+        match self.upper_info {
+            Some(trs) => trs.take_selected_impls(),
+            None => std::mem::take(&mut *self.selected_impls.borrow_mut()),
+        }
     }
 
     fn regist_selection_candidate(&mut self, trait_id: &TraitId, cand: SelectionCandidate) {
@@ -283,7 +390,7 @@ impl<'a> TraitsInfo<'a> {
         }
     }
 
-    fn get_traitinfo(&self, trait_id: &TraitId) -> Option<&TraitDefinitionInfo> {
+    pub fn get_traitinfo(&self, trait_id: &TraitId) -> Option<&TraitDefinitionInfo> {
         if let Some(info) = self.traits.get(trait_id) {
             Some(info)
         }
@@ -294,6 +401,65 @@ impl<'a> TraitsInfo<'a> {
             None
         }
     }
+    pub fn find_member_trait(&self, trait_gen: &TraitGenerics, self_ty: &Type, has_member: &dyn Fn(&TraitDefinitionInfo) -> bool) -> Result<TraitGenerics, String> {
+        let mut visited = HashSet::new();
+        Ok(self.search_member_trait(trait_gen, self_ty, has_member, &mut visited)?.unwrap_or_else(|| trait_gen.clone()))
+    }
+    fn search_member_trait(&self, trait_gen: &TraitGenerics, self_ty: &Type, has_member: &dyn Fn(&TraitDefinitionInfo) -> bool, visited: &mut HashSet<TraitId>) -> Result<Option<TraitGenerics>, String> {
+        let tr = match self.get_traitinfo(&trait_gen.trait_id) {
+            Some(tr) => tr,
+            None => return Ok(None),
+        };
+        if has_member(tr) {
+            return Ok(Some(trait_gen.clone()))
+        }
+        if !visited.insert(trait_gen.trait_id.clone()) {
+            return Ok(None)
+        }
+        let gen_mp = tr.generics.iter().cloned().zip(trait_gen.generics.iter().cloned()).collect::<HashMap<_, _>>();
+        let empty_gen_mp = GenericsTypeMap::empty();
+        let gen_mp = empty_gen_mp.next(gen_mp);
+        let mut equs = TypeEquations::new();
+        equs.set_self_type(Some(self_ty.clone()));
+        for super_spec in tr.where_sec.self_supertraits() {
+            let super_gen = super_spec.generate_trait_generics(&mut equs, self, &gen_mp)?;
+            if let Some(found) = self.search_member_trait(&super_gen, self_ty, has_member, visited)? {
+                return Ok(Some(found))
+            }
+        }
+        Ok(None)
+    }
+    pub fn get_associated_const_type(&self, equs: &mut TypeEquations, trait_gen: &TraitGenerics, ty: &Type, const_id: &Identifier) -> Result<Option<Type>, String> {
+        let tr = match self.get_traitinfo(&trait_gen.trait_id) {
+            Some(tr) => tr,
+            None => return Ok(None),
+        };
+        match tr.asso_consts.get(&AssociatedTypeIdentifier { id: const_id.clone() }) {
+            Some(spec) => {
+                let gen_mp = tr.generics.iter().cloned().zip(trait_gen.generics.iter().cloned()).collect::<HashMap<_, _>>();
+                let empty_gen_mp = GenericsTypeMap::empty();
+                let gen_mp = empty_gen_mp.next(gen_mp);
+                let before = equs.set_self_type(Some(ty.clone()));
+                let res = spec.generics_to_type(&gen_mp, equs, self);
+                equs.set_self_type(before);
+                res.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+    pub fn builtin_impls(&self) -> Result<Vec<ImplDefinition>, String> {
+        let before = set_current_source(None);
+        let impls = BUILTIN_ARRAY_IMPLS.iter().map(|src| src.to_string()).chain(builtin_tuple_impls())
+            .map(|src| match parse_impl_definition(&src) {
+                Ok(("", ti)) => Ok(ti),
+                Ok((rest, _)) => Err(format!("failed to build builtin impl, unexpected {:?}\n{}", rest, src)),
+                Err(err) => Err(format!("failed to build builtin impl, {}\n{}", err, src)),
+            })
+            .filter(|ti| ti.as_ref().map_or(true, |ti| self.get_traitinfo(&ti.get_trait_id()).is_some()))
+            .collect();
+        set_current_source(before);
+        impls
+    }
     pub fn preregist_impl_candidate(&mut self, ti: &ImplDefinition) {
         let (trait_id, cand) = ti.get_impl_trait_pair();
         self.regist_selection_candidate(&trait_id, cand);
@@ -306,12 +472,12 @@ impl<'a> TraitsInfo<'a> {
             gen_trs.regist_generics_type(id)?;
         }
         let impl_ty = ti.impl_ty.generics_to_type(&GenericsTypeMap::empty(), equs, &gen_trs)?;
-        let before_self_type = equs.set_self_type(Some(impl_ty));
+        let before_self_type = equs.set_self_type(Some(impl_ty.clone()));
         ti.where_sec.regist_candidate(equs, &mut gen_trs)?;
         self.check_trait(&ti.trait_spec)?;
 
         match self.get_traitinfo(&trait_id) {
-            None => Err(format!("trait {:?} is not defined", trait_id)),
+            None => Err(trait_id.id.get_span().error_message(&format!("trait {} is not defined", trait_id))),
             Some(tr) => {
                 let empty_gen_map = GenericsTypeMap::empty();
                 let tr_gen_map = tr.generics.iter().zip(ti.trait_spec.generics.iter())
@@ -319,17 +485,24 @@ impl<'a> TraitsInfo<'a> {
                     .collect::<Result<HashMap<_, _>, String>>()?;
                 let tr_gen_map = empty_gen_map.next(tr_gen_map);
                 log::debug!("{:?}", tr_gen_map);
+                for sup in tr.where_sec.self_supertraits() {
+                    let sup_gen = sup.generate_trait_generics(equs, &gen_trs, &tr_gen_map)?;
+                    if let Err(0) = gen_trs.match_to_impls_for_type(&sup_gen, &impl_ty) {
+                        Err(ti.trait_spec.trait_id.id.get_span().error_message(&format!("trait {} requires supertrait {}, but it is not implemented for {}", tr.trait_id.id.name, sup.trait_id.id.name, ti.impl_ty)))?
+                    }
+                }
                 {
                     tr.where_sec.regist_equations(&GenericsTypeMap::empty(), equs, &gen_trs)?;
                     match equs.unify(&gen_trs) {
                         Ok(_) => Ok(()),
-                        Err(UnifyErr::Deficiency(s)) => Err(format!("trait {:?} where section error, {:?}", tr.trait_id, s)),
-                        Err(UnifyErr::Contradiction(s)) => Err(format!("trait {:?} where section error, {:?}", tr.trait_id, s)),
+                        Err(UnifyErr::Deficiency(s)) => Err(format!("trait {} where section error, {}", tr.trait_id, s)),
+                        Err(UnifyErr::Contradiction(s)) => Err(format!("trait {} where section error, {}", tr.trait_id, s)),
                     }?;
                 }
                 for (id, info) in tr.required_methods.iter() {
                     match ti.require_methods.get(id) {
-                        None => Err(format!("method {:?}::{:?} is not defined for {:?}", tr, id, ti.impl_ty))?,
+                        None if tr.default_methods.contains(id) => {}
+                        None => Err(ti.trait_spec.trait_id.id.get_span().error_message(&format!("method {}::{} is not defined for {}", tr.trait_id, id.id.name, ti.impl_ty)))?,
                         Some(impl_method) => {
                             {
                                 equs.clear_equations();
@@ -338,6 +511,21 @@ impl<'a> TraitsInfo<'a> {
                         }
                     }
                 }
+                for (id, tr_spec) in tr.asso_consts.iter() {
+                    match ti.asso_consts.get_key_value(id) {
+                        None => Err(ti.trait_spec.trait_id.id.get_span().error_message(&format!("const {}::{} is not defined for {}", tr.trait_id, id.id.name, ti.impl_ty)))?,
+                        Some((id, (impl_spec, _))) => {
+                            equs.clear_equations();
+                            let tr_ty = tr_spec.generics_to_type(&tr_gen_map, equs, &gen_trs)?;
+                            let impl_ty = impl_spec.generics_to_type(&empty_gen_map, equs, &gen_trs)?;
+                            equs.add_equation(tr_ty, impl_ty);
+                            equs.unify(&gen_trs).map_err(|err| id.id.get_span().error_message(&format!("const {} type is not matched, {}", id.id.name, err.to_string())))?;
+                        }
+                    }
+                }
+                if let Some(id) = ti.asso_consts.keys().find(|id| !tr.asso_consts.contains_key(id)) {
+                    Err(id.id.get_span().error_message(&format!("const {} is not a member of trait {}", id.id.name, tr.trait_id.id.name)))?
+                }
                 equs.set_self_type(before_self_type);
                 Ok(())
             }
@@ -346,7 +534,7 @@ impl<'a> TraitsInfo<'a> {
     pub fn regist_param_candidate(&mut self, ty: Type, trait_gen: &TraitGenerics, mut asso_mp: HashMap<AssociatedTypeIdentifier, Type>) -> Result<(), String> {
         log::debug!("param {:?}, {:?}", ty, trait_gen);
         match self.get_traitinfo(&trait_gen.trait_id).cloned() {
-            None => Err(format!("trait {:?} is not defined", trait_gen)),
+            None => Err(trait_gen.trait_id.id.get_span().error_message(&format!("trait {} is not defined", trait_gen))),
             Some(tr_def) => {
                 let mut equs = TypeEquations::new();
                 equs.set_self_type(Some(ty.clone()));
@@ -377,6 +565,17 @@ impl<'a> TraitsInfo<'a> {
         }
     }
 
+    pub fn regist_fn_bound(&mut self, ty: Type, func_ty: Type) {
+        self.fn_bounds.push((ty, func_ty));
+    }
+
+    pub fn search_fn_bound(&self, ty: &Type) -> Option<Type> {
+        match self.fn_bounds.iter().find(|(bound_ty, _)| bound_ty == ty) {
+            Some((_, func_ty)) => Some(func_ty.clone()),
+            None => self.upper_info.and_then(|trs| trs.search_fn_bound(ty)),
+        }
+    }
+
     fn match_to_impls(&self, trait_gen: &TraitGenerics, ty: &Type, top_trs: &Self) -> Vec<(SubstsMap, &SelectionCandidate, usize)> {
         let mut ans = Vec::new();
         if let Some(impls) = self.impls.get(&trait_gen.trait_id) {
@@ -405,6 +604,7 @@ impl<'a> TraitsInfo<'a> {
         match idx {
             Some(i) => {
                 let (substs, cand, _) = cands.swap_remove(i);
+                self.select_candidate(cand);
                 Ok((substs, cand))
             }
             None => {
@@ -481,6 +681,11 @@ impl<'a> TraitsInfo<'a> {
     pub fn regist_for_call_equtions(&self, equs: &mut TypeEquations, call_eq: &CallEquation) -> Result<Type, Vec<&SelectionCandidate>> {
         let mut st = HashSet::new();
         self.search_traits_for_member(&call_eq.func_id, &mut st);
+        if let Some(ref trait_gen) = call_eq.trait_gen {
+            if st.contains(&trait_gen.trait_id) {
+                st.retain(|t| *t == trait_gen.trait_id);
+            }
+        }
         let mut unify_res = Vec::new();
         for t in st.into_iter() {
             let vs = self.generate_call_equations_for_trait(&t, call_eq, self);
@@ -519,7 +724,8 @@ impl<'a> TraitsInfo<'a> {
             }
         }
         if unify_res.len() == 1 {
-            let (gen_equ, _) = unify_res.pop().unwrap();
+            let (gen_equ, cand) = unify_res.pop().unwrap();
+            self.select_candidate(cand);
             let ret_ty = gen_equ.try_get_substs(TypeVariable::Counter(call_eq.tag.get_num(), "ReturnType", 0));
 
             //log::debug!("take over by call >> ");
@@ -580,7 +786,7 @@ impl<'a> TraitsInfo<'a> {
             trs.search_typeid(&id)
         }
         else {
-            Err(format!("not exist definition: {:?}", id))
+            Err(id.id.get_span().error_message(&format!("type {} is not defined", id.id.name)))
         }
     }
 }
@@ -8,6 +8,7 @@ use crate::unify::*;
 use crate::type_spec::*;
 use crate::type_id::*;
 use crate::identifier::*;
+use crate::diagnostics::Span;
 #[derive(Debug, Clone, PartialEq, Eq)] pub struct CppInlineInfo {
     pub elems: Vec<CppInlineInfoElem>,
     pub tag: Tag,
@@ -80,12 +81,29 @@ impl TraitGenerics {
     }
 }
 
+impl std::fmt::Display for TraitGenerics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.trait_id)?;
+        if !self.generics.is_empty() {
+            write!(f, "<{}>", types_to_string(&self.generics))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn types_to_string(tys: &[Type]) -> String {
+    tys.iter().map(|ty| ty.to_string()).collect::<Vec<_>>().join(", ")
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     SolvedAssociatedType(Box<Type>, TraitGenerics, AssociatedTypeIdentifier),
     Func(Vec<Type>, Box<Type>, FuncTypeInfo),
     TypeVariable(TypeVariable),
     Generics(TypeId, Vec<Type>),
+    Tuple(Vec<Type>),
+    Array(Box<Type>, Box<Type>),
+    Const(i128),
     AssociatedType(Box<Type>, TraitGenerics, AssociatedTypeIdentifier),
     TraitMethod(Box<Type>, Option<TraitGenerics>, Identifier),
     Member(Box<Type>, Identifier),
@@ -94,6 +112,7 @@ pub enum Type {
     MutRef(Box<Type>),
     Deref(Box<Type>),
     AutoRef(Box<Type>, AutoRefTag),
+    Never,
     End,
 }
 
@@ -102,10 +121,17 @@ impl Type {
         Type::Generics(TypeId::from_str(s), vec![])
     }
 
+    pub fn is_void(&self) -> bool {
+        *self == Type::Never || *self == Type::from_str("void")
+    }
+
     fn is_solved_type(&self) -> bool {
         match self {
             Type::SolvedAssociatedType(_, _, _) => true,
             Type::Generics(_, gens) => gens.iter().map(|gen| gen.is_solved_type()).all(|t| t),
+            Type::Tuple(elems) => elems.iter().all(|elem| elem.is_solved_type()),
+            Type::Array(elem, len) => elem.is_solved_type() && len.is_solved_type(),
+            Type::Const(_) => true,
             Type::Ref(ref ty) => ty.as_ref().is_solved_type(),
             Type::MutRef(ref ty) => ty.as_ref().is_solved_type(),
             _ => false,
@@ -127,6 +153,9 @@ impl Type {
                 }
                 false
             }
+            Type::Tuple(ref elems) => elems.iter().any(|elem| elem.occurs(t)),
+            Type::Array(ref elem, ref len) => elem.occurs(t) || len.occurs(t),
+            Type::Const(_) => false,
             Type::AssociatedType(ref ty, ref tr, _) => {
                 ty.as_ref().occurs(t) || tr.occurs(t)
             }
@@ -154,7 +183,7 @@ impl Type {
             Type::AutoRef(ref ty, _) => {
                 ty.as_ref().occurs(t)
             }
-            Type::End => false,
+            Type::Never | Type::End => false,
         }
     }
 
@@ -171,6 +200,13 @@ impl Type {
             Type::Generics(ref _ty, ref mut gens) => {
                 gens.iter_mut().map(|gen| gen.subst(theta)).fold(SolveChange::Not, |a, b| a & b)
             }
+            Type::Tuple(ref mut elems) => {
+                elems.iter_mut().map(|elem| elem.subst(theta)).fold(SolveChange::Not, |a, b| a & b)
+            }
+            Type::Array(ref mut elem, ref mut len) => {
+                elem.as_mut().subst(theta) & len.as_mut().subst(theta)
+            }
+            Type::Const(_) => { SolveChange::Not },
             Type::SolvedAssociatedType(_, _, _) => { SolveChange::Not },
             Type::AssociatedType(ref mut ty, ref mut tr, _) => {
                 ty.as_mut().subst(theta) & tr.subst(theta)
@@ -196,7 +232,7 @@ impl Type {
             Type::AutoRef(ref mut ty, _) => {
                 ty.as_mut().subst(theta)
             }
-            Type::End => { SolveChange::Not },
+            Type::Never | Type::End => { SolveChange::Not },
             // TypeVariable
             ref mut ty => {
                 let TypeSubst { tv: y, t: into_t } = theta;
@@ -216,13 +252,17 @@ impl Transpile for Type {
     fn transpile(&self, ta: &TypeAnnotation) -> String {
         match *self {
             Type::SolvedAssociatedType(ref ty, ref tr, ref asso_id) => {
-                match find_binary_operator(tr.trait_id.id.into_string().as_str()) {
-                    Some((_, ope)) => {
+                let tr_name = tr.trait_id.id.into_string();
+                match (find_binary_operator(&tr_name), find_unary_operator(&tr_name)) {
+                    (Some((_, ope)), _) => {
                         let left = ty.transpile(ta);
                         let right = tr.generics[0].transpile(ta);
                         format!("decltype(std::declval<{}>() {} std::declval<{}>())", left, ope, right)
                     }
-                    None => {
+                    (None, Some((_, ope))) => {
+                        format!("decltype({}std::declval<{}>())", ope, ty.transpile(ta))
+                    }
+                    (None, None) => {
 
                         let generics = std::iter::once(ty.transpile(ta)).chain(tr.generics.iter().map(|g| g.transpile(ta))).collect::<Vec<_>>().join(", ");
                         format!("typename {}<{}>::{}", tr.trait_id.transpile(ta), generics, asso_id.transpile(ta))
@@ -230,10 +270,14 @@ impl Transpile for Type {
                 }
             }
             Type::Ref(ref ty) => {
+                format!("const {}*", ty.as_ref().transpile(ta))
+            }
+            Type::MutRef(ref ty) => {
                 format!("{}*", ty.as_ref().transpile(ta))
             }
             Type::Generics(ref ty_id, ref gens) => {
                 if let Some((ids, cppinline)) = ta.is_inline_struct(ty_id) {
+                    ta.references.use_type(&ty_id.id.into_string());
                     let mp = ids.iter().cloned().zip(gens.iter().map(|g| g.transpile(ta))).collect::<HashMap<_, _>>();
                     cppinline.transpile(ta, &mp)
                 }
@@ -247,16 +291,61 @@ impl Transpile for Type {
                     format!("{}{}", ty_id.transpile(ta), gens_trans)
                 }
             }
+            Type::Func(ref args, ref ret, _) => {
+                let args = args.iter().map(|arg| arg.transpile(ta)).collect::<Vec<_>>().join(", ");
+                format!("std::function<{}({})>", ret.as_ref().transpile(ta), args)
+            }
+            Type::Tuple(ref elems) => {
+                format!("std::tuple<{}>", elems.iter().map(|elem| elem.transpile(ta)).collect::<Vec<_>>().join(", "))
+            }
+            Type::Array(ref elem, ref len) => {
+                format!("std::array<{}, {}>", elem.transpile(ta), len.transpile(ta))
+            }
+            Type::Const(value) => value.to_string(),
+            Type::Never => "void".to_string(),
             ref ty => unreachable!(format!("it is not Type {:?}", ty)),
         }
     }
 }
 
+fn tuple_member_type(elems: &[Type], mem_id: &Identifier) -> Result<Type, UnifyErr> {
+    match mem_id.into_string().parse::<usize>() {
+        Ok(i) if i < elems.len() => Ok(elems[i].clone()),
+        _ => Err(UnifyErr::Contradiction(format!("tuple ({}) has no member {}", types_to_string(elems), mem_id.name))),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TypeVariable {
     Counter(usize, &'static str, usize),
 }
 
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Type::SolvedAssociatedType(ref ty, ref tr, ref asso) | Type::AssociatedType(ref ty, ref tr, ref asso) => write!(f, "{}#{}::{}", ty, tr, asso.id.name),
+            Type::Func(ref args, ref ret, _) => write!(f, "fn({}) -> {}", types_to_string(args), ret),
+            Type::TypeVariable(_) => write!(f, "_"),
+            Type::Generics(ref id, ref gens) if gens.is_empty() => write!(f, "{}", id),
+            Type::Generics(ref id, ref gens) => write!(f, "{}<{}>", id, types_to_string(gens)),
+            Type::Tuple(ref elems) if elems.len() == 1 => write!(f, "({},)", elems[0]),
+            Type::Tuple(ref elems) => write!(f, "({})", types_to_string(elems)),
+            Type::Array(ref elem, ref len) => write!(f, "[{}; {}]", elem, len),
+            Type::Const(value) => write!(f, "{}", value),
+            Type::TraitMethod(ref ty, Some(ref tr), ref id) => write!(f, "{}#{}::{}", ty, tr, id.name),
+            Type::TraitMethod(ref ty, None, ref id) => write!(f, "{}::{}", ty, id.name),
+            Type::Member(ref ty, ref id) => write!(f, "{}.{}", ty, id.name),
+            Type::CallEquation(ref call) => write!(f, "{}", call),
+            Type::Ref(ref ty) => write!(f, "&{}", ty),
+            Type::MutRef(ref ty) => write!(f, "&mut {}", ty),
+            Type::Deref(ref ty) => write!(f, "*{}", ty),
+            Type::AutoRef(ref ty, _) => write!(f, "{}", ty),
+            Type::Never => write!(f, "!"),
+            Type::End => write!(f, "()"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SolveChange {
     Changed,
@@ -291,8 +380,26 @@ impl std::ops::BitAndAssign for SolveChange {
 
 #[derive(Debug)]
 pub enum TypeEquation {
-    HasTrait(Type, TraitGenerics, SolveChange),
-    Equal(Type, Type, SolveChange),
+    HasTrait(Type, TraitGenerics, SolveChange, Span),
+    Equal(Type, Type, SolveChange, Span),
+    CompoundAssign(Type, Type, CompoundAssignEquation, SolveChange, Span),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompoundAssignEquation {
+    pub assign_trait: TraitId,
+    pub binary_trait: TraitId,
+    pub tag: Tag,
+}
+
+impl TypeEquation {
+    fn get_span(&self) -> Span {
+        match *self {
+            TypeEquation::HasTrait(_, _, _, span) => span,
+            TypeEquation::Equal(_, _, _, span) => span,
+            TypeEquation::CompoundAssign(_, _, _, _, span) => span,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -304,6 +411,27 @@ pub struct CallEquation {
     pub tag: Tag,
 }
 
+impl std::fmt::Display for CallEquation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.caller_type.as_ref(), self.trait_gen.as_ref()) {
+            (Some(caller), Some(tr)) => write!(f, "{}#{}::", caller, tr)?,
+            (Some(caller), None) => write!(f, "{}::", caller)?,
+            (None, _) => {}
+        }
+        write!(f, "{}({})", self.func_id.name, types_to_string(&self.args))
+    }
+}
+
+impl std::fmt::Display for TypeEquation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TypeEquation::HasTrait(ref ty, ref tr, _, _) => write!(f, "{}: {}", ty, tr),
+            TypeEquation::Equal(ref left, ref right, _, _) => write!(f, "{} == {}", left, right),
+            TypeEquation::CompoundAssign(ref left, ref right, ref comp, _, _) => write!(f, "{}: {}<{}>", left, comp.assign_trait, right),
+        }
+    }
+}
+
 impl CallEquation {
     pub fn occurs(&self, tv: &TypeVariable) -> bool {
         self.caller_type.as_ref().map_or(false, |t| t.occurs(tv))
@@ -352,8 +480,12 @@ pub struct TypeEquations {
     equs: VecDeque<TypeEquation>,
     want_solve: HashSet<TypeVariable>,
     not_void_vars: HashSet<TypeVariable>,
+    never_vars: Vec<TypeVariable>,
     substs: Vec<TypeSubst>,
     self_type: Option<Type>,
+    return_type: Option<(Type, bool)>,
+    loops: Vec<(Option<Type>, bool)>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -406,6 +538,12 @@ impl UnifyErr {
             Self::Deficiency(st) => st,
         }
     }
+    fn locate(self, span: &Span) -> Self {
+        match self {
+            Self::Contradiction(st) => Self::Contradiction(span.error_message(&st)),
+            Self::Deficiency(st) => Self::Deficiency(span.error_message(&st)),
+        }
+    }
 }
 
 impl TypeEquations {
@@ -418,8 +556,12 @@ impl TypeEquations {
             variables: Vec::new(),
             want_solve: HashSet::new(),
             not_void_vars: HashSet::new(),
+            never_vars: Vec::new(),
             substs: Vec::new(),
             self_type: None,
+            return_type: None,
+            loops: Vec::new(),
+            span: Span::empty(),
         }
     }
     pub fn debug(&self){
@@ -442,7 +584,9 @@ impl TypeEquations {
     }
     pub fn take_over_equations(&mut self, mut gen_equs: Self) {
         for elem in std::mem::replace(&mut gen_equs.want_solve, HashSet::new()).into_iter() {
-            self.want_solve.insert(elem);
+            if !self.substs.iter().any(|TypeSubst { tv, .. }| *tv == elem) {
+                self.want_solve.insert(elem);
+            }
         }
         for subst in gen_equs.substs.iter() {
             self.subst(subst);
@@ -450,7 +594,11 @@ impl TypeEquations {
         for TypeSubst { tv, .. } in gen_equs.substs.iter() {
             self.want_solve.remove(tv);
         }
+        for subst in self.substs.iter() {
+            gen_equs.subst(subst);
+        }
         self.equs.append(&mut gen_equs.equs);
+        self.never_vars.append(&mut gen_equs.never_vars);
         self.substs.append(&mut gen_equs.substs);
         self.change_cnt += gen_equs.change_cnt;
     }
@@ -472,12 +620,49 @@ impl TypeEquations {
             None => Err(format!("cant use Self")),
         }
     }
+    pub fn set_return_type(&mut self, return_type: Option<(Type, bool)>) -> Option<(Type, bool)> {
+        std::mem::replace(&mut self.return_type, return_type)
+    }
+    pub fn get_return_type(&self) -> Result<(Type, bool), String> {
+        match self.return_type.clone() {
+            Some(ret) => Ok(ret),
+            None => Err(self.span.error_message("return outside of function")),
+        }
+    }
+    pub fn into_loop(&mut self, break_type: Option<Type>) {
+        self.loops.push((break_type, false));
+    }
+    pub fn out_loop(&mut self) -> bool {
+        self.loops.pop().unwrap().1
+    }
+    pub fn get_break_type(&mut self) -> Result<Option<Type>, String> {
+        match self.loops.last_mut() {
+            Some((break_type, used)) => {
+                *used = true;
+                Ok(break_type.clone())
+            }
+            None => Err(self.span.error_message("break outside of loop")),
+        }
+    }
+    pub fn replace_loops(&mut self, loops: Vec<(Option<Type>, bool)>) -> Vec<(Option<Type>, bool)> {
+        std::mem::replace(&mut self.loops, loops)
+    }
+    pub fn in_loop(&self) -> bool {
+        !self.loops.is_empty()
+    }
+    pub fn set_span(&mut self, span: Span) -> Span {
+        std::mem::replace(&mut self.span, span)
+    }
     pub fn add_has_trait(&mut self, ty: Type, tr: TraitGenerics) {
-        self.equs.push_back(TypeEquation::HasTrait(ty, tr, SolveChange::Changed));
+        self.equs.push_back(TypeEquation::HasTrait(ty, tr, SolveChange::Changed, self.span));
+        self.change_cnt += 1;
+    }
+    pub fn add_compound_assign(&mut self, left: Type, right: Type, comp: CompoundAssignEquation) {
+        self.equs.push_back(TypeEquation::CompoundAssign(left, right, comp, SolveChange::Changed, self.span));
         self.change_cnt += 1;
     }
     pub fn add_equation(&mut self, left: Type, right: Type) {
-        self.equs.push_back(TypeEquation::Equal(left, right, SolveChange::Changed));
+        self.equs.push_back(TypeEquation::Equal(left, right, SolveChange::Changed, self.span));
         self.change_cnt += 1;
     }
     pub fn into_scope(&mut self) {
@@ -502,7 +687,7 @@ impl TypeEquations {
                 return Ok(t.clone())
             }
         }
-        Err(format!("Variable {:?} is not found", var))
+        Err(var.id.get_span().error_message(&format!("variable {} is not found", var.id.name)))
     }
     pub fn clear_equations(&mut self) {
         self.equs.clear();
@@ -514,15 +699,20 @@ impl TypeEquations {
         }
         for equation in self.equs.iter_mut() {
             match *equation {
-                TypeEquation::Equal(ref mut left, ref mut right, ref mut changed) => {
+                TypeEquation::Equal(ref mut left, ref mut right, ref mut changed, _) => {
                     *changed &= left.subst(theta);
                     *changed &= right.subst(theta);
                     self.change_cnt += changed.cnt();
                 }
-                TypeEquation::HasTrait(ref mut ty, _, ref mut changed) => {
+                TypeEquation::HasTrait(ref mut ty, _, ref mut changed, _) => {
                     *changed &= ty.subst(theta);
                     self.change_cnt += changed.cnt();
                 }
+                TypeEquation::CompoundAssign(ref mut left, ref mut right, _, ref mut changed, _) => {
+                    *changed &= left.subst(theta);
+                    *changed &= right.subst(theta);
+                    self.change_cnt += changed.cnt();
+                }
                 /* TypeEquation::Call(ref mut call) => {
                     call.subst(theta);
                 }*/
@@ -538,7 +728,9 @@ impl TypeEquations {
         let (ty, b4) = self.solve_generics(ty, trs)?;
         let (ty, b5) = self.solve_deref(ty, trs)?;
         let (ty, b6) = self.solve_autoref(ty, trs)?;
-        Ok((ty, b0 & b1 & b2 & b3 & b4 & b5 & b6))
+        let (ty, b7) = self.solve_tuple(ty, trs)?;
+        let (ty, b8) = self.solve_array(ty, trs)?;
+        Ok((ty, b0 & b1 & b2 & b3 & b4 & b5 & b6 & b7 & b8))
     }
 
     fn solve_call_equation(&mut self, ty: Type, trs: &TraitsInfo) -> Result<(Type, SolveChange), UnifyErr> {
@@ -565,10 +757,10 @@ impl TypeEquations {
                         Err(len) => {
                             if inner_ty.is_solved_type() {
                                 if len == 0 { 
-                                    Err(UnifyErr::Contradiction(format!("type {:?} is not implemented trait {:?}", inner_ty, tr)))
+                                    Err(UnifyErr::Contradiction(format!("type {} does not implement trait {}", inner_ty, tr)))
                                 }
                                 else if len > 1 {
-                                    Err(UnifyErr::Contradiction(format!("type {:?} is implemented too many trait {:?}", inner_ty, substs)))
+                                    Err(UnifyErr::Contradiction(format!("type {} matches {} implementations of trait {}", inner_ty, len, tr)))
                                 }
                                 else {
                                     unreachable!();
@@ -611,10 +803,10 @@ impl TypeEquations {
                     Err(len) => {
                         if inner_ty.is_solved_type() {
                             if len == 0 { 
-                                Err(UnifyErr::Contradiction(format!("type {:?} is not implemented trait {:?}", inner_ty, trait_gen)))
+                                Err(UnifyErr::Contradiction(format!("type {} does not implement trait {}", inner_ty, trait_gen)))
                             }
                             else if len > 1 {
-                                Err(UnifyErr::Contradiction(format!("type {:?} is implemented too many trait {:?}", inner_ty, substs)))
+                                Err(UnifyErr::Contradiction(format!("type {} matches {} implementations of trait {}", inner_ty, len, trait_gen)))
                             }
                             else {
                                 unreachable!();
@@ -643,10 +835,10 @@ impl TypeEquations {
                 }
                 else if inner_ty.is_solved_type() {
                     if substs.len() == 0 { 
-                        Err(UnifyErr::Contradiction(format!("type {:?} is not implemented function {:?}", inner_ty, method_id)))
+                        Err(UnifyErr::Contradiction(format!("type {} does not have function {}", inner_ty, method_id.name)))
                     }
                     else if substs.len() > 1 {
-                        Err(UnifyErr::Contradiction(format!("type {:?} is implemented too many trait {:?}", inner_ty, substs)))
+                        Err(UnifyErr::Contradiction(format!("function {} of type {} is found in {} traits", method_id.name, inner_ty, substs.len())))
                     }
                     else {
                         unreachable!();
@@ -674,21 +866,25 @@ impl TypeEquations {
                 self.set_self_type(before);
                 if let Type::Func(args, returns, info) = res {
                     let mut iter = args.into_iter();
-                    let self_ty = iter.next().ok_or(UnifyErr::Contradiction(format!("trait method {:?} have no argument", mem_id)))?;
+                    let self_ty = iter.next().ok_or(UnifyErr::Contradiction(format!("trait method {} has no argument", mem_id.name)))?;
                     self.add_equation(self_ty.clone(), inner_ty.clone());
                     let res = Type::Func(iter.collect(), returns, info);
                     self.solve_relations(res, trs).map(|(ty, _)| (ty, SolveChange::Changed))
                 }
                 else { unreachable!() }
             }
+            else if let Type::Tuple(ref elems) = inner_ty {
+                let res = tuple_member_type(elems, &mem_id)?;
+                self.solve_relations(res, trs).map(|(ty, _)| (ty, SolveChange::Changed))
+            }
             else if let Type::Generics(ref id, ref gens) = inner_ty {
                 match trs.search_typeid(id).map_err(|st| UnifyErr::Contradiction(st))? {
                     StructDefinitionInfo::Def(def)  => {
                         let res = def.get_member_type(self, trs, gens, &mem_id).map_err(|st| UnifyErr::Contradiction(st))?;
                         self.solve_relations(res, trs).map(|(ty, _)| (ty, SolveChange::Changed))
                     }
-                    StructDefinitionInfo::Generics  => Err(UnifyErr::Contradiction(format!("generics type has no member: {:?}", id))),
-                    StructDefinitionInfo::Primitive => Err(UnifyErr::Contradiction(format!("primitive type has no member: {:?}", id))),
+                    StructDefinitionInfo::Generics  => Err(UnifyErr::Contradiction(format!("generics type {} has no member", id))),
+                    StructDefinitionInfo::Primitive => Err(UnifyErr::Contradiction(format!("primitive type {} has no member", id))),
                 }
             }
             else if let Type::Ref(ty) = inner_ty {
@@ -698,12 +894,16 @@ impl TypeEquations {
                             let res = def.get_member_type(self, trs, gens, &mem_id).map_err(|st| UnifyErr::Contradiction(st))?;
                             self.solve_relations(res, trs).map(|(ty, _)| (ty, SolveChange::Changed))
                         }
-                        StructDefinitionInfo::Generics  => Err(UnifyErr::Contradiction(format!("generics type has no member: {:?}", id))),
-                        StructDefinitionInfo::Primitive => Err(UnifyErr::Contradiction(format!("primitive type has no member: {:?}", id))),
+                        StructDefinitionInfo::Generics  => Err(UnifyErr::Contradiction(format!("generics type {} has no member", id))),
+                        StructDefinitionInfo::Primitive => Err(UnifyErr::Contradiction(format!("primitive type {} has no member", id))),
                     }
                 }
+                else if let Type::Tuple(ref elems) = ty.as_ref() {
+                    let res = tuple_member_type(elems, &mem_id)?;
+                    self.solve_relations(res, trs).map(|(ty, _)| (ty, SolveChange::Changed))
+                }
                 else {
-                    Err(UnifyErr::Contradiction(format!("cant solve member ref({:?})", ty)))
+                    Err(UnifyErr::Contradiction(format!("cant solve member of {}", ty)))
                 }
             }
             else if let Type::MutRef(ty) = inner_ty {
@@ -713,19 +913,23 @@ impl TypeEquations {
                             let res = def.get_member_type(self, trs, gens, &mem_id).map_err(|st| UnifyErr::Contradiction(st))?;
                             self.solve_relations(res, trs).map(|(ty, _)| (ty, SolveChange::Changed))
                         }
-                        StructDefinitionInfo::Generics  => Err(UnifyErr::Contradiction(format!("generics type has no member: {:?}", id))),
-                        StructDefinitionInfo::Primitive => Err(UnifyErr::Contradiction(format!("primitive type has no member: {:?}", id))),
+                        StructDefinitionInfo::Generics  => Err(UnifyErr::Contradiction(format!("generics type {} has no member", id))),
+                        StructDefinitionInfo::Primitive => Err(UnifyErr::Contradiction(format!("primitive type {} has no member", id))),
                     }
                 }
+                else if let Type::Tuple(ref elems) = ty.as_ref() {
+                    let res = tuple_member_type(elems, &mem_id)?;
+                    self.solve_relations(res, trs).map(|(ty, _)| (ty, SolveChange::Changed))
+                }
                 else {
-                    Err(UnifyErr::Contradiction(format!("cant solve member mutref({:?})", ty)))
+                    Err(UnifyErr::Contradiction(format!("cant solve member of {}", ty)))
                 }
             }
             else if let Type::SolvedAssociatedType(_, _, _) = inner_ty {
-                Err(UnifyErr::Contradiction(format!("SolvedAssociatedType has no member: {:?}", inner_ty)))
+                Err(UnifyErr::Contradiction(format!("associated type {} has no member", inner_ty)))
             }
             else if inner_ty.is_solved_type() {
-                Err(UnifyErr::Contradiction(format!("{:?} cant solve member {:?}", inner_ty, mem_id)))
+                Err(UnifyErr::Contradiction(format!("type {} has no member {}", inner_ty, mem_id.name)))
             }
             else {
                 Ok((Type::Member(Box::new(inner_ty), mem_id), inner_changed))
@@ -748,6 +952,28 @@ impl TypeEquations {
         }
     }
 
+    fn solve_tuple(&mut self, ty: Type, trs: &TraitsInfo) -> Result<(Type, SolveChange), UnifyErr> {
+        if let Type::Tuple(elems) = ty {
+            let try_solve = elems.into_iter().map(|elem| self.solve_relations(elem, trs)).collect::<Result<Vec<_>, _>>()?;
+            let inner_changed = try_solve.iter().map(|(_, changed)| *changed).fold(SolveChange::Not, |b, c| b & c);
+            Ok((Type::Tuple(try_solve.into_iter().map(|(ty, _)| ty).collect()), inner_changed))
+        }
+        else {
+            Ok((ty, SolveChange::Not))
+        }
+    }
+
+    fn solve_array(&mut self, ty: Type, trs: &TraitsInfo) -> Result<(Type, SolveChange), UnifyErr> {
+        if let Type::Array(elem, len) = ty {
+            let (elem, elem_changed) = self.solve_relations(*elem, trs)?;
+            let (len, len_changed) = self.solve_relations(*len, trs)?;
+            Ok((Type::Array(Box::new(elem), Box::new(len)), elem_changed & len_changed))
+        }
+        else {
+            Ok((ty, SolveChange::Not))
+        }
+    }
+
     fn solve_deref(&mut self, ty: Type, trs: &TraitsInfo) -> Result<(Type, SolveChange), UnifyErr> {
         if let Type::Deref(ty) = ty {
             let (ty, inner_change) = self.solve_relations(*ty, trs)?;
@@ -772,196 +998,274 @@ impl TypeEquations {
         }
     }
 
-    pub fn unify(&mut self, trs: &TraitsInfo) -> Result<(), UnifyErr> {
-        /* log::debug!("unify");
-        for (i, equ) in self.equs.iter().enumerate() {
-            log::debug!("{}. {:?}", i, equ);
-        } */
-        while let Some(equation) = self.equs.pop_front() {
-            match equation {
-                TypeEquation::HasTrait(left, tr, before_changed) => {
-                    self.change_cnt -= before_changed.cnt();
-                    let (left, left_changed) = self.solve_relations(left, trs)?;
-                    if left.is_solved_type() {
-                        let solve_cnt = self.solve_has_trait(&left, &tr, trs);
-                        if solve_cnt == 0 {
-                            Err(UnifyErr::Contradiction(format!("type {:?} is not implemented trait {:?}", left, tr)))?;
-                        }
-                        else if solve_cnt > 1{
-                            Err(UnifyErr::Contradiction(format!("type {:?} is too many implemented traits {:?}", left, tr)))?;
-                        }
+    fn unify_equation(&mut self, equation: TypeEquation, trs: &TraitsInfo) -> Result<(), UnifyErr> {
+        match equation {
+            TypeEquation::HasTrait(left, tr, before_changed, _) => {
+                self.change_cnt -= before_changed.cnt();
+                let (left, left_changed) = self.solve_relations(left, trs)?;
+                if left.is_solved_type() {
+                    let solve_cnt = self.solve_has_trait(&left, &tr, trs);
+                    if solve_cnt == 0 {
+                        Err(UnifyErr::Contradiction(format!("type {} does not implement trait {}", left, tr)))?;
+                    }
+                    else if solve_cnt > 1{
+                        Err(UnifyErr::Contradiction(format!("type {} matches {} implementations of trait {}", left, solve_cnt, tr)))?;
+                    }
+                }
+                else {
+                    self.equs.push_back(TypeEquation::HasTrait(left, tr, left_changed, self.span));
+                    self.change_cnt += left_changed.cnt();
+                }
+            }
+            TypeEquation::CompoundAssign(left, right, comp, before_changed, _) => {
+                self.change_cnt -= before_changed.cnt();
+                let (left, left_changed) = self.solve_relations(left, trs)?;
+                let (right, right_changed) = self.solve_relations(right, trs)?;
+                if left.is_solved_type() && right.is_solved_type() {
+                    let assign_gen = TraitGenerics { trait_id: comp.assign_trait.clone(), generics: vec![right.clone()] };
+                    let binary_gen = TraitGenerics { trait_id: comp.binary_trait.clone(), generics: vec![right.clone()] };
+                    if self.solve_has_trait(&left, &assign_gen, trs) == 1 {
+                        let alpha = comp.tag.generate_type_variable("CompoundAssignTrait", 0, self);
+                        self.add_equation(alpha, left);
+                    }
+                    else if self.solve_has_trait(&left, &binary_gen, trs) == 1 {
+                        let (method, _) = find_binary_operator(&comp.binary_trait.id.into_string()).unwrap();
+                        let call = Type::CallEquation(CallEquation {
+                            caller_type: Some(Box::new(left.clone())),
+                            trait_gen: Some(binary_gen),
+                            func_id: Identifier::from_str(method),
+                            args: vec![left.clone(), right],
+                            tag: Tag::new(),
+                        });
+                        let alpha = comp.tag.generate_type_variable("CompoundAssignTrait", 1, self);
+                        self.add_equation(alpha, left.clone());
+                        self.add_equation(left, call);
                     }
                     else {
-                        self.equs.push_back(TypeEquation::HasTrait(left, tr, left_changed));
-                        self.change_cnt += left_changed.cnt();
+                        Err(UnifyErr::Contradiction(format!("type {} implements neither {} nor {}", left, comp.assign_trait, comp.binary_trait)))?;
                     }
                 }
-                TypeEquation::Equal(left, right, before_changed) => {
-                    self.change_cnt -= before_changed.cnt();
-                    let (left, left_changed) = self.solve_relations(left, trs)?;
-                    let (right, right_changed) = self.solve_relations(right, trs)?;
+                else {
                     let changed = left_changed & right_changed;
-                    match (left, right) {
-                        (l, r) if l == r => {}
-                        (Type::AssociatedType(a, b, c), right) => {
-                            self.equs.push_back(TypeEquation::Equal(Type::AssociatedType(a, b, c), right, changed));
-                            self.change_cnt += changed.cnt();
-                        }
-                        (left, Type::AssociatedType(a, b, c)) => {
-                            self.equs.push_back(TypeEquation::Equal(left, Type::AssociatedType(a, b, c), changed));
-                            self.change_cnt += changed.cnt();
-                        }
-                        (Type::TraitMethod(a, b, c), right) => {
-                            self.equs.push_back(TypeEquation::Equal(Type::TraitMethod(a, b, c), right, changed));
-                            self.change_cnt += changed.cnt();
-                        }
-                        (left, Type::TraitMethod(a, b, c)) => {
-                            self.equs.push_back(TypeEquation::Equal(left, Type::TraitMethod(a, b, c), changed));
-                            self.change_cnt += changed.cnt();
+                    self.equs.push_back(TypeEquation::CompoundAssign(left, right, comp, changed, self.span));
+                    self.change_cnt += changed.cnt();
+                }
+            }
+            TypeEquation::Equal(left, right, before_changed, _) => {
+                self.change_cnt -= before_changed.cnt();
+                let (left, left_changed) = self.solve_relations(left, trs)?;
+                let (right, right_changed) = self.solve_relations(right, trs)?;
+                let changed = left_changed & right_changed;
+                match (left, right) {
+                    (l, r) if l == r => {}
+                    (Type::AssociatedType(a, b, c), right) => {
+                        self.equs.push_back(TypeEquation::Equal(Type::AssociatedType(a, b, c), right, changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (left, Type::AssociatedType(a, b, c)) => {
+                        self.equs.push_back(TypeEquation::Equal(left, Type::AssociatedType(a, b, c), changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (Type::TraitMethod(a, b, c), right) => {
+                        self.equs.push_back(TypeEquation::Equal(Type::TraitMethod(a, b, c), right, changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (left, Type::TraitMethod(a, b, c)) => {
+                        self.equs.push_back(TypeEquation::Equal(left, Type::TraitMethod(a, b, c), changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (Type::Member(b, a), right) => {
+                        self.equs.push_back(TypeEquation::Equal(Type::Member(b, a), right, changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (left, Type::Member(b, a)) => {
+                        self.equs.push_back(TypeEquation::Equal(left, Type::Member(b, a), changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (Type::CallEquation(call), right) => {
+                        self.equs.push_back(TypeEquation::Equal(Type::CallEquation(call), right, changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (left, Type::CallEquation(call)) => {
+                        self.equs.push_back(TypeEquation::Equal(left, Type::CallEquation(call), changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (Type::Deref(ty), right) => {
+                        self.equs.push_back(TypeEquation::Equal(Type::Deref(ty), right, changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (left, Type::Deref(ty)) => {
+                        self.equs.push_back(TypeEquation::Equal(left, Type::Deref(ty), changed, self.span));
+                        self.change_cnt += changed.cnt();
+                    }
+                    (Type::TypeVariable(var), Type::Never) | (Type::Never, Type::TypeVariable(var)) => {
+                        self.never_vars.push(var);
+                    }
+                    (Type::Never, _) | (_, Type::Never) => {}
+                    (left, Type::AutoRef(ty, AutoRefTag::Tag(tag))) | (Type::AutoRef(ty, AutoRefTag::Tag(tag)), left) => {
+                        let (ty, ty_changed) = self.solve_relations(*ty, trs)?;
+                        let mut oks = vec![
+                                (AutoRefTag::Nothing, ty.clone()),
+                                (AutoRefTag::Ref, Type::Ref(Box::new(ty.clone()))),
+                                (AutoRefTag::MutRef, Type::MutRef(Box::new(ty.clone()))),
+                        ].into_iter()
+                            .map(|(ref_tag, right)| {
+                                let mut tmp_equs = TypeEquations::new();
+                                tmp_equs.add_equation(left.clone(), right);
+                                (ref_tag, tmp_equs)
+                        }).filter_map(
+                            |(ref_tag, mut tmp_equs)| match tmp_equs.unify(trs) {
+                                Err(UnifyErr::Contradiction(_)) => None,
+                                _ => Some((ref_tag, tmp_equs)),
+                            }
+                        ).collect::<Vec<_>>();
+                        //log::debug!("--------------------");
+                        //log::debug!("AUTOREF {:?} : {:?} {:?}", left, ty, tag);
+                        //log::debug!("oks = {:?}", oks);
+                        if oks.len() == 0 {
+                            Err(UnifyErr::Contradiction(format!("type {} is not equal to {} or a reference to it", left, ty)))?;
                         }
-                        (Type::Member(b, a), right) => {
-                            self.equs.push_back(TypeEquation::Equal(Type::Member(b, a), right, changed));
-                            self.change_cnt += changed.cnt();
+                        if oks.len() == 1 {
+                            //log::debug!("OK");
+                            //log::debug!("--------------------");
+                            let (ref_tag, tmp_equs) = oks.pop().unwrap();
+                            self.take_over_equations(tmp_equs);
+                            let var = tag.generate_type_variable("AutoRefType", 0, self);
+                            self.add_equation(var, Type::AutoRef(Box::new(ty), ref_tag));
                         }
-                        (left, Type::Member(b, a)) => {
-                            self.equs.push_back(TypeEquation::Equal(left, Type::Member(b, a), changed));
-                            self.change_cnt += changed.cnt();
+                        else {
+                            //log::debug!("NG");
+                            //log::debug!("--------------------");
+                            self.equs.push_back(TypeEquation::Equal(left, Type::AutoRef(Box::new(ty), AutoRefTag::Tag(tag)), changed & ty_changed, self.span));
                         }
-                        (Type::CallEquation(call), right) => {
-                            self.equs.push_back(TypeEquation::Equal(Type::CallEquation(call), right, changed));
-                            self.change_cnt += changed.cnt();
+                    }
+                    (Type::Func(l_args, l_return, _), Type::Func(r_args, r_return, _)) => {
+                        if l_args.len() != r_args.len() {
+                            Err(UnifyErr::Deficiency(format!("length of args is not equal. {:?}, {:?} vs {:?}, {:?}",
+                                        l_args, l_return, r_args, r_return
+                                        )))?;
                         }
-                        (left, Type::CallEquation(call)) => {
-                            self.equs.push_back(TypeEquation::Equal(left, Type::CallEquation(call), changed));
-                            self.change_cnt += changed.cnt();
+                        for (l, r) in l_args.into_iter().zip(r_args.into_iter()) {
+                            self.add_equation(l, r);
                         }
-                        (Type::Deref(ty), right) => {
-                            self.equs.push_back(TypeEquation::Equal(Type::Deref(ty), right, changed));
-                            self.change_cnt += changed.cnt();
+                        self.add_equation(*l_return, *r_return);
+                    }
+                    (Type::Generics(id, gens), Type::Func(args, ret, info)) | (Type::Func(args, ret, info), Type::Generics(id, gens)) => {
+                        let generics = Type::Generics(id, gens);
+                        match trs.search_fn_bound(&generics) {
+                            Some(bound) => self.add_equation(bound, Type::Func(args, ret, info)),
+                            None => Err(UnifyErr::Contradiction(format!("type {} is not a function", generics)))?,
                         }
-                        (left, Type::Deref(ty)) => {
-                            self.equs.push_back(TypeEquation::Equal(left, Type::Deref(ty), changed));
-                            self.change_cnt += changed.cnt();
+                    }
+                    (Type::Generics(l_id, l_gens), Type::Generics(r_id, r_gens)) => {
+                        if l_id != r_id {
+                            Err(UnifyErr::Contradiction(format!("mismatched types {} and {}", l_id, r_id)))?;
                         }
-                        (left, Type::AutoRef(ty, AutoRefTag::Tag(tag))) | (Type::AutoRef(ty, AutoRefTag::Tag(tag)), left) => {
-                            let (ty, ty_changed) = self.solve_relations(*ty, trs)?;
-                            let mut oks = vec![
-                                    (AutoRefTag::Nothing, ty.clone()),
-                                    (AutoRefTag::Ref, Type::Ref(Box::new(ty.clone()))),
-                                    (AutoRefTag::MutRef, Type::MutRef(Box::new(ty.clone()))),
-                            ].into_iter()
-                                .map(|(ref_tag, right)| {
-                                    let mut tmp_equs = TypeEquations::new();
-                                    tmp_equs.add_equation(left.clone(), right);
-                                    (ref_tag, tmp_equs)
-                            }).filter_map(
-                                |(ref_tag, mut tmp_equs)| match tmp_equs.unify(trs) {
-                                    Err(UnifyErr::Contradiction(_)) => None,
-                                    _ => Some((ref_tag, tmp_equs)),
-                                }
-                            ).collect::<Vec<_>>();
-                            //log::debug!("--------------------");
-                            //log::debug!("AUTOREF {:?} : {:?} {:?}", left, ty, tag);
-                            //log::debug!("oks = {:?}", oks);
-                            if oks.len() == 0 {
-                                Err(UnifyErr::Contradiction(format!("not equal {:?}, auto ref {:?}", left, ty)))?;
-                            }
-                            if oks.len() == 1 {
-                                //log::debug!("OK");
-                                //log::debug!("--------------------");
-                                let (ref_tag, tmp_equs) = oks.pop().unwrap();
-                                self.take_over_equations(tmp_equs);
-                                let var = tag.generate_type_variable("AutoRefType", 0, self);
-                                self.add_equation(var, Type::AutoRef(Box::new(ty), ref_tag));
-                            }
-                            else {
-                                //log::debug!("NG");
-                                //log::debug!("--------------------");
-                                self.equs.push_back(TypeEquation::Equal(left, Type::AutoRef(Box::new(ty), AutoRefTag::Tag(tag)), changed & ty_changed));
-                            }
+                        else if l_gens.len() != r_gens.len() {
+                            Err(UnifyErr::Contradiction(format!("unreachable, generics lengths are checked")))?;
                         }
-                        (Type::Func(l_args, l_return, _), Type::Func(r_args, r_return, _)) => {
-                            if l_args.len() != r_args.len() {
-                                Err(UnifyErr::Deficiency(format!("length of args is not equal. {:?}, {:?} vs {:?}, {:?}",
-                                            l_args, l_return, r_args, r_return
-                                            )))?;
-                            }
-                            for (l, r) in l_args.into_iter().zip(r_args.into_iter()) {
+                        else {
+                            for (l, r) in l_gens.into_iter().zip(r_gens.into_iter()) {
                                 self.add_equation(l, r);
                             }
-                            self.add_equation(*l_return, *r_return);
-                        }
-                        (Type::Generics(l_id, l_gens), Type::Generics(r_id, r_gens)) => {
-                            if l_id != r_id {
-                                Err(UnifyErr::Contradiction(format!("generics type id is not equal. {:?} != {:?}", l_id, r_id)))?;
-                            }
-                            else if l_gens.len() != r_gens.len() {
-                                Err(UnifyErr::Contradiction(format!("unreachable, generics lengths are checked")))?;
-                            }
-                            else {
-                                for (l, r) in l_gens.into_iter().zip(r_gens.into_iter()) {
-                                    self.add_equation(l, r);
-                                }
-                            }
                         }
-                        (Type::Ref(l_ty), Type::Ref(r_ty)) => {
-                            self.add_equation(*l_ty, *r_ty);
+                    }
+                    (Type::Tuple(l_elems), Type::Tuple(r_elems)) => {
+                        if l_elems.len() != r_elems.len() {
+                            Err(UnifyErr::Contradiction(format!("length of tuple is not equal. ({}) vs ({})", types_to_string(&l_elems), types_to_string(&r_elems))))?;
                         }
-                        (Type::MutRef(l_ty), Type::MutRef(r_ty)) => {
-                            self.add_equation(*l_ty, *r_ty);
+                        for (l, r) in l_elems.into_iter().zip(r_elems).rev() {
+                            self.equs.push_front(TypeEquation::Equal(l, r, SolveChange::Changed, self.span));
+                            self.change_cnt += 1;
                         }
-                        (Type::TypeVariable(lv), rt) if self.remove_want_solve(&lv) => {
-                            if rt.occurs(&lv) {
-                                Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
-                            }
-                            let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
-                            self.subst(&th);
-                            self.substs.push(th);
+                    }
+                    (Type::Array(l_elem, l_len), Type::Array(r_elem, r_len)) => {
+                        self.add_equation(*l_elem, *r_elem);
+                        self.add_equation(*l_len, *r_len);
+                    }
+                    (Type::Ref(l_ty), Type::Ref(r_ty)) => {
+                        self.add_equation(*l_ty, *r_ty);
+                    }
+                    (Type::MutRef(l_ty), Type::MutRef(r_ty)) => {
+                        self.add_equation(*l_ty, *r_ty);
+                    }
+                    (Type::TypeVariable(lv), rt) if self.remove_want_solve(&lv) => {
+                        if rt.occurs(&lv) {
+                            Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
                         }
-                        (rt, Type::TypeVariable(lv)) if self.remove_want_solve(&lv) => {
-                            if rt.occurs(&lv) {
-                                Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
-                            }
-                            let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
-                            self.subst(&th);
-                            self.substs.push(th);
+                        let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
+                        self.subst(&th);
+                        self.substs.push(th);
+                    }
+                    (rt, Type::TypeVariable(lv)) if self.remove_want_solve(&lv) => {
+                        if rt.occurs(&lv) {
+                            Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
                         }
-                        (Type::TypeVariable(lv), rt) => {
-                            if rt.occurs(&lv) {
-                                Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
-                            }
-                            let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
-                            self.subst(&th);
-                            self.substs.push(th);
+                        let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
+                        self.subst(&th);
+                        self.substs.push(th);
+                    }
+                    (Type::TypeVariable(lv), rt) => {
+                        if rt.occurs(&lv) {
+                            Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
                         }
-                        (rt, Type::TypeVariable(lv)) => {
-                            if rt.occurs(&lv) {
-                                Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
-                            }
-                            let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
-                            self.subst(&th);
-                            self.substs.push(th);
+                        let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
+                        self.subst(&th);
+                        self.substs.push(th);
+                    }
+                    (rt, Type::TypeVariable(lv)) => {
+                        if rt.occurs(&lv) {
+                            Err(UnifyErr::Contradiction(format!("unification failed, occurs")))?;
                         }
-                        /*(Type::TypeVariable(_), Type::TypeVariable(_)) => {
-                            let all_not_want = self.equs.iter().map(|equ| match equ {
-                                TypeEquation::Equal(Type::TypeVariable(l), Type::TypeVariable(r), _)
-                                    if !self.want_solve.contains(l) && !self.want_solve.contains(r) => {
-                                        true
-                                    }
-                                _ => false,
-                            }).all(|f| f);
-                            if all_not_want {
-                                return Err(UnifyErr::Deficiency(format!("all not want solve variable {:?}", self.equs)))
-                            }
-                        }*/
-                        (l, r) => {
-                            Err(UnifyErr::Contradiction(format!("unfication failed, {:?} != {:?}", l, r)))?
+                        let th = TypeSubst { tv: lv.clone(), t: rt.clone() };
+                        self.subst(&th);
+                        self.substs.push(th);
+                    }
+                    /*(Type::TypeVariable(_), Type::TypeVariable(_)) => {
+                        let all_not_want = self.equs.iter().map(|equ| match equ {
+                            TypeEquation::Equal(Type::TypeVariable(l), Type::TypeVariable(r), _)
+                                if !self.want_solve.contains(l) && !self.want_solve.contains(r) => {
+                                    true
+                                }
+                            _ => false,
+                        }).all(|f| f);
+                        if all_not_want {
+                            return Err(UnifyErr::Deficiency(format!("all not want solve variable {:?}", self.equs)))
                         }
+                    }*/
+                    (l, r) => {
+                        Err(UnifyErr::Contradiction(format!("mismatched types {} and {}", l, r)))?
                     }
                 }
             }
+        }
+        Ok(())
+    }
+
+    pub fn unify(&mut self, trs: &TraitsInfo) -> Result<(), UnifyErr> {
+        /* log::debug!("unify");
+        for (i, equ) in self.equs.iter().enumerate() {
+            log::debug!("{}. {:?}", i, equ);
+        } */
+        while let Some(equation) = self.equs.pop_front() {
+            let span = equation.get_span();
+            let before = self.set_span(span);
+            let result = self.unify_equation(equation, trs);
+            self.set_span(before);
+            result.map_err(|err| err.locate(&span))?;
             if self.change_cnt == 0 && self.equs.len() > 0 {
-                return Err(UnifyErr::Deficiency(format!("change cnt = 0 \n{}", self.equs.iter().map(|equ| format!("{:?}", equ)).collect::<Vec<_>>().join("\n"))));
+                let err = UnifyErr::Deficiency(format!("cannot solve type equations\n{}", self.equs.iter().map(|equ| format!("{}", equ)).collect::<Vec<_>>().join("\n")));
+                return Err(err.locate(&self.equs[0].get_span()));
+            }
+        }
+
+        for var in std::mem::take(&mut self.never_vars) {
+            if let Type::TypeVariable(var) = self.try_get_substs(var) {
+                if self.remove_want_solve(&var) {
+                    let th = TypeSubst { tv: var, t: Type::Never };
+                    self.subst(&th);
+                    self.substs.push(th);
+                }
             }
         }
 
@@ -973,7 +1277,7 @@ impl TypeEquations {
             Err(UnifyErr::Deficiency(format!("want_solve {:?} cant solve now", self.want_solve)))
         }
         else if voids.len() > 0 {
-            Err(UnifyErr::Contradiction(format!("voids appear {:?}", voids)))
+            Err(UnifyErr::Contradiction(format!("variables of type void appear {}", voids.len())))
         }
         else {
             Ok(())
@@ -1017,3 +1321,11 @@ fn unify_test2() {
     //equs.add_equation(Type::Type(TypeSpec::from_id(&TypeId::from_str("i64"))), Type::Member(Box::new(t), Identifier::from_str("x")));
     log::debug!("{:?}", equs.unify(&trs));
 }*/
+
+#[test]
+fn type_display_test() {
+    let pair = Type::Generics(TypeId::from_str("Pair"), vec![Type::from_str("i64"), Type::Array(Box::new(Type::from_str("u64")), Box::new(Type::Const(3)))]);
+    assert_eq!(pair.to_string(), "Pair<i64, [u64; 3]>");
+    let tr = TraitGenerics { trait_id: TraitId::from_str("Add"), generics: vec![Type::Ref(Box::new(Type::Tuple(vec![Type::from_str("bool"), Type::from_str("char")])))] };
+    assert_eq!(tr.to_string(), "Add<&(bool, char)>");
+}
@@ -1,5 +1,6 @@
 use std::collections::{ HashSet, HashMap };
 
+use nom::branch::*;
 use nom::bytes::complete::*;
 use nom::character::complete::*;
 use nom::combinator::*;
@@ -7,11 +8,13 @@ use nom::multi::*;
 use nom::sequence::*;
 use nom::IResult;
 
+use crate::comment::{ multispace0, multispace1 };
 use crate::type_spec::*;
 use crate::traits::*;
 
 use crate::unify::*;
 use crate::trans::*;
+use crate::module::{ ModuleScope, Resolve };
 
 #[derive(Debug, Clone)]
 pub struct WhereSection {
@@ -25,9 +28,27 @@ impl WhereSection {
     pub fn is_empty(&self) -> bool {
         self.has_traits.is_empty()
     }
+    pub fn add_supertraits(&mut self, supertraits: Vec<TraitSpec>) {
+        let supers = supertraits.into_iter().map(|tr| (TypeSpec::from_str("Self"), 0, tr, Vec::new()));
+        self.has_traits.splice(0..0, supers);
+    }
+    pub fn append(&mut self, mut other: WhereSection) {
+        self.has_traits.append(&mut other.has_traits);
+    }
+    pub fn self_supertraits(&self) -> impl Iterator<Item=&TraitSpec> {
+        let self_spec = TypeSpec::from_str("Self");
+        self.has_traits.iter()
+            .filter(move |(spec, _, tr, _)| *spec == self_spec && !is_fn_bound(tr))
+            .map(|(_, _, tr, _)| tr)
+    }
     pub fn regist_equations(&self, mp: &GenericsTypeMap, equs: &mut TypeEquations, trs: &TraitsInfo) -> Result<(), String> {
         for (spec, _, tr_spec, asso_eqs) in self.has_traits.iter() {
             let ty = spec.generics_to_type(mp, equs, trs)?;
+            if is_fn_bound(tr_spec) {
+                let tys = tr_spec.generics.iter().map(|g| g.generics_to_type(mp, equs, trs)).collect::<Result<Vec<_>, String>>()?;
+                equs.add_equation(ty, fn_bound_to_type(tys));
+                continue;
+            }
             let tr_gen = tr_spec.generate_trait_generics(equs, trs, mp)?;
             equs.add_has_trait(ty.clone(), tr_gen.clone());
             for (asso_id, asso_spec) in asso_eqs.iter() {
@@ -41,7 +62,12 @@ impl WhereSection {
 
     pub fn regist_candidate(&self, equs: &TypeEquations, trs: &mut TraitsInfo) -> Result<(), String> {
         for (spec, _, tr_spec, asso_eqs) in self.has_traits.iter() {
-
+            if is_fn_bound(tr_spec) {
+                let param_ty = spec.generate_type_no_auto_generics(equs, trs)?;
+                let tys = tr_spec.generics.iter().map(|g| g.generate_type_no_auto_generics(equs, trs)).collect::<Result<Vec<_>, String>>()?;
+                trs.regist_fn_bound(param_ty, fn_bound_to_type(tys));
+                continue;
+            }
 
             let mut tmp_equs = TypeEquations::new();
 
@@ -79,7 +105,31 @@ impl WhereSection {
     pub fn transpile(&self, ta: &TypeAnnotation) -> String {
         let mut conds = Vec::new();
         let bin_opes = BINARY_OPERATOR_TRAITS.iter().cloned().collect::<HashMap<_, _>>();
+        let unary_opes = UNARY_OPERATOR_TRAITS.iter().cloned().collect::<HashMap<_, _>>();
+        let comparison_opes = COMPARISON_OPERATOR_TRAITS.iter().cloned().collect::<HashMap<_, _>>();
         for (ty, _, tr, assos) in self.has_traits.iter() {
+            if is_fn_bound(tr) {
+                let (ret, args) = tr.generics.split_last().unwrap();
+                let args = args.iter().map(|arg| format!(", {}", arg.transpile(ta))).collect::<Vec<_>>().join("");
+                conds.push(format!("std::is_invocable_r<{}, {}{}>", ret.transpile(ta), ty.transpile(ta), args));
+                continue;
+            }
+            if let Some((_, ope)) = comparison_opes.get(tr.trait_id.id.into_string().as_str()) {
+                conds.push(format!("decltype(std::declval<{}>() {} std::declval<{}>(), std::true_type())", ty.transpile(ta), ope, ty.transpile(ta)));
+                continue;
+            }
+            if let Some((_, ope)) = unary_opes.get(tr.trait_id.id.into_string().as_str()) {
+                let assos = assos.iter().map(|(id, asso_ty)| (id.id.into_string(), asso_ty.clone())).collect::<HashMap<_, _>>();
+                match assos.get("Output") {
+                    Some(output_ty) => {
+                        conds.push(format!("std::is_same<decltype({}std::declval<{}>()), {}>", ope, ty.transpile(ta), output_ty.transpile(ta)));
+                    }
+                    None => {
+                        conds.push(format!("decltype({}std::declval<{}>(), std::true_type())", ope, ty.transpile(ta)));
+                    }
+                }
+                continue;
+            }
             match bin_opes.get(tr.trait_id.id.into_string().as_str()) {
                 Some((_, ope)) => {
                     let assos = assos.iter().map(|(id, asso_ty)| (id.id.into_string(), asso_ty.clone())).collect::<HashMap<_, _>>();
@@ -115,6 +165,17 @@ impl WhereSection {
     }
 }
 
+impl Resolve for WhereSection {
+    fn resolve(&mut self, scope: &ModuleScope) -> Result<(), String> {
+        for (spec, _, tr_spec, assos) in self.has_traits.iter_mut() {
+            spec.resolve(scope)?;
+            tr_spec.resolve(scope)?;
+            assos.iter_mut().try_for_each(|(_, spec)| spec.resolve(scope))?;
+        }
+        Ok(())
+    }
+}
+
 fn parse_associated_type_specifier_elem(s: &str) -> IResult<&str, (AssociatedTypeIdentifier, TypeSpec)> {
     let (s, (id, _, _, _, spec)) = tuple((parse_associated_type_identifier, multispace0, char('='), multispace0, parse_type_spec))(s)?;
     Ok((s, (id, spec)))
@@ -129,8 +190,33 @@ fn parse_associated_type_specifiers(s: &str) -> IResult<&str, Vec<(AssociatedTyp
     Ok((s, res))
 }
 
+fn is_fn_bound(tr_spec: &TraitSpec) -> bool {
+    tr_spec.trait_id == TraitId::from_str("Fn")
+}
+
+fn fn_bound_to_type(mut tys: Vec<Type>) -> Type {
+    let ret = tys.pop().unwrap();
+    Type::Func(tys, Box::new(ret), FuncTypeInfo::None)
+}
+
+fn parse_fn_bound(s: &str) -> IResult<&str, TraitSpec> {
+    let (s, (trait_id, _, _, _, args, _, _, _, ret)) = tuple((
+            parse_trait_id, multispace0, char('('), multispace0,
+            separated_list0(tuple((multispace0, char(','), multispace0)), parse_type_spec),
+            multispace0, opt(char(',')), char(')'),
+            opt(tuple((multispace0, tag("->"), multispace0, parse_type_spec)))))(s)?;
+    if trait_id != TraitId::from_str("Fn") {
+        return Err(nom::Err::Error(nom::error::Error::new(s, nom::error::ErrorKind::Tag)));
+    }
+    let ret = ret.map_or(TypeSpec::from_str("void"), |(_, _, _, ret)| ret);
+    let generics = args.into_iter().chain(std::iter::once(ret)).collect();
+    Ok((s, TraitSpec { trait_id, generics }))
+}
+
 fn parse_has_trait_element(s: &str) -> IResult<&str, (TypeSpec, usize, TraitSpec, Vec<(AssociatedTypeIdentifier, TypeSpec)>)> {
-    let (s, (spec, _, _, _, tr_id, _, assos)) = tuple((parse_type_spec, multispace0, char(':'), multispace0, parse_trait_spec, multispace0, parse_associated_type_specifiers))(s)?;
+    let (s, (spec, _, _, _, (tr_id, assos))) = tuple((parse_type_spec, multispace0, char(':'), multispace0,
+            alt((map(parse_fn_bound, |tr_id| (tr_id, Vec::new())),
+                 map(tuple((parse_trait_spec, multispace0, parse_associated_type_specifiers)), |(tr_id, _, assos)| (tr_id, assos))))))(s)?;
     let dep = spec.associated_type_depth();
     Ok((s, (spec, dep, tr_id, assos)))
 }
@@ -138,7 +224,7 @@ fn parse_has_trait_element(s: &str) -> IResult<&str, (TypeSpec, usize, TraitSpec
 pub fn parse_where_section(s: &str) -> IResult<&str, WhereSection> {
     let (s, op) = opt(
         tuple((
-                tag("where"), space1,
+                tag("where"), multispace1,
                 separated_list0(tuple((multispace0, char(','), multispace0)), parse_has_trait_element),
                 opt(tuple((multispace0, char(','))))
                 ))
@@ -154,4 +240,5 @@ pub fn parse_where_section(s: &str) -> IResult<&str, WhereSection> {
 fn parse_where_section_test() {
     log::debug!("{:?}", parse_where_section("where T: Add, T#Hoge::Output: Add, T#Hoge::Output=i64"));
     log::debug!("{:?}", parse_where_section("where S: Add(Output=T)"));
+    log::debug!("{:?}", parse_where_section("where F: Fn(i64, i64) -> bool, G: Fn()"));
 }